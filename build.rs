@@ -12,15 +12,15 @@
  * under the License.                                                         * 
  ******************************************************************************/
 
-#[cfg(feature = "bindgen")]
+#[cfg(feature = "netsnmp")]
 extern crate bindgen;
 
-#[cfg(feature = "bindgen")]
+#[cfg(feature = "netsnmp")]
 use std::env;
-#[cfg(feature = "bindgen")]
+#[cfg(feature = "netsnmp")]
 use std::path::PathBuf;
 
-#[cfg(feature = "bindgen")]
+#[cfg(feature = "netsnmp")]
 fn main() {
     let lib_paths = &[
         PathBuf::from("/usr/lib"),
@@ -71,6 +71,7 @@ fn main() {
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .opaque_type("timex") /* Problematic for now, because it contains
         a slice longer than 32. Remove when generic trait derivation lands. */
+        .header_contents("netsnmp_free.h", "#include <stdlib.h>") /* free() */
         .header(format!("{}/net-snmp/net-snmp-config.h", include_path))
         .header(format!("{}/net-snmp/net-snmp-includes.h", include_path))
         .header(format!("{}/net-snmp/library/large_fd_set.h", include_path));
@@ -86,5 +87,5 @@ fn main() {
         .expect("Couldn't write bindings!");
 }
 
-#[cfg(not(feature = "bindgen"))]
+#[cfg(not(feature = "netsnmp"))]
 fn main() {}
@@ -14,7 +14,13 @@
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use super::engine::EngineId;
+use super::error::Result;
+use super::key::Key;
+use super::secret::SecretString;
+use super::usm;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(tag = "version")]
 pub enum Auth {
     #[serde(rename = "2c")]
@@ -22,25 +28,27 @@ pub enum Auth {
     V2c(V2cAuth),
     #[serde(rename = "3")]
     V3(V3Auth),
+    #[serde(rename = "tsm")]
+    Tsm(TsmAuth),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct V2cAuth {
-    pub community: String,
+    pub community: SecretString,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct V3Auth {
     #[serde(flatten)]
     pub level: V3Level,
     // Unimplemented:
     pub context: Option<String>,
-    pub context_engine: Option<String>,
-    pub security_engine: Option<String>,
+    pub context_engine: Option<EngineId>,
+    pub security_engine: Option<EngineId>,
     pub destination_engine: Option<(String, String)>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(tag = "level")]
 pub enum V3Level {
     #[serde(rename = "noAuthNoPriv")]
@@ -55,20 +63,94 @@ pub enum V3Level {
     },
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct V3AuthParams {
     pub protocol: V3AuthProtocol,
     pub user: String,
-    pub password: String,
+    #[serde(flatten)]
+    pub key: V3Key,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct V3PrivParams {
     pub protocol: V3PrivProtocol,
-    pub password: String,
+    #[serde(flatten)]
+    pub key: V3Key,
+}
+
+/// Key input for SNMPv3 authentication or privacy.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum V3Key {
+    /// Password, converted to a master key on every session open.
+    #[serde(rename = "password")]
+    Password(SecretString),
+    /// Precomputed master key (Ku), in hex.
+    #[serde(rename = "master_key")]
+    MasterKey(Key),
+    /// Key localized to the peer engine id (Kul), in hex.
+    #[serde(rename = "localized_key")]
+    LocalizedKey(Key),
+}
+
+impl V3Level {
+    /// Replace passwords by master keys (Ku). Password to key
+    /// conversion is expensive; the result can be cached and used
+    /// for any engine.
+    pub fn to_master_keys(&self) -> Result<V3Level> {
+        self.map_keys(|protocol, key| match key {
+            V3Key::Password(password) => Ok(V3Key::MasterKey(usm::generate_ku(
+                protocol,
+                password.as_bytes(),
+            )?)),
+            key => Ok(key.clone()),
+        })
+    }
+
+    /// Derive keys localized to the given engine id (Kul). The
+    /// result can be cached and used for sessions to that engine.
+    pub fn localize(&self, engine_id: &EngineId) -> Result<V3Level> {
+        self.map_keys(|protocol, key| match key {
+            V3Key::Password(password) => {
+                let ku = usm::generate_ku(protocol, password.as_bytes())?;
+                Ok(V3Key::LocalizedKey(usm::generate_kul(
+                    protocol, engine_id, &ku,
+                )?))
+            }
+            V3Key::MasterKey(ku) => Ok(V3Key::LocalizedKey(usm::generate_kul(
+                protocol, engine_id, ku,
+            )?)),
+            V3Key::LocalizedKey(kul) => Ok(V3Key::LocalizedKey(kul.clone())),
+        })
+    }
+
+    /* Privacy keys are derived using the authentication protocol. */
+    fn map_keys<F>(&self, f: F) -> Result<V3Level>
+    where
+        F: Fn(&V3AuthProtocol, &V3Key) -> Result<V3Key>,
+    {
+        match self {
+            V3Level::NoAuthNoPriv => Ok(V3Level::NoAuthNoPriv),
+            V3Level::AuthNoPriv { auth } => Ok(V3Level::AuthNoPriv {
+                auth: V3AuthParams {
+                    key: f(&auth.protocol, &auth.key)?,
+                    ..auth.clone()
+                },
+            }),
+            V3Level::AuthPriv { auth, privacy } => Ok(V3Level::AuthPriv {
+                auth: V3AuthParams {
+                    key: f(&auth.protocol, &auth.key)?,
+                    ..auth.clone()
+                },
+                privacy: V3PrivParams {
+                    key: f(&auth.protocol, &privacy.key)?,
+                    ..privacy.clone()
+                },
+            }),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum V3AuthProtocol {
     #[serde(alias = "sha")]
     SHA,
@@ -76,8 +158,52 @@ pub enum V3AuthProtocol {
     MD5,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum V3PrivProtocol {
     DES,
     AES,
 }
+
+/// SNMPv3 with the transport security model (RFC 6353).
+///
+/// Certificates are looked up in the netsnmp certificate store: the
+/// `tls/certs`, `tls/ca-certs` and `tls/private` (keys) directories
+/// of the configuration path. Use `NetSNMP::init_with_config_dir` to
+/// add a directory to the store.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TsmAuth {
+    pub transport: TsmTransport,
+    /// Local certificate (fingerprint or file name in the certificate
+    /// store); its private key is found by the same name.
+    pub local_cert: String,
+    /// Expected peer certificate (fingerprint or file name).
+    pub peer_cert: Option<String>,
+    /// CA certificate trusted to sign the peer certificate.
+    pub trust_cert: Option<String>,
+    /// Hostname to verify against the peer certificate.
+    pub peer_hostname: Option<String>,
+    /// Security name to use in requests: the name the agent maps our
+    /// certificate to (its certSecName configuration).
+    pub security_name: String,
+    pub context: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TsmTransport {
+    #[serde(rename = "dtls")]
+    #[serde(alias = "dtlsudp")]
+    Dtls,
+    #[serde(rename = "tls")]
+    #[serde(alias = "tlstcp")]
+    Tls,
+}
+
+impl TsmTransport {
+    /// The netsnmp transport domain prefix.
+    pub fn domain(&self) -> &'static str {
+        match self {
+            TsmTransport::Dtls => "dtlsudp",
+            TsmTransport::Tls => "tls",
+        }
+    }
+}
@@ -0,0 +1,740 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use std::convert::TryFrom;
+
+use super::error::{Error, Result};
+use super::msg::Msg;
+use super::oid::Oid;
+use super::types::ErrType;
+use super::value::Value;
+use super::version::Version;
+
+/* Universal and SNMP application tags. */
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STR: u8 = 0x03;
+const TAG_OCTET_STR: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OBJECT_ID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_IPADDRESS: u8 = 0x40;
+const TAG_COUNTER: u8 = 0x41;
+const TAG_GAUGE: u8 = 0x42;
+const TAG_TIMETICKS: u8 = 0x43;
+const TAG_OPAQUE: u8 = 0x44;
+const TAG_COUNTER64: u8 = 0x46;
+const TAG_NO_SUCH_OBJECT: u8 = 0x80;
+const TAG_NO_SUCH_INSTANCE: u8 = 0x81;
+const TAG_END_OF_MIB_VIEW: u8 = 0x82;
+
+/* Types wrapped in an opaque (netsnmp extension). Netsnmp uses the
+ * float and double tags as variable types. */
+const OPAQUE_TAG1: u8 = 0x9f;
+const OPAQUE_COUNTER64: u8 = 0x76;
+pub(crate) const OPAQUE_FLOAT: u8 = 0x78;
+pub(crate) const OPAQUE_DOUBLE: u8 = 0x79;
+const OPAQUE_I64: u8 = 0x7a;
+const OPAQUE_U64: u8 = 0x7b;
+
+/// The user-based security model (RFC 3414).
+pub const SECURITY_MODEL_USM: i64 = 3;
+
+/// A decoded SNMP message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// SNMPv1 or SNMPv2c message.
+    Community {
+        version: Version,
+        community: Vec<u8>,
+        pdu: BerPdu,
+    },
+    /// SNMPv3 message (RFC 3412). The security parameters are kept
+    /// encoded; see `UsmSecurityParams` for the USM format.
+    V3 {
+        header: V3Header,
+        security_params: Vec<u8>,
+        data: ScopedPduData,
+    },
+}
+
+/// The global header of an SNMPv3 message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct V3Header {
+    pub msg_id: i64,
+    pub max_size: i64,
+    pub flags: u8,
+    pub security_model: i64,
+}
+
+/// Security parameters of the user-based security model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsmSecurityParams {
+    pub engine_id: Vec<u8>,
+    pub engine_boots: i64,
+    pub engine_time: i64,
+    pub user_name: Vec<u8>,
+    pub auth_params: Vec<u8>,
+    pub priv_params: Vec<u8>,
+}
+
+/// The (possibly encrypted) scoped pdu of an SNMPv3 message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScopedPduData {
+    Plain(ScopedPdu),
+    Encrypted(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScopedPdu {
+    pub context_engine_id: Vec<u8>,
+    pub context_name: Vec<u8>,
+    pub pdu: BerPdu,
+}
+
+/// A decoded pdu. For GetBulk requests, `error_status` and
+/// `error_index` hold non-repeaters and max-repetitions (as in
+/// netsnmp).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BerPdu {
+    pub command: Msg,
+    pub request_id: i64,
+    pub error_status: i64,
+    pub error_index: i64,
+    /// Only for SNMPv1 traps.
+    pub trap: Option<TrapV1>,
+    pub varbinds: Vec<VarBind>,
+}
+
+/// The header fields of an SNMPv1 trap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrapV1 {
+    pub enterprise: Oid,
+    pub agent_addr: [u8; 4],
+    pub generic_trap: i64,
+    pub specific_trap: i64,
+    pub time_stamp: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarBind {
+    pub oid: Oid,
+    pub value: std::result::Result<Value, ErrType>,
+}
+
+impl Message {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let mut msg = Reader::new(reader.expect(TAG_SEQUENCE)?);
+        reader.finish()?;
+
+        let version = msg.read_int()?;
+        let message = match version {
+            0 | 1 => Message::Community {
+                version: match version {
+                    0 => Version::V1,
+                    _ => Version::V2c,
+                },
+                community: msg.expect(TAG_OCTET_STR)?.to_vec(),
+                pdu: BerPdu::decode_from(&mut msg)?,
+            },
+            3 => {
+                let mut global = Reader::new(msg.expect(TAG_SEQUENCE)?);
+                let header = V3Header {
+                    msg_id: global.read_int()?,
+                    max_size: global.read_int()?,
+                    flags: match global.expect(TAG_OCTET_STR)? {
+                        [flags] => *flags,
+                        _ => return Err(ber_error("invalid msgFlags")),
+                    },
+                    security_model: global.read_int()?,
+                };
+                global.finish()?;
+                let security_params = msg.expect(TAG_OCTET_STR)?.to_vec();
+                let data = match msg.peek_tag()? {
+                    TAG_OCTET_STR => ScopedPduData::Encrypted(msg.expect(TAG_OCTET_STR)?.to_vec()),
+                    _ => ScopedPduData::Plain(ScopedPdu::decode_from(&mut msg)?),
+                };
+                Message::V3 {
+                    header,
+                    security_params,
+                    data,
+                }
+            }
+            v => return Err(ber_error(&format!("unsupported version {}", v))),
+        };
+        msg.finish()?;
+        Ok(message)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut msg = Vec::new();
+        match self {
+            Message::Community {
+                version,
+                community,
+                pdu,
+            } => {
+                let version = match version {
+                    Version::V1 => 0,
+                    Version::V2c => 1,
+                    Version::V3 => return Err(ber_error("community message with version 3")),
+                };
+                write_int(&mut msg, TAG_INTEGER, version);
+                write_tlv(&mut msg, TAG_OCTET_STR, community);
+                pdu.encode_to(&mut msg)?;
+            }
+            Message::V3 {
+                header,
+                security_params,
+                data,
+            } => {
+                write_int(&mut msg, TAG_INTEGER, 3);
+                let mut global = Vec::new();
+                write_int(&mut global, TAG_INTEGER, header.msg_id);
+                write_int(&mut global, TAG_INTEGER, header.max_size);
+                write_tlv(&mut global, TAG_OCTET_STR, &[header.flags]);
+                write_int(&mut global, TAG_INTEGER, header.security_model);
+                write_tlv(&mut msg, TAG_SEQUENCE, &global);
+                write_tlv(&mut msg, TAG_OCTET_STR, security_params);
+                match data {
+                    ScopedPduData::Plain(scoped) => scoped.encode_to(&mut msg)?,
+                    ScopedPduData::Encrypted(data) => write_tlv(&mut msg, TAG_OCTET_STR, data),
+                }
+            }
+        }
+        let mut out = Vec::with_capacity(msg.len() + 4);
+        write_tlv(&mut out, TAG_SEQUENCE, &msg);
+        Ok(out)
+    }
+
+    /// The pdu, if not encrypted.
+    pub fn pdu(&self) -> Option<&BerPdu> {
+        match self {
+            Message::Community { pdu, .. } => Some(pdu),
+            Message::V3 {
+                data: ScopedPduData::Plain(scoped),
+                ..
+            } => Some(&scoped.pdu),
+            Message::V3 { .. } => None,
+        }
+    }
+}
+
+impl V3Header {
+    pub const FLAG_AUTH: u8 = 0x01;
+    pub const FLAG_PRIV: u8 = 0x02;
+    pub const FLAG_REPORTABLE: u8 = 0x04;
+}
+
+impl UsmSecurityParams {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let mut params = Reader::new(reader.expect(TAG_SEQUENCE)?);
+        reader.finish()?;
+        let result = Self {
+            engine_id: params.expect(TAG_OCTET_STR)?.to_vec(),
+            engine_boots: params.read_int()?,
+            engine_time: params.read_int()?,
+            user_name: params.expect(TAG_OCTET_STR)?.to_vec(),
+            auth_params: params.expect(TAG_OCTET_STR)?.to_vec(),
+            priv_params: params.expect(TAG_OCTET_STR)?.to_vec(),
+        };
+        params.finish()?;
+        Ok(result)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut params = Vec::new();
+        write_tlv(&mut params, TAG_OCTET_STR, &self.engine_id);
+        write_int(&mut params, TAG_INTEGER, self.engine_boots);
+        write_int(&mut params, TAG_INTEGER, self.engine_time);
+        write_tlv(&mut params, TAG_OCTET_STR, &self.user_name);
+        write_tlv(&mut params, TAG_OCTET_STR, &self.auth_params);
+        write_tlv(&mut params, TAG_OCTET_STR, &self.priv_params);
+        let mut out = Vec::new();
+        write_tlv(&mut out, TAG_SEQUENCE, &params);
+        out
+    }
+}
+
+impl ScopedPdu {
+    /// Decode a (decrypted) scoped pdu.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let scoped = Self::decode_from(&mut reader)?;
+        reader.finish()?;
+        Ok(scoped)
+    }
+
+    /// Decode a decrypted scoped pdu, ignoring padding added for
+    /// the block cipher (DES).
+    pub fn decode_padded(data: &[u8]) -> Result<Self> {
+        Self::decode_from(&mut Reader::new(data))
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.encode_to(&mut out)?;
+        Ok(out)
+    }
+
+    fn decode_from(reader: &mut Reader) -> Result<Self> {
+        let mut scoped = Reader::new(reader.expect(TAG_SEQUENCE)?);
+        let result = Self {
+            context_engine_id: scoped.expect(TAG_OCTET_STR)?.to_vec(),
+            context_name: scoped.expect(TAG_OCTET_STR)?.to_vec(),
+            pdu: BerPdu::decode_from(&mut scoped)?,
+        };
+        scoped.finish()?;
+        Ok(result)
+    }
+
+    fn encode_to(&self, out: &mut Vec<u8>) -> Result<()> {
+        let mut scoped = Vec::new();
+        write_tlv(&mut scoped, TAG_OCTET_STR, &self.context_engine_id);
+        write_tlv(&mut scoped, TAG_OCTET_STR, &self.context_name);
+        self.pdu.encode_to(&mut scoped)?;
+        write_tlv(out, TAG_SEQUENCE, &scoped);
+        Ok(())
+    }
+}
+
+impl BerPdu {
+    pub fn new(command: Msg, request_id: i64) -> Self {
+        Self {
+            command,
+            request_id,
+            error_status: 0,
+            error_index: 0,
+            trap: None,
+            varbinds: Vec::new(),
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let pdu = Self::decode_from(&mut reader)?;
+        reader.finish()?;
+        Ok(pdu)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.encode_to(&mut out)?;
+        Ok(out)
+    }
+
+    fn decode_from(reader: &mut Reader) -> Result<Self> {
+        let (tag, content) = reader.read_tlv()?;
+        let command = Msg::try_from(tag as u32)?;
+        let mut pdu = Reader::new(content);
+        let result = match command {
+            Msg::Trap => {
+                let trap = TrapV1 {
+                    enterprise: decode_oid(pdu.expect(TAG_OBJECT_ID)?)?,
+                    agent_addr: match pdu.expect(TAG_IPADDRESS)? {
+                        [a, b, c, d] => [*a, *b, *c, *d],
+                        _ => return Err(ber_error("invalid agent address")),
+                    },
+                    generic_trap: pdu.read_int()?,
+                    specific_trap: pdu.read_int()?,
+                    time_stamp: decode_uint(pdu.expect(TAG_TIMETICKS)?)?,
+                };
+                Self {
+                    command,
+                    request_id: 0,
+                    error_status: 0,
+                    error_index: 0,
+                    trap: Some(trap),
+                    varbinds: decode_varbinds(&mut pdu)?,
+                }
+            }
+            _ => Self {
+                command,
+                request_id: pdu.read_int()?,
+                error_status: pdu.read_int()?,
+                error_index: pdu.read_int()?,
+                trap: None,
+                varbinds: decode_varbinds(&mut pdu)?,
+            },
+        };
+        pdu.finish()?;
+        Ok(result)
+    }
+
+    fn encode_to(&self, out: &mut Vec<u8>) -> Result<()> {
+        let mut pdu = Vec::new();
+        match (&self.command, &self.trap) {
+            (Msg::Trap, Some(trap)) => {
+                write_tlv(&mut pdu, TAG_OBJECT_ID, &encode_oid(&trap.enterprise)?);
+                write_tlv(&mut pdu, TAG_IPADDRESS, &trap.agent_addr);
+                write_int(&mut pdu, TAG_INTEGER, trap.generic_trap);
+                write_int(&mut pdu, TAG_INTEGER, trap.specific_trap);
+                write_uint(&mut pdu, TAG_TIMETICKS, trap.time_stamp);
+            }
+            (Msg::Trap, None) => return Err(ber_error("v1 trap without trap header")),
+            _ => {
+                write_int(&mut pdu, TAG_INTEGER, self.request_id);
+                write_int(&mut pdu, TAG_INTEGER, self.error_status);
+                write_int(&mut pdu, TAG_INTEGER, self.error_index);
+            }
+        }
+        let mut varbinds = Vec::new();
+        for varbind in &self.varbinds {
+            varbind.encode_to(&mut varbinds)?;
+        }
+        write_tlv(&mut pdu, TAG_SEQUENCE, &varbinds);
+        write_tlv(out, self.command as u8, &pdu);
+        Ok(())
+    }
+}
+
+impl VarBind {
+    fn decode_from(reader: &mut Reader) -> Result<Self> {
+        let mut varbind = Reader::new(reader.expect(TAG_SEQUENCE)?);
+        let oid = decode_oid(varbind.expect(TAG_OBJECT_ID)?)?;
+        let (tag, content) = varbind.read_tlv()?;
+        varbind.finish()?;
+        Ok(Self {
+            oid,
+            value: decode_value(tag, content)?,
+        })
+    }
+
+    fn encode_to(&self, out: &mut Vec<u8>) -> Result<()> {
+        let mut varbind = Vec::new();
+        write_tlv(&mut varbind, TAG_OBJECT_ID, &encode_oid(&self.oid)?);
+        match &self.value {
+            Ok(value) => encode_value(&mut varbind, value)?,
+            Err(ErrType::NoSuchObject) => write_tlv(&mut varbind, TAG_NO_SUCH_OBJECT, &[]),
+            Err(ErrType::NoSuchInstance) => write_tlv(&mut varbind, TAG_NO_SUCH_INSTANCE, &[]),
+            Err(ErrType::EndOfMibView) => write_tlv(&mut varbind, TAG_END_OF_MIB_VIEW, &[]),
+            Err(err) => return Err(ber_error(&format!("can not encode {:?}", err))),
+        }
+        write_tlv(out, TAG_SEQUENCE, &varbind);
+        Ok(())
+    }
+}
+
+fn decode_varbinds(pdu: &mut Reader) -> Result<Vec<VarBind>> {
+    let mut list = Reader::new(pdu.expect(TAG_SEQUENCE)?);
+    let mut varbinds = Vec::new();
+    while !list.is_empty() {
+        varbinds.push(VarBind::decode_from(&mut list)?);
+    }
+    Ok(varbinds)
+}
+
+fn decode_value(tag: u8, content: &[u8]) -> Result<std::result::Result<Value, ErrType>> {
+    Ok(Ok(match tag {
+        TAG_BOOLEAN => Value::Boolean(decode_int(content)? != 0),
+        TAG_INTEGER => Value::Integer(decode_int(content)?),
+        TAG_BIT_STR => Value::BitStr(content.to_vec()),
+        TAG_OCTET_STR => Value::OctetStr(content.to_vec()),
+        TAG_NULL => Value::Null,
+        TAG_OBJECT_ID => Value::Oid(decode_oid(content)?),
+        /* As in netsnmp, the address is kept in network byte order. */
+        TAG_IPADDRESS => match content {
+            [a, b, c, d] => Value::IpAddress(u32::from_ne_bytes([*a, *b, *c, *d])),
+            _ => return Err(ber_error("invalid ip address")),
+        },
+        TAG_COUNTER => Value::Counter(decode_uint(content)?),
+        TAG_GAUGE => Value::Gauge(decode_uint(content)?),
+        TAG_TIMETICKS => Value::TimeTicks(decode_uint(content)?),
+        TAG_COUNTER64 => Value::Counter64(decode_uint(content)?),
+        TAG_OPAQUE => return decode_opaque(content),
+        TAG_NO_SUCH_OBJECT => return Ok(Err(ErrType::NoSuchObject)),
+        TAG_NO_SUCH_INSTANCE => return Ok(Err(ErrType::NoSuchInstance)),
+        TAG_END_OF_MIB_VIEW => return Ok(Err(ErrType::EndOfMibView)),
+        tag => return Ok(Err(ErrType::NotImplemented(tag))),
+    }))
+}
+
+fn decode_opaque(content: &[u8]) -> Result<std::result::Result<Value, ErrType>> {
+    let (typ, data) = match content {
+        [OPAQUE_TAG1, typ, rest @ ..] => {
+            let mut reader = Reader::new(rest);
+            let data = reader.read_content()?;
+            reader.finish()?;
+            (*typ, data)
+        }
+        _ => return Ok(Err(ErrType::NotImplemented(TAG_OPAQUE))),
+    };
+    Ok(Ok(match typ {
+        OPAQUE_COUNTER64 => Value::Counter64(decode_uint(data)?),
+        OPAQUE_U64 => Value::Unsigned64(decode_uint(data)?),
+        OPAQUE_I64 => Value::Integer64(decode_int(data)?),
+        OPAQUE_FLOAT => match data {
+            [a, b, c, d] => Value::Float(f32::from_be_bytes([*a, *b, *c, *d])),
+            _ => return Err(ber_error("invalid opaque float")),
+        },
+        OPAQUE_DOUBLE => match <[u8; 8]>::try_from(data) {
+            Ok(bytes) => Value::Double(f64::from_be_bytes(bytes)),
+            Err(_) => return Err(ber_error("invalid opaque double")),
+        },
+        _ => return Ok(Err(ErrType::NotImplemented(TAG_OPAQUE))),
+    }))
+}
+
+fn encode_value(out: &mut Vec<u8>, value: &Value) -> Result<()> {
+    match value {
+        Value::Boolean(v) => write_int(out, TAG_BOOLEAN, *v as i64),
+        Value::Integer(v) => write_int(out, TAG_INTEGER, *v),
+        Value::BitStr(v) => write_tlv(out, TAG_BIT_STR, v),
+        Value::OctetStr(v) => write_tlv(out, TAG_OCTET_STR, v),
+        Value::Null => write_tlv(out, TAG_NULL, &[]),
+        Value::Oid(v) => write_tlv(out, TAG_OBJECT_ID, &encode_oid(v)?),
+        Value::IpAddress(v) => write_tlv(out, TAG_IPADDRESS, &v.to_ne_bytes()),
+        /* Not an SNMP type; sent as a six byte octet string. */
+        Value::MacAddress(v) => write_tlv(out, TAG_OCTET_STR, &v.to_be_bytes()[2..]),
+        Value::Counter(v) => write_uint(out, TAG_COUNTER, *v),
+        Value::Gauge(v) => write_uint(out, TAG_GAUGE, *v),
+        Value::TimeTicks(v) => write_uint(out, TAG_TIMETICKS, *v),
+        Value::Counter64(v) => write_uint(out, TAG_COUNTER64, *v),
+        Value::Unsigned64(v) => write_opaque(out, OPAQUE_U64, &uint_content(*v)),
+        Value::Integer64(v) => write_opaque(out, OPAQUE_I64, &int_content(*v)),
+        Value::Float(v) => write_opaque(out, OPAQUE_FLOAT, &v.to_be_bytes()),
+        Value::Double(v) => write_opaque(out, OPAQUE_DOUBLE, &v.to_be_bytes()),
+        Value::Sequence | Value::Set | Value::Opaque => {
+            return Err(ber_error(&format!("can not encode {:?}", value)))
+        }
+    }
+    Ok(())
+}
+
+fn write_opaque(out: &mut Vec<u8>, typ: u8, content: &[u8]) {
+    let mut opaque = vec![OPAQUE_TAG1, typ];
+    write_length(&mut opaque, content.len());
+    opaque.extend_from_slice(content);
+    write_tlv(out, TAG_OPAQUE, &opaque);
+}
+
+/* Primitive encoding. */
+
+fn write_tlv(out: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    out.push(tag);
+    write_length(out, content.len());
+    out.extend_from_slice(content);
+}
+
+fn write_length(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = (len as u64).to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, tag: u8, value: i64) {
+    write_tlv(out, tag, &int_content(value));
+}
+
+fn write_uint(out: &mut Vec<u8>, tag: u8, value: u64) {
+    write_tlv(out, tag, &uint_content(value));
+}
+
+/// Minimal two's complement encoding.
+fn int_content(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+fn uint_content(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take(7).take_while(|b| **b == 0).count();
+    let mut content = Vec::with_capacity(9);
+    if bytes[skip] & 0x80 != 0 {
+        content.push(0);
+    }
+    content.extend_from_slice(&bytes[skip..]);
+    content
+}
+
+fn encode_oid(oid: &Oid) -> Result<Vec<u8>> {
+    let ids = oid.as_slice();
+    /* netsnmp encodes an empty oid as 0.0. */
+    let (first, rest) = match ids {
+        [] => (0, &[][..]),
+        [a] => (check_first_arcs(*a, 0)?, &[][..]),
+        [a, b, rest @ ..] => (check_first_arcs(*a, *b)?, rest),
+    };
+    let mut out = Vec::new();
+    for id in std::iter::once(first).chain(rest.iter().copied()) {
+        let mut chunks = vec![(id & 0x7f) as u8];
+        let mut id = id >> 7;
+        while id > 0 {
+            chunks.push((id & 0x7f) as u8 | 0x80);
+            id >>= 7;
+        }
+        out.extend(chunks.iter().rev());
+    }
+    Ok(out)
+}
+
+fn check_first_arcs(a: u64, b: u64) -> Result<u64> {
+    match a <= 2 && (a == 2 || b < 40) {
+        true => b
+            .checked_add(a * 40)
+            .ok_or_else(|| ber_error("oid sub-identifier too large")),
+        false => Err(ber_error("invalid first oid sub-identifiers")),
+    }
+}
+
+/* Primitive decoding. */
+
+fn decode_int(content: &[u8]) -> Result<i64> {
+    if content.is_empty() || content.len() > 8 {
+        return Err(ber_error("invalid integer length"));
+    }
+    let init = match content[0] & 0x80 {
+        0 => 0,
+        _ => -1,
+    };
+    Ok(content
+        .iter()
+        .fold(init, |acc: i64, b| (acc << 8) | *b as i64))
+}
+
+/* Some agents do not add the leading zero byte for large unsigned
+ * values; accept those too. */
+fn decode_uint(content: &[u8]) -> Result<u64> {
+    let content = match content {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => content,
+    };
+    if content.is_empty() || content.len() > 8 {
+        return Err(ber_error("invalid unsigned integer length"));
+    }
+    Ok(content.iter().fold(0, |acc: u64, b| (acc << 8) | *b as u64))
+}
+
+fn decode_oid(content: &[u8]) -> Result<Oid> {
+    let mut ids = Vec::new();
+    let mut id: u64 = 0;
+    for (i, b) in content.iter().enumerate() {
+        if id > u64::MAX >> 7 {
+            return Err(ber_error("oid sub-identifier too large"));
+        }
+        id = (id << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            match ids.is_empty() {
+                true => {
+                    let first = (id / 40).min(2);
+                    ids.push(first);
+                    ids.push(id - first * 40);
+                }
+                false => ids.push(id),
+            }
+            id = 0;
+        } else if i + 1 == content.len() {
+            return Err(ber_error("truncated oid"));
+        }
+    }
+    if ids.is_empty() {
+        return Err(ber_error("empty oid"));
+    }
+    Ok(Oid::from_slice(&ids))
+}
+
+fn ber_error(msg: &str) -> Error {
+    Error::Ber(msg.to_string())
+}
+
+/// A reader over a sequence of BER-encoded values.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.data.is_empty() {
+            true => Ok(()),
+            false => Err(ber_error("trailing data")),
+        }
+    }
+
+    fn peek_tag(&self) -> Result<u8> {
+        self.data
+            .first()
+            .copied()
+            .ok_or_else(|| ber_error("unexpected end of data"))
+    }
+
+    fn read_tlv(&mut self) -> Result<(u8, &'a [u8])> {
+        let tag = self.peek_tag()?;
+        if tag & 0x1f == 0x1f {
+            return Err(ber_error("unsupported multi-byte tag"));
+        }
+        self.data = &self.data[1..];
+        Ok((tag, self.read_content()?))
+    }
+
+    /// Read a length and the content it covers.
+    fn read_content(&mut self) -> Result<&'a [u8]> {
+        let (len, rest) = match self.data {
+            [len, rest @ ..] if len & 0x80 == 0 => (*len as usize, rest),
+            [0x80, ..] => return Err(ber_error("indefinite length")),
+            [len, rest @ ..] => {
+                let n = (len & 0x7f) as usize;
+                if n > 4 || rest.len() < n {
+                    return Err(ber_error("invalid length"));
+                }
+                let len = rest[..n]
+                    .iter()
+                    .fold(0, |acc: usize, b| (acc << 8) | *b as usize);
+                (len, &rest[n..])
+            }
+            [] => return Err(ber_error("unexpected end of data")),
+        };
+        if rest.len() < len {
+            return Err(ber_error("truncated value"));
+        }
+        let (content, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok(content)
+    }
+
+    fn expect(&mut self, tag: u8) -> Result<&'a [u8]> {
+        match self.read_tlv()? {
+            (t, content) if t == tag => Ok(content),
+            (t, _) => Err(ber_error(&format!(
+                "expected tag 0x{:02x}, got 0x{:02x}",
+                tag, t
+            ))),
+        }
+    }
+
+    fn read_int(&mut self) -> Result<i64> {
+        decode_int(self.expect(TAG_INTEGER)?)
+    }
+}
@@ -0,0 +1,487 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Shared argument handling and output for the command-line tools. */
+
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fs;
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use netsnmp::{
+    printable, render_value, Auth, BulkResponse, ErrType, NdjsonWriter, NetSNMP, Oid, Pdu,
+    SecretString, SingleSession, V2cAuth, V3Auth, V3AuthParams, V3AuthProtocol, V3Key, V3Level,
+    V3PrivParams, V3PrivProtocol, Value, VarBindRecord, Version,
+};
+
+pub type Var = (Oid, Result<Value, ErrType>);
+pub type CliResult<T> = Result<T, Box<dyn StdError>>;
+
+#[derive(Args, Debug)]
+pub struct SessionArgs {
+    /// The agent, in net-snmp peer syntax (eg. udp:192.0.2.1:161).
+    pub peer: String,
+
+    /// Read credentials from a JSON file, in the serde format of `Auth`.
+    #[arg(long, value_name = "FILE")]
+    pub auth_file: Option<PathBuf>,
+    #[arg(short = 'v', long = "snmp-version", value_enum)]
+    pub version: Option<VersionArg>,
+    #[arg(short = 'c', long)]
+    pub community: Option<String>,
+    #[arg(short = 'u', long)]
+    pub user: Option<String>,
+    #[arg(short = 'l', long, value_enum)]
+    pub level: Option<LevelArg>,
+    #[arg(short = 'a', long, value_enum, default_value = "MD5")]
+    pub auth_protocol: AuthProtocolArg,
+    #[arg(short = 'A', long)]
+    pub auth_password: Option<String>,
+    #[arg(short = 'x', long, value_enum, default_value = "DES")]
+    pub priv_protocol: PrivProtocolArg,
+    #[arg(short = 'X', long)]
+    pub priv_password: Option<String>,
+
+    /// Timeout in seconds.
+    #[arg(short = 't', long, default_value_t = 1.0)]
+    pub timeout: f64,
+    #[arg(short = 'r', long, default_value_t = 5)]
+    pub retries: u64,
+
+    #[arg(short = 'o', long, value_enum, default_value = "text")]
+    pub output: Format,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionArg {
+    #[value(name = "1")]
+    V1,
+    #[value(name = "2c")]
+    V2c,
+    #[value(name = "3")]
+    V3,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LevelArg {
+    #[value(name = "noAuthNoPriv")]
+    NoAuth,
+    #[value(name = "authNoPriv")]
+    Auth,
+    #[value(name = "authPriv")]
+    Priv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum AuthProtocolArg {
+    #[value(name = "MD5", alias = "md5")]
+    Md5,
+    #[value(name = "SHA", alias = "sha")]
+    Sha,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PrivProtocolArg {
+    #[value(name = "DES", alias = "des")]
+    Des,
+    #[value(name = "AES", alias = "aes")]
+    Aes,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl SessionArgs {
+    /// Open a session on the async (single session) path.
+    pub fn open(&self, snmp: &NetSNMP) -> CliResult<SingleSession> {
+        let timeout = Duration::try_from_secs_f64(self.timeout)
+            .map_err(|e| format!("Invalid timeout {}: {}", self.timeout, e))?;
+        let mut builder = snmp
+            .session()
+            .set_peer(self.peer.as_bytes())?
+            .set_auth(&self.auth()?)?
+            .set_timeout(timeout)
+            .set_retries(self.retries)
+            .set_async_probe(true);
+        if self.version == Some(VersionArg::V1) {
+            builder = builder.set_version(Version::V1);
+        }
+        Ok(builder.open_single()?)
+    }
+
+    fn auth(&self) -> CliResult<Auth> {
+        if let Some(path) = &self.auth_file {
+            let data = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            return Ok(serde_json::from_str(&data)
+                .map_err(|e| format!("Invalid credentials in {}: {}", path.display(), e))?);
+        }
+
+        let version = self.version.unwrap_or(match self.user {
+            Some(_) => VersionArg::V3,
+            None => VersionArg::V2c,
+        });
+        if version != VersionArg::V3 {
+            return Ok(Auth::V2c(V2cAuth {
+                community: SecretString::from(self.community.as_deref().unwrap_or("public")),
+            }));
+        }
+
+        let level = self
+            .level
+            .unwrap_or(match (&self.auth_password, &self.priv_password) {
+                (Some(_), Some(_)) => LevelArg::Priv,
+                (Some(_), None) => LevelArg::Auth,
+                _ => LevelArg::NoAuth,
+            });
+        let auth = || -> CliResult<V3AuthParams> {
+            Ok(V3AuthParams {
+                protocol: match self.auth_protocol {
+                    AuthProtocolArg::Md5 => V3AuthProtocol::MD5,
+                    AuthProtocolArg::Sha => V3AuthProtocol::SHA,
+                },
+                user: self.user.clone().ok_or("A user name (-u) is required")?,
+                key: V3Key::Password(SecretString::from(
+                    self.auth_password
+                        .as_deref()
+                        .ok_or("An authentication password (-A) is required")?,
+                )),
+            })
+        };
+        let level = match level {
+            LevelArg::NoAuth => V3Level::NoAuthNoPriv,
+            LevelArg::Auth => V3Level::AuthNoPriv { auth: auth()? },
+            LevelArg::Priv => V3Level::AuthPriv {
+                auth: auth()?,
+                privacy: V3PrivParams {
+                    protocol: match self.priv_protocol {
+                        PrivProtocolArg::Des => V3PrivProtocol::DES,
+                        PrivProtocolArg::Aes => V3PrivProtocol::AES,
+                    },
+                    key: V3Key::Password(SecretString::from(
+                        self.priv_password
+                            .as_deref()
+                            .ok_or("A privacy password (-X) is required")?,
+                    )),
+                },
+            },
+        };
+        Ok(Auth::V3(V3Auth {
+            level,
+            context: None,
+            context_engine: None,
+            security_engine: None,
+            destination_engine: None,
+        }))
+    }
+}
+
+/// Print the error and exit with a failure code.
+pub fn exit(result: CliResult<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parse a numeric or symbolic (MIB) oid.
+pub fn parse_oid(oid: &str) -> CliResult<Oid> {
+    Oid::from_str(oid)
+        .or_else(|_| Oid::parse_symbol(oid))
+        .map_err(|_| format!("Unknown oid: {}", oid).into())
+}
+
+/// Parse a value for snmpset, given the net-snmp type character.
+pub fn parse_value(typ: &str, value: &str) -> CliResult<Value> {
+    let invalid = || format!("Invalid value for type {}: {}", typ, value);
+    Ok(match typ {
+        "i" => Value::Integer(value.parse().map_err(|_| invalid())?),
+        "u" => Value::Gauge(value.parse().map_err(|_| invalid())?),
+        "c" => Value::Counter(value.parse().map_err(|_| invalid())?),
+        "C" => Value::Counter64(value.parse().map_err(|_| invalid())?),
+        "t" => Value::TimeTicks(value.parse().map_err(|_| invalid())?),
+        "a" => {
+            let addr: Ipv4Addr = value.parse().map_err(|_| invalid())?;
+            Value::IpAddress(u32::from_ne_bytes(addr.octets()))
+        }
+        "o" => Value::Oid(parse_oid(value)?),
+        "s" => Value::OctetStr(value.as_bytes().to_vec()),
+        "x" => Value::OctetStr(parse_hex(value).ok_or_else(invalid)?),
+        "d" => Value::OctetStr(
+            value
+                .split(|c: char| c == '.' || c.is_whitespace())
+                .filter(|byte| !byte.is_empty())
+                .map(|byte| byte.parse().ok())
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?,
+        ),
+        "U" => Value::Unsigned64(value.parse().map_err(|_| invalid())?),
+        "I" => Value::Integer64(value.parse().map_err(|_| invalid())?),
+        "F" => Value::Float(value.parse().map_err(|_| invalid())?),
+        "D" => Value::Double(value.parse().map_err(|_| invalid())?),
+        _ => {
+            return Err(format!(
+                "Unknown type: {} (use one of i u c C t a o s x d U I F D)",
+                typ
+            )
+            .into())
+        }
+    })
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    let digits: Vec<u32> = value
+        .strip_prefix("0x")
+        .unwrap_or(value)
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .map(|c| c.to_digit(16))
+        .collect::<Option<_>>()?;
+    let pairs = digits.chunks_exact(2);
+    match pairs.remainder().is_empty() {
+        true => Some(pairs.map(|pair| (pair[0] << 4 | pair[1]) as u8).collect()),
+        false => None,
+    }
+}
+
+/// The variables in a response, failing on an error status.
+pub fn response_vars(pdu: &Pdu) -> CliResult<Vec<Var>> {
+    if let Some(err) = pdu.error() {
+        return Err(err.into());
+    }
+    Ok(pdu
+        .variables()
+        .map(|var| (var.get_name(), var.get_value()))
+        .collect())
+}
+
+/// Walk a subtree with GETBULK requests.
+pub async fn walk(
+    session: &mut SingleSession,
+    root: &Oid,
+    repetitions: usize,
+) -> CliResult<Vec<Var>> {
+    let mut vars = Vec::new();
+    walk_with(session, root, repetitions, |var| {
+        vars.push(var);
+        Ok(())
+    })
+    .await?;
+    Ok(vars)
+}
+
+/// Walk a subtree with GETBULK requests, handling variables as they
+/// arrive.
+pub async fn walk_with<F>(
+    session: &mut SingleSession,
+    root: &Oid,
+    repetitions: usize,
+    mut handle: F,
+) -> CliResult<()>
+where
+    F: FnMut(Var) -> CliResult<()>,
+{
+    let mut from = root.clone();
+    loop {
+        let pdu = session
+            .get_bulk_async(&[], &[from.clone()], repetitions)
+            .await?;
+        let response = BulkResponse::split_from(&[], &[(root.clone(), from)], &pdu)?;
+        let column = match response.columns.into_iter().next() {
+            Some(column) => column,
+            None => return Ok(()),
+        };
+        for var in column.vars {
+            handle(var)?;
+        }
+        match column.next {
+            Some(next) => from = next,
+            None => return Ok(()),
+        }
+    }
+}
+
+/* Output formats. Text output follows snmpwalk -On, and can be read
+ * back with `parse_snmpwalk`. JSON and NDJSON use the `VarBindRecord`
+ * interchange format. */
+
+pub fn print_vars(format: Format, vars: &[Var]) -> CliResult<()> {
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Text => {
+            for (oid, value) in vars {
+                match value {
+                    Ok(Value::Null) => writeln!(out, ".{} = NULL", oid)?,
+                    Ok(_) => writeln!(
+                        out,
+                        ".{} = {}: {}",
+                        oid,
+                        type_name(value),
+                        render_value(value)
+                    )?,
+                    Err(_) => writeln!(out, ".{} = {}", oid, render_value(value))?,
+                }
+            }
+        }
+        Format::Json => {
+            let vars: Vec<_> = vars
+                .iter()
+                .map(|(oid, value)| VarBindRecord::new(oid.clone(), value).resolve_name())
+                .collect();
+            serde_json::to_writer_pretty(&mut out, &vars)?;
+            writeln!(out)?;
+        }
+        Format::Ndjson => {
+            let mut writer = NdjsonWriter::new(out).set_resolve_names(true);
+            for (oid, value) in vars {
+                writer.write_var(oid.clone(), value)?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "oid,type,value")?;
+            for (oid, value) in vars {
+                writeln!(
+                    out,
+                    "{},{},{}",
+                    oid,
+                    type_name(value),
+                    csv_field(&render_value(value))
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print table rows (by index), with one column per entry column.
+pub fn print_table(
+    format: Format,
+    columns: &[u64],
+    rows: &BTreeMap<Oid, BTreeMap<u64, Result<Value, ErrType>>>,
+) -> CliResult<()> {
+    let cell = |row: &BTreeMap<u64, Result<Value, ErrType>>, column: &u64| {
+        row.get(column).map(render_value).unwrap_or_default()
+    };
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Text => {
+            let mut table = vec![std::iter::once(String::from("index"))
+                .chain(columns.iter().map(|c| c.to_string()))
+                .collect::<Vec<_>>()];
+            for (index, row) in rows {
+                table.push(
+                    std::iter::once(index.to_string())
+                        .chain(columns.iter().map(|c| cell(row, c)))
+                        .collect(),
+                );
+            }
+            let widths: Vec<usize> = (0..=columns.len())
+                .map(|i| {
+                    table
+                        .iter()
+                        .map(|row| row[i].chars().count())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            for row in table {
+                let line: Vec<_> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(value, width)| format!("{:width$}", value, width = width))
+                    .collect();
+                writeln!(out, "{}", line.join("  ").trim_end())?;
+            }
+        }
+        Format::Json | Format::Ndjson => {
+            let rows: Vec<_> = rows
+                .iter()
+                .map(|(index, row)| {
+                    let mut object = serde_json::Map::new();
+                    object.insert(String::from("index"), index.to_string().into());
+                    for (column, value) in row {
+                        object.insert(column.to_string(), render_value(value).into());
+                    }
+                    object
+                })
+                .collect();
+            if format == Format::Json {
+                serde_json::to_writer_pretty(&mut out, &rows)?;
+                writeln!(out)?;
+            } else {
+                for row in rows {
+                    serde_json::to_writer(&mut out, &row)?;
+                    writeln!(out)?;
+                }
+            }
+        }
+        Format::Csv => {
+            let header: Vec<_> = columns.iter().map(|c| c.to_string()).collect();
+            writeln!(out, "index,{}", header.join(","))?;
+            for (index, row) in rows {
+                let cells: Vec<_> = columns.iter().map(|c| csv_field(&cell(row, c))).collect();
+                writeln!(out, "{},{}", index, cells.join(","))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The type name, as printed by net-snmp.
+pub fn type_name(value: &Result<Value, ErrType>) -> &'static str {
+    match value {
+        Ok(Value::Boolean(_)) => "BOOLEAN",
+        Ok(Value::Integer(_)) => "INTEGER",
+        Ok(Value::BitStr(_)) => "BITS",
+        Ok(Value::OctetStr(s)) if printable(s) => "STRING",
+        Ok(Value::OctetStr(_)) => "Hex-STRING",
+        Ok(Value::Null) => "NULL",
+        Ok(Value::Oid(_)) => "OID",
+        Ok(Value::Sequence) => "SEQUENCE",
+        Ok(Value::Set) => "SET",
+        Ok(Value::IpAddress(_)) => "IpAddress",
+        Ok(Value::MacAddress(_)) => "MacAddress",
+        Ok(Value::Counter(_)) => "Counter32",
+        Ok(Value::Gauge(_)) => "Gauge32",
+        Ok(Value::TimeTicks(_)) => "Timeticks",
+        Ok(Value::Counter64(_)) => "Counter64",
+        Ok(Value::Opaque | Value::Float(_) | Value::Double(_)) => "Opaque",
+        Ok(Value::Integer64(_) | Value::Unsigned64(_)) => "Opaque",
+        Err(_) => "",
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
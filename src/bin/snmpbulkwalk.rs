@@ -0,0 +1,56 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+mod common;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use common::{CliResult, Format, SessionArgs};
+use netsnmp::NdjsonWriter;
+
+/// Walk a subtree with GETBULK requests.
+#[derive(Parser, Debug)]
+#[command(name = "snmpbulkwalk")]
+struct Args {
+    #[command(flatten)]
+    session: SessionArgs,
+    /// Max-repetitions per request.
+    #[arg(long, default_value_t = 10)]
+    repetitions: usize,
+    /// The subtree to walk (default: mib-2).
+    #[arg(default_value = "1.3.6.1.2.1")]
+    oid: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    common::exit(run(Args::parse()).await)
+}
+
+async fn run(args: Args) -> CliResult<()> {
+    let snmp = netsnmp::init("snmpbulkwalk");
+    let mut session = args.session.open(&snmp)?;
+    let root = common::parse_oid(&args.oid)?;
+    if args.session.output == Format::Ndjson {
+        /* Stream, rather than wait for the whole walk. */
+        let mut writer = NdjsonWriter::new(std::io::stdout()).set_resolve_names(true);
+        return common::walk_with(&mut session, &root, args.repetitions, |(oid, value)| {
+            Ok(writer.write_var(oid, &value)?)
+        })
+        .await;
+    }
+    let vars = common::walk(&mut session, &root, args.repetitions).await?;
+    common::print_vars(args.session.output, &vars)
+}
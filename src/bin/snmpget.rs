@@ -0,0 +1,48 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+mod common;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use common::{CliResult, SessionArgs};
+
+/// Get the values of one or more oids.
+#[derive(Parser, Debug)]
+#[command(name = "snmpget")]
+struct Args {
+    #[command(flatten)]
+    session: SessionArgs,
+    /// The oids to get (numeric or symbolic).
+    #[arg(required = true)]
+    oids: Vec<String>,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    common::exit(run(Args::parse()).await)
+}
+
+async fn run(args: Args) -> CliResult<()> {
+    let snmp = netsnmp::init("snmpget");
+    let mut session = args.session.open(&snmp)?;
+    let oids = args
+        .oids
+        .iter()
+        .map(|oid| common::parse_oid(oid))
+        .collect::<CliResult<Vec<_>>>()?;
+    let vars = session.get_values_async(&oids).await?;
+    common::print_vars(args.session.output, &vars)
+}
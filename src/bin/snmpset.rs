@@ -0,0 +1,56 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+mod common;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use common::{CliResult, SessionArgs};
+
+/// Set the values of one or more oids.
+#[derive(Parser, Debug)]
+#[command(name = "snmpset")]
+struct Args {
+    #[command(flatten)]
+    session: SessionArgs,
+    /// Triples of oid, type (i u c C t a o s x d U I F D) and value.
+    #[arg(required = true, num_args = 3.., value_names = ["OID", "TYPE", "VALUE"])]
+    vars: Vec<String>,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    common::exit(run(Args::parse()).await)
+}
+
+async fn run(args: Args) -> CliResult<()> {
+    let triples = args.vars.chunks_exact(3);
+    if !triples.remainder().is_empty() {
+        return Err("Expected triples of oid, type and value".into());
+    }
+    let vars = triples
+        .map(|var| {
+            Ok((
+                common::parse_oid(&var[0])?,
+                common::parse_value(&var[1], &var[2])?,
+            ))
+        })
+        .collect::<CliResult<Vec<_>>>()?;
+
+    let snmp = netsnmp::init("snmpset");
+    let mut session = args.session.open(&snmp)?;
+    let response = session.set_async(&vars).await?;
+    common::print_vars(args.session.output, &common::response_vars(&response)?)
+}
@@ -0,0 +1,65 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use clap::Parser;
+use common::{CliResult, SessionArgs};
+use netsnmp::Oid;
+
+/// Walk a conceptual table and print it by row.
+#[derive(Parser, Debug)]
+#[command(name = "snmptable")]
+struct Args {
+    #[command(flatten)]
+    session: SessionArgs,
+    /// Max-repetitions per request.
+    #[arg(long, default_value_t = 10)]
+    repetitions: usize,
+    /// The table (eg. IF-MIB::ifTable or 1.3.6.1.2.1.2.2).
+    table: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    common::exit(run(Args::parse()).await)
+}
+
+async fn run(args: Args) -> CliResult<()> {
+    let snmp = netsnmp::init("snmptable");
+    let mut session = args.session.open(&snmp)?;
+    let table = common::parse_oid(&args.table)?;
+
+    /* Instances are table.entry.column.index. */
+    let entry = table.len() + 1;
+    let mut columns = Vec::new();
+    let mut rows = BTreeMap::new();
+    for (oid, value) in common::walk(&mut session, &table, args.repetitions).await? {
+        let (column, index) = match oid.as_slice().get(entry..) {
+            Some([column, index @ ..]) if !index.is_empty() => (*column, Oid::from_slice(index)),
+            _ => continue,
+        };
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+        rows.entry(index)
+            .or_insert_with(BTreeMap::new)
+            .insert(column, value);
+    }
+    columns.sort_unstable();
+    common::print_table(args.session.output, &columns, &rows)
+}
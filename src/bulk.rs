@@ -0,0 +1,142 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Splitting of GETBULK responses. A response holds the results of
+ * the non-repeaters, followed by rows with one varbind per repeater
+ * (RFC 3416, section 4.2.3). The last row may be incomplete when the
+ * agent had to leave out varbinds to fit the message size. */
+
+use super::error::{Error, Result};
+use super::oid::Oid;
+use super::pdu::PduPtr;
+use super::types::ErrType;
+use super::validate::{check_next, VarResults};
+use super::variable::VariablePtr;
+
+/// A GETBULK response, split into the results of the non-repeaters
+/// and a column per repeater.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkResponse {
+    pub non_repeaters: VarResults,
+    pub columns: Vec<BulkColumn>,
+}
+
+/// The results of a repeater.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkColumn {
+    /// The subtree that is walked.
+    pub root: Oid,
+    /// The variables in the subtree, in order.
+    pub vars: VarResults,
+    /// The oid to continue from in the next round, or None if the
+    /// walk left the subtree or reached the end of the mib view.
+    pub next: Option<Oid>,
+}
+
+impl BulkResponse {
+    /// Split the response to a GETBULK request for `non_repeaters`
+    /// followed by `repeaters`, walking the subtrees of the repeaters.
+    pub fn split(non_repeaters: &[Oid], repeaters: &[Oid], response: &PduPtr) -> Result<Self> {
+        let repeaters: Vec<_> = repeaters
+            .iter()
+            .map(|oid| (oid.clone(), oid.clone()))
+            .collect();
+        Self::split_from(non_repeaters, &repeaters, response)
+    }
+
+    /// Split the response to a follow-up request; `repeaters` holds the
+    /// subtree root and the requested oid of each repeater (see
+    /// `next_repeaters`). Fails if there are repeaters but the response
+    /// holds no rows, as the walk would not make progress.
+    pub fn split_from(
+        non_repeaters: &[Oid],
+        repeaters: &[(Oid, Oid)],
+        response: &PduPtr,
+    ) -> Result<Self> {
+        if let Some(err) = response.error() {
+            return Err(err);
+        }
+        let vars: Vec<_> = response.variables().collect();
+        if vars.len() < non_repeaters.len() {
+            return Err(Error::VarBindCount(non_repeaters.len(), vars.len()));
+        }
+
+        let (first, rows) = vars.split_at(non_repeaters.len());
+        let non_repeaters = non_repeaters
+            .iter()
+            .zip(first)
+            .map(|(oid, var)| check_next(oid, var))
+            .collect::<Result<_>>()?;
+
+        let mut columns: Vec<_> = repeaters
+            .iter()
+            .map(|(root, from)| BulkColumn {
+                root: root.clone(),
+                vars: Vec::new(),
+                next: Some(from.clone()),
+            })
+            .collect();
+        /* Without any rows, a follow-up request would be the same. */
+        if !columns.is_empty() && rows.is_empty() {
+            return Err(Error::NoRepetitions);
+        }
+        if !columns.is_empty() {
+            for row in rows.chunks(columns.len()) {
+                for (column, var) in columns.iter_mut().zip(row) {
+                    column.push(var)?;
+                }
+            }
+        }
+
+        Ok(Self {
+            non_repeaters,
+            columns,
+        })
+    }
+
+    /// Whether all columns are complete (no further round is needed).
+    pub fn is_complete(&self) -> bool {
+        self.columns.iter().all(|column| column.next.is_none())
+    }
+
+    /// The repeaters for the next round: the subtree root and the oid
+    /// to continue from of each column that is not complete.
+    pub fn next_repeaters(&self) -> Vec<(Oid, Oid)> {
+        self.columns
+            .iter()
+            .filter_map(|column| Some((column.root.clone(), column.next.clone()?)))
+            .collect()
+    }
+}
+
+impl BulkColumn {
+    /* Entries after the end of the column are ignored. */
+    fn push(&mut self, var: &VariablePtr) -> Result<()> {
+        let last = match &self.next {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        let name = var.get_name();
+        let value = var.get_value();
+        if value == Err(ErrType::EndOfMibView) || !self.root.contains(&name) {
+            self.next = None;
+        } else if &name <= last {
+            return Err(Error::OidsNotIncreasing);
+        } else {
+            self.next = Some(name.clone());
+            self.vars.push((name, value));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,368 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::error::{Error, Result};
+use super::hex;
+use super::oid::Oid;
+use super::value::Value;
+
+/* Value tags in snmprec files (the BER tag numbers). */
+const REC_INTEGER: u32 = 2;
+const REC_BIT_STR: u32 = 3;
+const REC_OCTET_STR: u32 = 4;
+const REC_NULL: u32 = 5;
+const REC_OBJECT_ID: u32 = 6;
+const REC_IPADDRESS: u32 = 64;
+const REC_COUNTER: u32 = 65;
+const REC_GAUGE: u32 = 66;
+const REC_TIMETICKS: u32 = 67;
+const REC_OPAQUE: u32 = 68;
+const REC_COUNTER64: u32 = 70;
+const REC_NO_SUCH_OBJECT: u32 = 128;
+const REC_NO_SUCH_INSTANCE: u32 = 129;
+const REC_END_OF_MIB_VIEW: u32 = 130;
+
+/// Read a dump file: snmpsim data if the extension is `.snmprec`,
+/// snmpwalk output otherwise.
+pub fn read_dump<P: AsRef<Path>>(path: P) -> Result<BTreeMap<Oid, Value>> {
+    let path = path.as_ref();
+    let data = fs::read(path)
+        .map_err(|e| Error::General(format!("Failed to read {}: {}", path.display(), e)))?;
+    let text = String::from_utf8_lossy(&data);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("snmprec") => parse_snmprec(&text),
+        _ => parse_snmpwalk(&text),
+    }
+}
+
+/// Parse snmpsim data (`oid|tag|value` lines, with a tag suffix of
+/// `x` for hex-encoded values). Error markers are skipped; variation
+/// modules are not supported. Invalid numbers are kept as strings,
+/// with a warning.
+pub fn parse_snmprec(text: &str) -> Result<BTreeMap<Oid, Value>> {
+    let mut vars = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let err = |msg: &str| Error::DumpParse(i + 1, msg.to_string());
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.splitn(3, '|');
+        let (oid, tag, value) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(oid), Some(tag), Some(value)) => (oid, tag, value),
+            _ => return Err(err("expected oid|tag|value")),
+        };
+        let oid = Oid::from_str(oid).map_err(|_| err("invalid oid"))?;
+        if tag.contains(':') {
+            return Err(err("variation modules are not supported"));
+        }
+        let (tag, hex) = match tag.strip_suffix('x') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let tag: u32 = tag.parse().map_err(|_| err("invalid tag"))?;
+        let bytes = match hex {
+            true => hex::decode(value).ok_or_else(|| err("invalid hex value"))?,
+            false => value.as_bytes().to_vec(),
+        };
+        /* Hex numbers are big-endian; integers are two's complement. */
+        let number = || match hex {
+            true => decode_be(&bytes).map(|(n, _)| n),
+            false => value.trim().parse::<u64>().ok(),
+        };
+        let integer = || match hex {
+            true => decode_be(&bytes).map(|(n, len)| sign_extend(n, len)),
+            false => value.trim().parse::<i64>().ok(),
+        };
+
+        let value = match tag {
+            REC_INTEGER => number_or_text(i + 1, integer().map(Value::Integer), &bytes),
+            REC_BIT_STR => Value::BitStr(bytes),
+            REC_OCTET_STR => Value::OctetStr(bytes),
+            REC_NULL => Value::Null,
+            /* Hex oids are the hex encoding of the dotted form. */
+            REC_OBJECT_ID => Value::Oid(
+                std::str::from_utf8(&bytes)
+                    .ok()
+                    .and_then(|oid| Oid::from_str(oid).ok())
+                    .ok_or_else(|| err("invalid oid value"))?,
+            ),
+            REC_IPADDRESS => match (hex, bytes.as_slice()) {
+                (true, [a, b, c, d]) => Value::IpAddress(u32::from_ne_bytes([*a, *b, *c, *d])),
+                (true, _) => return Err(err("invalid ip address")),
+                (false, _) => parse_ip_address(value).ok_or_else(|| err("invalid ip address"))?,
+            },
+            REC_COUNTER => number_or_text(i + 1, number().map(Value::Counter), &bytes),
+            REC_GAUGE => number_or_text(i + 1, number().map(Value::Gauge), &bytes),
+            REC_TIMETICKS => number_or_text(i + 1, number().map(Value::TimeTicks), &bytes),
+            REC_OPAQUE => Value::Opaque,
+            REC_COUNTER64 => number_or_text(i + 1, number().map(Value::Counter64), &bytes),
+            REC_NO_SUCH_OBJECT | REC_NO_SUCH_INSTANCE | REC_END_OF_MIB_VIEW => continue,
+            _ => return Err(err("unsupported tag")),
+        };
+        vars.insert(oid, value);
+    }
+    Ok(vars)
+}
+
+/// Parse the output of snmpwalk, with numeric (`-On`) or symbolic
+/// oids. Symbolic oids and values are resolved with the loaded MIBs.
+/// Values rendered with a display hint (eg. DateAndTime) are kept as
+/// strings, except for decimal hints on numbers ("23.5"); use `-On
+/// -Ox` for an exact replay.
+pub fn parse_snmpwalk(text: &str) -> Result<BTreeMap<Oid, Value>> {
+    let mut vars = BTreeMap::new();
+    let mut current: Option<(usize, &str, String)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+
+        /* Quoted strings can span lines; hex strings are wrapped. */
+        if let Some((_, _, value)) = &mut current {
+            if is_open_string(value) {
+                value.push('\n');
+                value.push_str(line);
+                continue;
+            }
+            if is_hex_continuation(value, line) {
+                value.push(' ');
+                value.push_str(line.trim());
+                continue;
+            }
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match line.split_once(" = ") {
+            Some((oid, value)) if !oid.trim().contains(' ') => {
+                if let Some(var) = current.take() {
+                    insert_walk_var(&mut vars, var)?;
+                }
+                current = Some((i + 1, oid.trim(), value.to_string()));
+            }
+            _ => match line.trim_end().strip_suffix(" =") {
+                /* An empty value. */
+                Some(oid) if !oid.trim().contains(' ') => {
+                    if let Some(var) = current.take() {
+                        insert_walk_var(&mut vars, var)?;
+                    }
+                    current = Some((i + 1, oid.trim(), String::new()));
+                }
+                _ => {
+                    return Err(Error::DumpParse(
+                        i + 1,
+                        String::from("expected oid = value"),
+                    ))
+                }
+            },
+        }
+    }
+    if let Some(var) = current {
+        insert_walk_var(&mut vars, var)?;
+    }
+    Ok(vars)
+}
+
+fn insert_walk_var(
+    vars: &mut BTreeMap<Oid, Value>,
+    (line, oid, value): (usize, &str, String),
+) -> Result<()> {
+    let err = |msg: &str| Error::DumpParse(line, msg.to_string());
+    let oid = parse_oid(oid).ok_or_else(|| err("invalid oid"))?;
+    if let Some(value) = parse_walk_value(line, &value).map_err(|msg| err(&msg))? {
+        vars.insert(oid, value);
+    }
+    Ok(())
+}
+
+/* Parse a value as printed by net-snmp (mib.c, sprint_realloc_*).
+ * Returns None for exceptions. */
+fn parse_walk_value(line: usize, text: &str) -> std::result::Result<Option<Value>, String> {
+    let text = text.trim();
+    if text.starts_with("No Such Object")
+        || text.starts_with("No Such Instance")
+        || text.starts_with("No more variables")
+    {
+        return Ok(None);
+    }
+    match text {
+        "" | "\"\"" => return Ok(Some(Value::OctetStr(Vec::new()))),
+        "NULL" => return Ok(Some(Value::Null)),
+        _ => {}
+    }
+    /* The value follows the actual type. */
+    let text = match text.strip_prefix("Wrong Type (should be ") {
+        Some(rest) => rest
+            .split_once("): ")
+            .map(|(_, value)| value)
+            .ok_or("invalid wrong type value")?,
+        None => text,
+    };
+    if text.starts_with('"') {
+        return Ok(Some(Value::OctetStr(unquote(text)?)));
+    }
+
+    let (typ, value) = text.split_once(':').ok_or("missing type")?;
+    let value = value.trim();
+    let invalid = |what: &str| format!("invalid {} value: {}", what, value);
+    Ok(Some(match typ {
+        "STRING" => match value.starts_with('"') {
+            true => Value::OctetStr(unquote(value)?),
+            false => Value::OctetStr(value.as_bytes().to_vec()),
+        },
+        "Hex-STRING" => Value::OctetStr(parse_hex_bytes(value).ok_or_else(|| invalid(typ))?),
+        /* The named bits after the octets are ignored. */
+        "BITS" => Value::BitStr(parse_hex_bytes(value).ok_or_else(|| invalid(typ))?),
+        "INTEGER" => number_or_text(line, parse_number(value).map(Value::Integer), value),
+        "Counter32" => number_or_text(line, parse_number(value).map(Value::Counter), value),
+        "Gauge32" | "Unsigned32" | "UInteger32" => {
+            number_or_text(line, parse_number(value).map(Value::Gauge), value)
+        }
+        "Counter64" => number_or_text(line, parse_number(value).map(Value::Counter64), value),
+        "Timeticks" => number_or_text(line, parse_number(value).map(Value::TimeTicks), value),
+        "IpAddress" => parse_ip_address(value).ok_or_else(|| invalid(typ))?,
+        "Network Address" => match hex::decode(value).as_deref() {
+            Some([a, b, c, d]) => Value::IpAddress(u32::from_ne_bytes([*a, *b, *c, *d])),
+            _ => return Err(invalid(typ)),
+        },
+        "OID" => Value::Oid(parse_oid(value).ok_or_else(|| invalid(typ))?),
+        "Opaque" => match value.split_once(':') {
+            Some(("Float", v)) => Value::Float(v.trim().parse().map_err(|_| invalid(typ))?),
+            Some(("Double", v)) => Value::Double(v.trim().parse().map_err(|_| invalid(typ))?),
+            Some(("Int64", v)) => Value::Integer64(v.trim().parse().map_err(|_| invalid(typ))?),
+            Some(("UInt64", v)) => Value::Unsigned64(v.trim().parse().map_err(|_| invalid(typ))?),
+            Some(("Counter64", v)) => Value::Counter64(v.trim().parse().map_err(|_| invalid(typ))?),
+            _ => Value::Opaque,
+        },
+        _ => return Err(format!("unsupported type {}", typ)),
+    }))
+}
+
+fn parse_oid(oid: &str) -> Option<Oid> {
+    match oid.starts_with('.') || oid.starts_with(|c: char| c.is_ascii_digit()) {
+        true => Oid::from_str(oid).ok(),
+        false => Oid::parse_symbol(oid).ok(),
+    }
+}
+
+/* Integers are printed as "5", "up(1)", "300 seconds" or, for
+ * timeticks, "(12345) 0:02:03.45". A "d-n" display hint inserts a
+ * decimal point ("23.5" for 235), which is dropped. */
+fn parse_number<T: FromStr>(value: &str) -> Option<T> {
+    let value = match (value.find('('), value.find(')')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value.split_whitespace().next()?,
+    };
+    value.parse().ok().or_else(|| match value.split_once('.') {
+        Some((int, frac)) if !frac.is_empty() && frac.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{}{}", int, frac).parse().ok()
+        }
+        _ => None,
+    })
+}
+
+/* Keep numbers that cannot be parsed (eg. rendered with another
+ * display hint) as strings, rather than failing the whole file. */
+fn number_or_text<B: AsRef<[u8]>>(line: usize, value: Option<Value>, text: B) -> Value {
+    value.unwrap_or_else(|| {
+        let text = text.as_ref();
+        log::warn!(
+            "line {}: invalid number {:?}, kept as a string",
+            line,
+            String::from_utf8_lossy(text)
+        );
+        Value::OctetStr(text.to_vec())
+    })
+}
+
+/* A big-endian number of at most 8 bytes, with its length. */
+fn decode_be(bytes: &[u8]) -> Option<(u64, usize)> {
+    match bytes.len() {
+        1..=8 => Some((bytes.iter().fold(0, |n, b| n << 8 | *b as u64), bytes.len())),
+        _ => None,
+    }
+}
+
+fn sign_extend(n: u64, len: usize) -> i64 {
+    let shift = 64 - 8 * len as u32;
+    ((n << shift) as i64) >> shift
+}
+
+fn parse_ip_address(value: &str) -> Option<Value> {
+    let addr: Ipv4Addr = value.trim().parse().ok()?;
+    Some(Value::IpAddress(u32::from_ne_bytes(addr.octets())))
+}
+
+/* Hex bytes separated by whitespace; BITS values are followed by the
+ * names of the bits that are set. */
+fn parse_hex_bytes(value: &str) -> Option<Vec<u8>> {
+    value
+        .split_whitespace()
+        .take_while(|byte| byte.len() == 2 && byte.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
+/* Quotes and backslashes in strings are escaped with a backslash. */
+fn unquote(value: &str) -> std::result::Result<Vec<u8>, String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or("unterminated string")?;
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut escaped = false;
+    for b in inner.bytes() {
+        match (escaped, b) {
+            (false, b'\\') => escaped = true,
+            (_, b) => {
+                bytes.push(b);
+                escaped = false;
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+fn is_open_string(value: &str) -> bool {
+    let value = value.trim_start();
+    let value = value.strip_prefix("STRING:").unwrap_or(value).trim_start();
+    let mut chars = match value.strip_prefix('"') {
+        Some(rest) => rest.chars(),
+        None => return false,
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+fn is_hex_continuation(value: &str, line: &str) -> bool {
+    value.trim_start().starts_with("Hex-STRING:")
+        && !line.trim().is_empty()
+        && parse_hex_bytes(line).map(|bytes| bytes.len()) == Some(line.split_whitespace().count())
+}
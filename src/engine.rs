@@ -0,0 +1,160 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use super::error::{Error, Result};
+use super::hex;
+
+/// An SNMP engine id (RFC 3411 SnmpEngineID).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String")]
+#[serde(into = "String")]
+pub struct EngineId(Vec<u8>);
+
+/// The decoded value part of an engine id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineIdFormat {
+    /// Pre-RFC 3411 format: 8 enterprise-specific octets.
+    Legacy(Vec<u8>),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Mac([u8; 6]),
+    Text(String),
+    Octets(Vec<u8>),
+    /// Formats 6-127 are reserved.
+    Reserved(u8, Vec<u8>),
+    /// Formats 128-255 are enterprise specific.
+    Enterprise(u8, Vec<u8>),
+}
+
+/// Engine information of an SNMPv3 peer, as discovered by the
+/// engine id probe. Can be cached and used to seed new sessions
+/// (see `SessionBuilder::set_engine`), to skip the probe round-trip.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EngineInfo {
+    pub engine_id: EngineId,
+    pub engine_boots: u32,
+    pub engine_time: u32,
+}
+
+impl EngineId {
+    pub fn from_slice(slice: &[u8]) -> Self {
+        Self(slice.to_vec())
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The IANA private enterprise number of the engine's vendor.
+    pub fn enterprise(&self) -> Option<u32> {
+        self.0
+            .get(..4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) & 0x7fffffff)
+    }
+
+    /// Decode the engine id value according to its format octet.
+    /// Values that do not match their format (eg. an IPv4 address
+    /// that is not four octets long) are returned as octets. None if
+    /// the engine id is too short to have a format octet.
+    pub fn format(&self) -> Option<EngineIdFormat> {
+        if self.0.len() < 5 {
+            return None;
+        }
+        if self.0[0] & 0x80 == 0 {
+            return Some(EngineIdFormat::Legacy(self.0[4..].to_vec()));
+        }
+        let value = &self.0[5..];
+        let octets = || EngineIdFormat::Octets(value.to_vec());
+        Some(match self.0[4] {
+            1 => <[u8; 4]>::try_from(value)
+                .map(|v| EngineIdFormat::Ipv4(Ipv4Addr::from(v)))
+                .unwrap_or_else(|_| octets()),
+            2 => <[u8; 16]>::try_from(value)
+                .map(|v| EngineIdFormat::Ipv6(Ipv6Addr::from(v)))
+                .unwrap_or_else(|_| octets()),
+            3 => <[u8; 6]>::try_from(value)
+                .map(EngineIdFormat::Mac)
+                .unwrap_or_else(|_| octets()),
+            4 => String::from_utf8(value.to_vec())
+                .map(EngineIdFormat::Text)
+                .unwrap_or_else(|_| octets()),
+            5 => octets(),
+            n @ 6..=127 => EngineIdFormat::Reserved(n, value.to_vec()),
+            n => EngineIdFormat::Enterprise(n, value.to_vec()),
+        })
+    }
+}
+
+impl FromStr for EngineId {
+    type Err = Error;
+
+    /// Parse the hex form, with or without 0x prefix and colons
+    /// (eg. "0x80001f8880...", "80:00:1f:88:80:..."). Engine ids are
+    /// 5 to 32 octets long (RFC 3411).
+    fn from_str(value: &str) -> Result<Self> {
+        match hex::decode(value) {
+            Some(bytes) if (5..=32).contains(&bytes.len()) => Ok(Self(bytes)),
+            _ => Err(Error::EngineIdParseError),
+        }
+    }
+}
+
+impl TryFrom<String> for EngineId {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<EngineId> for String {
+    fn from(id: EngineId) -> String {
+        id.to_string()
+    }
+}
+
+impl fmt::Display for EngineId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(&self.0))
+    }
+}
+
+impl fmt::Display for EngineIdFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colons = |v: &[u8]| hex::encode_with(v, ":");
+        match self {
+            EngineIdFormat::Legacy(v) => write!(f, "legacy {}", colons(v)),
+            EngineIdFormat::Ipv4(addr) => write!(f, "ipv4 {}", addr),
+            EngineIdFormat::Ipv6(addr) => write!(f, "ipv6 {}", addr),
+            EngineIdFormat::Mac(mac) => write!(f, "mac {}", colons(mac)),
+            EngineIdFormat::Text(text) => write!(f, "text {:?}", text),
+            EngineIdFormat::Octets(v) => write!(f, "octets {}", colons(v)),
+            EngineIdFormat::Reserved(n, v) => write!(f, "reserved({}) {}", n, colons(v)),
+            EngineIdFormat::Enterprise(n, v) => write!(f, "enterprise({}) {}", n, colons(v)),
+        }
+    }
+}
@@ -24,17 +24,31 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     General(String),
     Transport(String, String),
+    Connect(String),
+    Disconnected,
+    Timeout,
     Packet(String),
     Response(String),
     Usm(String),
+    Vacm(String),
     KeyError,
+    KeyParseError,
     OidsNotIncreasing,
     OidParseError,
+    EngineIdParseError,
     NoSuchObject(Oid),
+    VarBindCount(usize, usize),
+    VarBindMismatch(Oid, Oid),
+    NoRepetitions,
     InvalidCallbackOp(i32),
     InvalidMsg(u32),
     InvalidVersion(u32),
     UnsupportedVersion(&'static str),
+    UnsupportedValue(String),
+    Ber(String),
+    Pcap(String),
+    DumpParse(usize, String),
+    Json(String),
     NulError(NulError),
 }
 
@@ -43,17 +57,39 @@ impl fmt::Display for Error {
         match self {
             Error::General(msg) => write!(f, "{}", msg),
             Error::Transport(app, addr) => write!(f, "Error in transport {} on {}", app, addr),
+            Error::Connect(peer) => write!(f, "Failed to connect to {}", peer),
+            Error::Disconnected => write!(f, "Connection closed by peer"),
+            Error::Timeout => write!(f, "Timeout"),
             Error::Packet(msg) => write!(f, "Error in packet: {}", msg),
             Error::Response(msg) => write!(f, "{}", msg),
             Error::Usm(msg) => write!(f, "Usm error: {}", msg),
+            Error::Vacm(msg) => write!(f, "Vacm error: {}", msg),
             Error::KeyError => write!(f, "Key loading failed"),
+            Error::KeyParseError => write!(f, "Failed to parse key"),
             Error::OidsNotIncreasing => write!(f, "Oids not increasing"),
             Error::OidParseError => write!(f, "Failed to parse Oid component"),
+            Error::EngineIdParseError => write!(f, "Failed to parse engine id"),
             Error::NoSuchObject(oid) => write!(f, "No such object available at Oid {}", oid),
+            Error::VarBindCount(expected, got) => {
+                write!(f, "Expected {} varbinds in response, got {}", expected, got)
+            }
+            Error::VarBindMismatch(requested, got) => write!(
+                f,
+                "Response varbind {} does not match requested Oid {}",
+                got, requested
+            ),
+            Error::NoRepetitions => write!(f, "GETBULK response holds no rows for the repeaters"),
             Error::InvalidCallbackOp(val) => write!(f, "Invalid callback op code: {}", val),
             Error::InvalidMsg(val) => write!(f, "Invalid message code: {}", val),
             Error::InvalidVersion(val) => write!(f, "Invalid version code: {}", val),
             Error::UnsupportedVersion(val) => write!(f, "Unsupported snmp version: {}", val),
+            Error::UnsupportedValue(val) => write!(f, "Unsupported value for set: {}", val),
+            Error::Ber(msg) => write!(f, "BER coding error: {}", msg),
+            Error::Pcap(msg) => write!(f, "Failed to read capture: {}", msg),
+            Error::DumpParse(line, msg) => {
+                write!(f, "Failed to parse dump at line {}: {}", line, msg)
+            }
+            Error::Json(msg) => write!(f, "Failed to write JSON: {}", msg),
             Error::NulError(err) => write!(f, "{}", err),
         }
     }
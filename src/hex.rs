@@ -0,0 +1,53 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+/* Hex encoding for engine ids and keys. */
+
+/// Decode a hex string, with or without "0x" prefix and colons
+/// (eg. "0x80001f88", "80:00:1f:88"). The digits are decoded straight
+/// into a buffer of the final size, so no copies of key material are
+/// left behind.
+pub(crate) fn decode(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    let digits = value.chars().filter(|c| *c != ':');
+    let len = digits.clone().count();
+    if len % 2 != 0 || !digits.clone().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(len / 2);
+    let mut digits = digits.filter_map(|c| c.to_digit(16));
+    while let (Some(high), Some(low)) = (digits.next(), digits.next()) {
+        bytes.push((high << 4 | low) as u8);
+    }
+    Some(bytes)
+}
+
+/// Encode bytes as lowercase hex, without prefix.
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    encode_with(bytes, "")
+}
+
+/// Encode bytes as lowercase hex, with a separator between bytes
+/// (eg. "80:00:1f:88").
+pub(crate) fn encode_with(bytes: &[u8], sep: &str) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
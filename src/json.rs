@@ -0,0 +1,407 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* JSON interchange format for varbinds.
+ *
+ * Each varbind is one object with stable field names:
+ *
+ *   {"oid":"1.3.6.1.2.1.1.3.0","name":"SNMPv2-MIB::sysUpTime.0",
+ *    "type":"TimeTicks","value":12345,"display":"(12345) 0:02:03.45"}
+ *
+ * - oid: numeric, dotted, without leading dot.
+ * - name: symbolic name from the loaded MIBs; omitted if unresolved
+ *   or not requested.
+ * - type: one of the `VarBindType` names below.
+ * - value: typed JSON value:
+ *   - Integer, Counter32, Gauge32, TimeTicks, Counter64, Integer64,
+ *     Unsigned64, Float, Double: number (64-bit types may exceed
+ *     the range of a double). JSON has no NaN or infinities: these
+ *     floats are the strings "NaN", "Infinity" and "-Infinity".
+ *   - Boolean: true / false.
+ *   - OctetString: the text if it is printable UTF-8, otherwise
+ *     lowercase hex ("00505a9b"); "encoding" tells which.
+ *   - BitString: lowercase hex, with "encoding": "hex".
+ *   - ObjectIdentifier: dotted string.
+ *   - IpAddress: dotted quad; MacAddress: "00:50:56:9a:1b:2c".
+ *   - Null, Sequence, Set, Opaque and exceptions (NoSuchObject,
+ *     NoSuchInstance, EndOfMibView, Undefined): null.
+ *   - NotImplemented: the raw type tag.
+ * - encoding: "utf8" or "hex", only for OctetString and BitString.
+ * - display: the value in the style of net-snmp's output, without
+ *   the type. It is rendered by this crate, not by net-snmp, so it
+ *   does not follow display hints from the MIBs.
+ *
+ * Fields are only ever added, never renamed or removed. NDJSON
+ * streams contain one such object per line. */
+
+use std::io::Write;
+use std::net::Ipv4Addr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use super::error::{Error, Result};
+use super::hex;
+use super::oid::Oid;
+use super::pdu::PduPtr;
+use super::types::ErrType;
+use super::value::Value;
+use super::variable::VariablePtr;
+use super::varlist::VarListPtr;
+
+/// A varbind in the JSON interchange format.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VarBindRecord {
+    pub oid: Oid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: VarBindType,
+    pub value: JsonValue,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+    pub display: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VarBindType {
+    Boolean,
+    Integer,
+    BitString,
+    OctetString,
+    Null,
+    ObjectIdentifier,
+    Sequence,
+    Set,
+    IpAddress,
+    MacAddress,
+    Counter32,
+    Gauge32,
+    TimeTicks,
+    Opaque,
+    Counter64,
+    Float,
+    Double,
+    Integer64,
+    Unsigned64,
+    NoSuchObject,
+    NoSuchInstance,
+    EndOfMibView,
+    Undefined,
+    NotImplemented,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Utf8,
+    Hex,
+}
+
+/// Writes varbinds as newline-delimited JSON, eg. page by page
+/// during a walk.
+pub struct NdjsonWriter<W> {
+    writer: W,
+    resolve_names: bool,
+}
+
+impl VarBindRecord {
+    pub fn new(oid: Oid, value: &std::result::Result<Value, ErrType>) -> Self {
+        let (typ, json, encoding) = match value {
+            Ok(value) => encode_value(value),
+            Err(ErrType::NoSuchObject) => (VarBindType::NoSuchObject, JsonValue::Null, None),
+            Err(ErrType::NoSuchInstance) => (VarBindType::NoSuchInstance, JsonValue::Null, None),
+            Err(ErrType::EndOfMibView) => (VarBindType::EndOfMibView, JsonValue::Null, None),
+            Err(ErrType::Undefined) => (VarBindType::Undefined, JsonValue::Null, None),
+            Err(ErrType::NotImplemented(tag)) => {
+                (VarBindType::NotImplemented, JsonValue::from(*tag), None)
+            }
+        };
+        Self {
+            oid,
+            name: None,
+            typ,
+            value: json,
+            encoding,
+            display: render_value(value),
+        }
+    }
+
+    pub fn from_pdu(pdu: &PduPtr) -> Vec<Self> {
+        pdu.variables().map(Self::from).collect()
+    }
+
+    pub fn from_varlist(vars: &VarListPtr) -> Vec<Self> {
+        vars.into_iter().map(Self::from).collect()
+    }
+
+    /// Fill in the symbolic name from the loaded MIBs.
+    pub fn resolve_name(mut self) -> Self {
+        self.name = self.oid.to_symbol();
+        self
+    }
+
+    /// Decode the record back into a value.
+    pub fn to_value(&self) -> Result<std::result::Result<Value, ErrType>> {
+        let invalid = || {
+            Error::UnsupportedValue(format!(
+                "invalid JSON value for {:?}: {}",
+                self.typ, self.value
+            ))
+        };
+        let u64 = || self.value.as_u64().ok_or_else(invalid);
+        let f64 = || match self.value.as_str() {
+            Some("NaN") => Ok(f64::NAN),
+            Some("Infinity") => Ok(f64::INFINITY),
+            Some("-Infinity") => Ok(f64::NEG_INFINITY),
+            _ => self.value.as_f64().ok_or_else(invalid),
+        };
+        let bytes = || {
+            let s = self.value.as_str().ok_or_else(invalid)?;
+            match self.encoding {
+                Some(Encoding::Hex) => hex::decode(s).ok_or_else(invalid),
+                _ => Ok(s.as_bytes().to_vec()),
+            }
+        };
+        Ok(Ok(match self.typ {
+            VarBindType::Boolean => Value::Boolean(self.value.as_bool().ok_or_else(invalid)?),
+            VarBindType::Integer => Value::Integer(self.value.as_i64().ok_or_else(invalid)?),
+            VarBindType::BitString => Value::BitStr(bytes()?),
+            VarBindType::OctetString => Value::OctetStr(bytes()?),
+            VarBindType::Null => Value::Null,
+            VarBindType::ObjectIdentifier => {
+                Value::Oid(self.value.as_str().ok_or_else(invalid)?.parse()?)
+            }
+            VarBindType::Sequence => Value::Sequence,
+            VarBindType::Set => Value::Set,
+            VarBindType::IpAddress => {
+                let addr: Ipv4Addr = self
+                    .value
+                    .as_str()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(invalid)?;
+                Value::IpAddress(u32::from_ne_bytes(addr.octets()))
+            }
+            VarBindType::MacAddress => {
+                let mac = self
+                    .value
+                    .as_str()
+                    .and_then(hex::decode)
+                    .filter(|mac| mac.len() == 6)
+                    .ok_or_else(invalid)?;
+                Value::MacAddress(mac.iter().fold(0, |acc, b| acc << 8 | *b as u64))
+            }
+            VarBindType::Counter32 => Value::Counter(u64()?),
+            VarBindType::Gauge32 => Value::Gauge(u64()?),
+            VarBindType::TimeTicks => Value::TimeTicks(u64()?),
+            VarBindType::Opaque => Value::Opaque,
+            VarBindType::Counter64 => Value::Counter64(u64()?),
+            VarBindType::Float => Value::Float(f64()? as f32),
+            VarBindType::Double => Value::Double(f64()?),
+            VarBindType::Integer64 => Value::Integer64(self.value.as_i64().ok_or_else(invalid)?),
+            VarBindType::Unsigned64 => Value::Unsigned64(u64()?),
+            VarBindType::NoSuchObject => return Ok(Err(ErrType::NoSuchObject)),
+            VarBindType::NoSuchInstance => return Ok(Err(ErrType::NoSuchInstance)),
+            VarBindType::EndOfMibView => return Ok(Err(ErrType::EndOfMibView)),
+            VarBindType::Undefined => return Ok(Err(ErrType::Undefined)),
+            VarBindType::NotImplemented => {
+                let tag = u64()?.try_into().map_err(|_| invalid())?;
+                return Ok(Err(ErrType::NotImplemented(tag)));
+            }
+        }))
+    }
+}
+
+impl From<&VariablePtr> for VarBindRecord {
+    fn from(var: &VariablePtr) -> Self {
+        Self::new(var.get_name(), &var.get_value())
+    }
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            resolve_names: false,
+        }
+    }
+
+    /// Include symbolic names (from the loaded MIBs).
+    pub fn set_resolve_names(mut self, resolve: bool) -> Self {
+        self.resolve_names = resolve;
+        self
+    }
+
+    pub fn write(&mut self, record: &VarBindRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record).map_err(|e| Error::Json(e.to_string()))?;
+        self.writer
+            .write_all(b"\n")
+            .map_err(|e| Error::Json(e.to_string()))
+    }
+
+    pub fn write_var(
+        &mut self,
+        oid: Oid,
+        value: &std::result::Result<Value, ErrType>,
+    ) -> Result<()> {
+        self.write_record(VarBindRecord::new(oid, value))
+    }
+
+    pub fn write_pdu(&mut self, pdu: &PduPtr) -> Result<()> {
+        pdu.variables()
+            .try_for_each(|var| self.write_record(VarBindRecord::from(var)))
+    }
+
+    pub fn write_varlist(&mut self, vars: &VarListPtr) -> Result<()> {
+        vars.into_iter()
+            .try_for_each(|var| self.write_record(VarBindRecord::from(var)))
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush().map_err(|e| Error::Json(e.to_string()))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_record(&mut self, record: VarBindRecord) -> Result<()> {
+        match self.resolve_names {
+            true => self.write(&record.resolve_name()),
+            false => self.write(&record),
+        }
+    }
+}
+
+fn encode_value(value: &Value) -> (VarBindType, JsonValue, Option<Encoding>) {
+    match value {
+        Value::Boolean(v) => (VarBindType::Boolean, JsonValue::from(*v), None),
+        Value::Integer(v) => (VarBindType::Integer, JsonValue::from(*v), None),
+        Value::BitStr(v) => (
+            VarBindType::BitString,
+            JsonValue::from(hex::encode(v)),
+            Some(Encoding::Hex),
+        ),
+        Value::OctetStr(v) => match printable(v) {
+            true => (
+                VarBindType::OctetString,
+                JsonValue::from(String::from_utf8_lossy(v)),
+                Some(Encoding::Utf8),
+            ),
+            false => (
+                VarBindType::OctetString,
+                JsonValue::from(hex::encode(v)),
+                Some(Encoding::Hex),
+            ),
+        },
+        Value::Null => (VarBindType::Null, JsonValue::Null, None),
+        Value::Oid(oid) => (
+            VarBindType::ObjectIdentifier,
+            JsonValue::from(oid.to_string()),
+            None,
+        ),
+        Value::Sequence => (VarBindType::Sequence, JsonValue::Null, None),
+        Value::Set => (VarBindType::Set, JsonValue::Null, None),
+        Value::IpAddress(v) => (
+            VarBindType::IpAddress,
+            JsonValue::from(Ipv4Addr::from(v.to_ne_bytes()).to_string()),
+            None,
+        ),
+        Value::MacAddress(v) => (
+            VarBindType::MacAddress,
+            JsonValue::from(hex::encode_with(&v.to_be_bytes()[2..], ":")),
+            None,
+        ),
+        Value::Counter(v) => (VarBindType::Counter32, JsonValue::from(*v), None),
+        Value::Gauge(v) => (VarBindType::Gauge32, JsonValue::from(*v), None),
+        Value::TimeTicks(v) => (VarBindType::TimeTicks, JsonValue::from(*v), None),
+        Value::Opaque => (VarBindType::Opaque, JsonValue::Null, None),
+        Value::Counter64(v) => (VarBindType::Counter64, JsonValue::from(*v), None),
+        Value::Float(v) => (VarBindType::Float, encode_float(*v as f64), None),
+        Value::Double(v) => (VarBindType::Double, encode_float(*v), None),
+        Value::Integer64(v) => (VarBindType::Integer64, JsonValue::from(*v), None),
+        Value::Unsigned64(v) => (VarBindType::Unsigned64, JsonValue::from(*v), None),
+    }
+}
+
+fn encode_float(v: f64) -> JsonValue {
+    match v {
+        v if v.is_nan() => JsonValue::from("NaN"),
+        f64::INFINITY => JsonValue::from("Infinity"),
+        f64::NEG_INFINITY => JsonValue::from("-Infinity"),
+        v => JsonValue::from(v),
+    }
+}
+
+/// The value in the style of net-snmp's output (without the type).
+pub fn render_value(value: &std::result::Result<Value, ErrType>) -> String {
+    match value {
+        Ok(Value::Boolean(v)) => v.to_string(),
+        Ok(Value::Integer(v)) => v.to_string(),
+        Ok(Value::BitStr(v)) => hex::encode_with(v, " ").to_uppercase(),
+        Ok(Value::OctetStr(s)) if printable(s) => format!(
+            "\"{}\"",
+            String::from_utf8_lossy(s)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        ),
+        Ok(Value::OctetStr(s)) => hex::encode_with(s, " ").to_uppercase(),
+        Ok(Value::Null) => String::from("NULL"),
+        Ok(Value::Oid(oid)) => format!(".{}", oid),
+        Ok(Value::Sequence | Value::Set | Value::Opaque) => String::new(),
+        Ok(Value::IpAddress(v)) => Ipv4Addr::from(v.to_ne_bytes()).to_string(),
+        Ok(Value::MacAddress(v)) => hex::encode_with(&v.to_be_bytes()[2..], " ").to_uppercase(),
+        Ok(Value::Counter(v) | Value::Gauge(v) | Value::Counter64(v)) => v.to_string(),
+        Ok(Value::TimeTicks(v)) => format_ticks(*v),
+        Ok(Value::Float(v)) => format!("Float: {}", v),
+        Ok(Value::Double(v)) => format!("Double: {}", v),
+        Ok(Value::Integer64(v)) => format!("Int64: {}", v),
+        Ok(Value::Unsigned64(v)) => format!("UInt64: {}", v),
+        Err(ErrType::NoSuchObject) => {
+            String::from("No Such Object available on this agent at this OID")
+        }
+        Err(ErrType::NoSuchInstance) => {
+            String::from("No Such Instance currently exists at this OID")
+        }
+        Err(ErrType::EndOfMibView) => String::from(
+            "No more variables left in this MIB View (It is past the end of the MIB tree)",
+        ),
+        Err(err) => format!("{:?}", err),
+    }
+}
+
+/// Whether net-snmp would print the octet string as text.
+pub fn printable(s: &[u8]) -> bool {
+    std::str::from_utf8(s).is_ok_and(|s| {
+        s.chars()
+            .all(|c| !c.is_control() || c == '\n' || c == '\r' || c == '\t')
+    })
+}
+
+/* "(ticks) [d day(s), ]h:mm:ss.cc" */
+fn format_ticks(ticks: u64) -> String {
+    let (days, rest) = (ticks / 8640000, ticks % 8640000);
+    let time = format!(
+        "{}:{:02}:{:02}.{:02}",
+        rest / 360000,
+        rest / 6000 % 60,
+        rest / 100 % 60,
+        rest % 100
+    );
+    match days {
+        0 => format!("({}) {}", ticks, time),
+        1 => format!("({}) 1 day, {}", ticks, time),
+        _ => format!("({}) {} days, {}", ticks, days, time),
+    }
+}
@@ -0,0 +1,95 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+use serde::{Deserialize, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::error::{Error, Result};
+use super::hex;
+
+/// USM key material (a master key Ku or localized key Kul),
+/// serialized in hex. The hex strings are zeroized after use.
+#[derive(Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(try_from = "String")]
+pub struct Key(Vec<u8>);
+
+impl Key {
+    pub fn from_slice(slice: &[u8]) -> Self {
+        Self(slice.to_vec())
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key(***)")
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl FromStr for Key {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match hex::decode(value) {
+            Some(bytes) if !bytes.is_empty() => Ok(Self(bytes)),
+            _ => Err(Error::KeyParseError),
+        }
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self> {
+        Zeroizing::new(value).parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> String {
+        format!("0x{}", hex::encode(&key.0))
+    }
+}
+
+/* The string is allocated at its final size, so it is not copied
+ * while it is written. */
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut value = Zeroizing::new(String::with_capacity(2 + 2 * self.0.len()));
+        value.push_str("0x");
+        for b in &self.0 {
+            let _ = write!(value, "{:02x}", b);
+        }
+        serializer.serialize_str(&value)
+    }
+}
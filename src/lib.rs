@@ -12,43 +12,136 @@
  * under the License.                                                         * 
  ******************************************************************************/
 
+#[cfg(feature = "netsnmp")]
 pub mod api;
+#[cfg(feature = "netsnmp")]
 mod auth;
+mod ber;
+#[cfg(feature = "netsnmp")]
+mod bulk;
+#[cfg(feature = "netsnmp")]
 mod callback_op;
+#[cfg(feature = "netsnmp")]
+mod dump;
+mod engine;
 mod error;
+mod hex;
+#[cfg(feature = "json")]
+mod json;
+mod key;
+#[cfg(feature = "netsnmp")]
+mod logging;
+#[cfg(feature = "mock")]
+mod mock;
 mod msg;
+#[cfg(feature = "netsnmp")]
 mod multi_session;
+#[cfg(feature = "netsnmp")]
 mod netsnmp;
 mod oid;
+#[cfg(feature = "pcap")]
+mod pcap;
+#[cfg(feature = "netsnmp")]
 mod pdu;
+#[cfg(feature = "netsnmp")]
+mod pool;
+#[cfg(feature = "netsnmp")]
+mod query;
+#[cfg(feature = "netsnmp")]
+mod retry;
+#[cfg(feature = "netsnmp")]
+mod row_status;
+mod secret;
+#[cfg(feature = "netsnmp")]
 mod session;
+#[cfg(feature = "netsnmp")]
 mod session_builder;
+#[cfg(feature = "netsnmp")]
 mod single_session;
+#[cfg(feature = "netsnmp")]
+mod stats;
+#[cfg(feature = "netsnmp")]
 mod transport;
 mod types;
+#[cfg(feature = "netsnmp")]
 mod usm;
+#[cfg(feature = "netsnmp")]
+mod usm_admin;
+#[cfg(feature = "netsnmp")]
+mod vacm;
+#[cfg(feature = "netsnmp")]
+mod validate;
 mod value;
+#[cfg(feature = "netsnmp")]
 mod variable;
+#[cfg(feature = "netsnmp")]
 mod varlist;
 mod version;
 
+#[cfg(feature = "netsnmp")]
 pub use auth::{
-    Auth, V2cAuth, V3Auth, V3AuthParams, V3AuthProtocol, V3Level, V3PrivParams, V3PrivProtocol,
+    Auth, TsmAuth, TsmTransport, V2cAuth, V3Auth, V3AuthParams, V3AuthProtocol, V3Key, V3Level,
+    V3PrivParams, V3PrivProtocol,
 };
+pub use ber::{
+    BerPdu, Message, ScopedPdu, ScopedPduData, TrapV1, UsmSecurityParams, V3Header, VarBind,
+    SECURITY_MODEL_USM,
+};
+#[cfg(feature = "netsnmp")]
+pub use bulk::{BulkColumn, BulkResponse};
+#[cfg(feature = "netsnmp")]
 pub use callback_op::CallbackOp;
+#[cfg(feature = "netsnmp")]
+pub use dump::{parse_snmprec, parse_snmpwalk, read_dump};
+pub use engine::{EngineId, EngineIdFormat, EngineInfo};
 pub use error::{Error, Result};
+#[cfg(feature = "json")]
+pub use json::{printable, render_value, Encoding, NdjsonWriter, VarBindRecord, VarBindType};
+pub use key::Key;
+#[cfg(feature = "mock")]
+pub use mock::{Fault, MockAgent, MockAgentBuilder};
 pub use msg::Msg;
+#[cfg(feature = "netsnmp")]
 pub use multi_session::{MultiSession, MultiSessionPtr};
+#[cfg(feature = "netsnmp")]
 pub use netsnmp::{init, NetSNMP};
 pub use oid::Oid;
+#[cfg(feature = "pcap")]
+pub use pcap::{CapturedMessage, PcapDecoder};
+#[cfg(feature = "netsnmp")]
 pub use pdu::{Pdu, PduPtr};
+#[cfg(feature = "netsnmp")]
+pub use pool::{PoolConfig, SessionLease, SessionPool};
+#[cfg(feature = "netsnmp")]
+pub use retry::RetryPolicy;
+pub use secret::SecretString;
+#[cfg(feature = "netsnmp")]
 pub use session::{SessionInfo, SessionPtr, SyncQuery};
+#[cfg(feature = "netsnmp")]
 pub use session_builder::SessionBuilder;
+#[cfg(feature = "netsnmp")]
 pub use single_session::{SessionRead, SingleSession, SingleSessionPtr};
+#[cfg(feature = "prometheus")]
+pub use stats::SessionCollector;
+#[cfg(feature = "netsnmp")]
+pub use stats::{LatencyHistogram, ReportStats, SessionStats, LATENCY_BUCKETS};
+#[cfg(feature = "netsnmp")]
 pub use transport::{Transport, TransportPtr};
 pub use types::{ErrType, VarType};
-pub use usm::{Usm, UsmUser};
+#[cfg(feature = "netsnmp")]
+pub use usm::{
+    authenticate_message, decode_keychange, decrypt_scoped_pdu, encode_keychange,
+    encrypt_scoped_pdu, generate_ku, generate_kul, Usm, UsmUser,
+};
+#[cfg(feature = "netsnmp")]
+pub use usm_admin::UsmAdmin;
+#[cfg(feature = "netsnmp")]
+pub use vacm::{ContextMatch, SecurityLevel, SecurityModel, VacmAccess, VacmAdmin, VacmView};
+#[cfg(feature = "netsnmp")]
+pub use validate::{validate_get, validate_next, VarResults};
 pub use value::Value;
+#[cfg(feature = "netsnmp")]
 pub use variable::{Variable, VariablePtr};
+#[cfg(feature = "netsnmp")]
 pub use varlist::{VarList, VarListPtr};
 pub use version::Version;
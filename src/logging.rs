@@ -0,0 +1,228 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Routing of net-snmp log messages into `log` (or `tracing`), and
+ * per-request logging. */
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+use super::api;
+use super::error::Result;
+use super::pdu::PduPtr;
+
+thread_local! {
+    /* net-snmp writes debug messages in fragments (token, message,
+     * ...); collect them until the end of the line. */
+    static LINE: RefCell<(c_int, String)> = const { RefCell::new((0, String::new())) };
+}
+
+/// Replace net-snmp's stderr logging by a handler that forwards
+/// messages to the `log` crate (or `tracing`, with the feature).
+pub(crate) fn route_logs() {
+    unsafe {
+        api::snmp_disable_stderrlog();
+        let logh = api::netsnmp_register_loghandler(
+            api::NETSNMP_LOGHANDLER_CALLBACK as c_int,
+            api::LOG_DEBUG as c_int,
+        );
+        if let Some(logh) = logh.as_mut() {
+            logh.handler = Some(log_handler);
+        }
+    }
+}
+
+/// Enable debug output for the given tokens (eg. "usm", "snmp_api").
+pub(crate) fn register_debug_tokens(tokens: &[&str]) -> Result<()> {
+    let tokens = CString::new(tokens.join(","))?;
+    unsafe {
+        api::debug_register_tokens(tokens.as_ptr());
+        api::snmp_set_do_debugging(1);
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn log_handler(
+    _logh: *mut api::netsnmp_log_handler,
+    pri: c_int,
+    msg: *const c_char,
+) -> c_int {
+    let msg = CStr::from_ptr(msg).to_string_lossy();
+    LINE.with(|line| {
+        let mut line = line.borrow_mut();
+        if line.0 != pri && !line.1.is_empty() {
+            let (pri, msg) = (line.0, std::mem::take(&mut line.1));
+            emit(pri, &msg);
+        }
+        line.0 = pri;
+        line.1.push_str(&msg);
+        while let Some(n) = line.1.find('\n') {
+            let msg: String = line.1.drain(..=n).collect();
+            emit(pri, msg.trim_end());
+        }
+    });
+    1
+}
+
+/* Debug lines are prefixed with their token ("usm: ..."). */
+fn split_token(pri: c_int, msg: &str) -> (Option<&str>, &str) {
+    match msg.split_once(": ") {
+        Some((token, msg))
+            if pri == api::LOG_DEBUG as c_int
+                && !token.is_empty()
+                && !token.contains(char::is_whitespace) =>
+        {
+            (Some(token), msg)
+        }
+        _ => (None, msg),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn emit(pri: c_int, msg: &str) {
+    let level = match pri as u32 {
+        api::LOG_EMERG..=api::LOG_ERR => log::Level::Error,
+        api::LOG_WARNING => log::Level::Warn,
+        api::LOG_NOTICE | api::LOG_INFO => log::Level::Info,
+        _ => log::Level::Debug,
+    };
+    match split_token(pri, msg) {
+        (Some(token), msg) => {
+            let target = format!("netsnmp::{}", token);
+            log::log!(target: target.as_str(), level, "{}", msg)
+        }
+        (None, msg) => log::log!(target: "netsnmp", level, "{}", msg),
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn emit(pri: c_int, msg: &str) {
+    let (token, msg) = split_token(pri, msg);
+    match pri as u32 {
+        api::LOG_EMERG..=api::LOG_ERR => tracing::error!(target: "netsnmp", token, "{}", msg),
+        api::LOG_WARNING => tracing::warn!(target: "netsnmp", token, "{}", msg),
+        api::LOG_NOTICE | api::LOG_INFO => tracing::info!(target: "netsnmp", token, "{}", msg),
+        _ => tracing::debug!(target: "netsnmp", token, "{}", msg),
+    }
+}
+
+/// Logging context of a single request: a span with the peer,
+/// request id and message type.
+#[cfg(feature = "tracing")]
+pub(crate) struct RequestLog(tracing::Span);
+
+/// Logging context of a single request.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct RequestLog {
+    peer: String,
+    reqid: i64,
+    msg: String,
+}
+
+impl RequestLog {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn new(peer: Option<String>, pdu: &PduPtr) -> Self {
+        Self(tracing::debug_span!(
+            target: "netsnmp",
+            "snmp_request",
+            peer = peer.unwrap_or_default(),
+            reqid = pdu.request_id(),
+            msg = msg_name(pdu),
+        ))
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn new(peer: Option<String>, pdu: &PduPtr) -> Self {
+        Self {
+            peer: peer.unwrap_or_default(),
+            reqid: pdu.request_id(),
+            msg: msg_name(pdu),
+        }
+    }
+
+    pub(crate) fn sync<T, F: FnOnce() -> Result<T>>(&self, request: F) -> Result<T> {
+        #[cfg(feature = "tracing")]
+        let _guard = self.0.enter();
+        self.sent();
+        let result = request();
+        self.done(&result);
+        result
+    }
+
+    pub(crate) async fn instrument<T, F>(&self, request: F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        let request = async {
+            self.sent();
+            let result = request.await;
+            self.done(&result);
+            result
+        };
+        #[cfg(feature = "tracing")]
+        let request = tracing::Instrument::instrument(request, self.0.clone());
+        request.await
+    }
+
+    #[cfg(feature = "tracing")]
+    fn sent(&self) {
+        tracing::debug!(target: "netsnmp", "request sent");
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn sent(&self) {
+        log::debug!(
+            target: "netsnmp",
+            "Sending {} request {} to {}",
+            self.msg,
+            self.reqid,
+            self.peer
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    fn done<T>(&self, result: &Result<T>) {
+        match result {
+            Ok(_) => tracing::debug!(target: "netsnmp", "response received"),
+            Err(err) => tracing::debug!(target: "netsnmp", error = %err, "request failed"),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn done<T>(&self, result: &Result<T>) {
+        match result {
+            Ok(_) => log::debug!(
+                target: "netsnmp",
+                "Received response to request {} from {}",
+                self.reqid,
+                self.peer
+            ),
+            Err(err) => log::debug!(
+                target: "netsnmp",
+                "Request {} to {} failed: {}",
+                self.reqid,
+                self.peer,
+                err
+            ),
+        }
+    }
+}
+
+fn msg_name(pdu: &PduPtr) -> String {
+    match pdu.command() {
+        Ok(msg) => format!("{:?}", msg),
+        Err(_) => String::from("Unknown"),
+    }
+}
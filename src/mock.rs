@@ -0,0 +1,892 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::mem;
+use std::net::SocketAddr;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+
+use super::auth::{V3Level, V3PrivProtocol};
+use super::ber::{
+    BerPdu, Message, ScopedPdu, ScopedPduData, UsmSecurityParams, V3Header, VarBind,
+    SECURITY_MODEL_USM,
+};
+use super::dump::read_dump;
+use super::engine::EngineId;
+use super::error::{Error, Result};
+use super::msg::Msg;
+use super::oid::Oid;
+use super::row_status::{ROW_STATUS_CREATE_AND_GO, ROW_STATUS_DESTROY};
+use super::types::ErrType;
+use super::usm::{self, LocalizedKeys};
+use super::usm_admin::{
+    USM_USER_AUTH_KEY_CHANGE, USM_USER_CLONE_FROM, USM_USER_ENTRY, USM_USER_OWN_AUTH_KEY_CHANGE,
+    USM_USER_OWN_PRIV_KEY_CHANGE, USM_USER_PRIV_KEY_CHANGE, USM_USER_SECURITY_NAME,
+    USM_USER_STATUS,
+};
+use super::value::Value;
+use super::version::Version;
+
+/* Error status codes (RFC 1157, RFC 3416). */
+const TOO_BIG: i64 = 1;
+const NO_SUCH_NAME: i64 = 2;
+const BAD_VALUE: i64 = 3;
+const WRONG_TYPE: i64 = 7;
+const WRONG_VALUE: i64 = 10;
+const NO_CREATION: i64 = 11;
+const INCONSISTENT_VALUE: i64 = 12;
+const NOT_WRITABLE: i64 = 17;
+const INCONSISTENT_NAME: i64 = 18;
+
+/* usmStats (RFC 3414), reported to the manager. */
+const USM_STATS: [u64; 9] = [1, 3, 6, 1, 6, 3, 15, 1, 1];
+const UNSUPPORTED_SEC_LEVELS: u64 = 1;
+const NOT_IN_TIME_WINDOWS: u64 = 2;
+const UNKNOWN_USER_NAMES: u64 = 3;
+const UNKNOWN_ENGINE_IDS: u64 = 4;
+const WRONG_DIGESTS: u64 = 5;
+const DECRYPTION_ERRORS: u64 = 6;
+
+/* The time window for authenticated messages, in seconds. */
+const TIME_WINDOW: i64 = 150;
+
+/// A fault, injected in the response to a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Send the response after a delay.
+    Delay(Duration),
+    /// Do not respond.
+    Drop,
+    /// Respond with a tooBig error.
+    TooBig,
+    /// Return the requested oids from GETNEXT and GETBULK, instead
+    /// of their successors.
+    NonIncreasing,
+    /// Return the varbinds of the response in reverse order.
+    Reorder,
+    /// Leave out the last varbind of the response.
+    DropVarBind,
+}
+
+/// Configuration of a mock agent.
+pub struct MockAgentBuilder {
+    community: Vec<u8>,
+    engine_id: EngineId,
+    users: Vec<(String, V3Level)>,
+    vars: BTreeMap<Oid, Value>,
+    max_size: usize,
+}
+
+/// An in-process SNMP agent for tests. The agent serves an in-memory
+/// map of variables over UDP on 127.0.0.1, for SNMPv1, SNMPv2c and
+/// SNMPv3 (USM), and can inject faults into its responses. Users can
+/// be managed through the usmUserTable. The agent runs on its own
+/// thread, so it can be used from synchronous tests, and is stopped
+/// on drop.
+pub struct MockAgent {
+    addr: SocketAddr,
+    agent: Arc<Agent>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Agent {
+    community: Vec<u8>,
+    engine_id: EngineId,
+    engine_boots: u32,
+    started: Instant,
+    max_size: usize,
+    salt: AtomicU64,
+    state: Mutex<State>,
+}
+
+struct State {
+    vars: BTreeMap<Oid, Value>,
+    users: HashMap<Vec<u8>, LocalizedKeys>,
+    faults: VecDeque<Fault>,
+    requests: usize,
+    usm_stats: BTreeMap<u64, u64>,
+}
+
+/* A response, to be sent after a delay. */
+type Reply = (Vec<u8>, Duration);
+
+impl MockAgentBuilder {
+    pub fn new() -> Self {
+        Self {
+            community: b"public".to_vec(),
+            engine_id: EngineId::from_slice(b"\x80\x00\x1f\x88\x04mock"),
+            users: Vec::new(),
+            vars: BTreeMap::new(),
+            max_size: 65507,
+        }
+    }
+
+    /// Set the community accepted for SNMPv1 and SNMPv2c (default: public).
+    pub fn set_community(mut self, community: &str) -> Self {
+        self.community = community.as_bytes().to_vec();
+        self
+    }
+
+    pub fn set_engine_id(mut self, engine_id: EngineId) -> Self {
+        self.engine_id = engine_id;
+        self
+    }
+
+    /// Add an SNMPv3 user. The user accepts messages at the level of
+    /// the given keys or lower.
+    pub fn add_user(mut self, name: &str, level: V3Level) -> Self {
+        self.users.push((name.to_string(), level));
+        self
+    }
+
+    pub fn set_vars(mut self, vars: BTreeMap<Oid, Value>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    pub fn add_var(mut self, oid: Oid, value: Value) -> Self {
+        self.vars.insert(oid, value);
+        self
+    }
+
+    /// Serve the variables from an snmpwalk or snmprec dump (see
+    /// `read_dump`), in addition to those already added.
+    pub fn load_dump<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.vars.extend(read_dump(path)?);
+        Ok(self)
+    }
+
+    /// Set the maximum size of an encoded response pdu. GETBULK
+    /// responses are truncated to fit; other requests fail with tooBig.
+    pub fn set_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Bind to a random port on 127.0.0.1 and start serving requests
+    /// on a background thread.
+    pub fn start(self) -> Result<MockAgent> {
+        let users = self
+            .users
+            .iter()
+            .map(|(name, level)| {
+                Ok((
+                    name.as_bytes().to_vec(),
+                    LocalizedKeys::new(&level.localize(&self.engine_id)?)?,
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let agent = Arc::new(Agent {
+            community: self.community,
+            engine_id: self.engine_id,
+            engine_boots: 1,
+            started: Instant::now(),
+            max_size: self.max_size,
+            salt: AtomicU64::new(0),
+            state: Mutex::new(State {
+                vars: self.vars,
+                users,
+                faults: VecDeque::new(),
+                requests: 0,
+                usm_stats: BTreeMap::new(),
+            }),
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(io_error)?;
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").map_err(io_error)?;
+        socket.set_nonblocking(true).map_err(io_error)?;
+        let addr = socket.local_addr().map_err(io_error)?;
+        let socket = runtime
+            .block_on(async { UdpSocket::from_std(socket) })
+            .map_err(io_error)?;
+
+        let (shutdown, stop) = oneshot::channel();
+        let thread = {
+            let agent = agent.clone();
+            thread::spawn(move || {
+                runtime.block_on(async move {
+                    tokio::select! {
+                        _ = agent.serve(Arc::new(socket)) => {}
+                        _ = stop => {}
+                    }
+                })
+            })
+        };
+
+        Ok(MockAgent {
+            addr,
+            agent,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Default for MockAgentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockAgent {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The peer name to pass to `SessionBuilder::set_peer`.
+    pub fn peer(&self) -> String {
+        format!("udp:{}", self.addr)
+    }
+
+    pub fn engine_id(&self) -> &EngineId {
+        &self.agent.engine_id
+    }
+
+    pub fn get(&self, oid: &Oid) -> Option<Value> {
+        self.agent.state().vars.get(oid).cloned()
+    }
+
+    pub fn insert(&self, oid: Oid, value: Value) -> Option<Value> {
+        self.agent.state().vars.insert(oid, value)
+    }
+
+    pub fn remove(&self, oid: &Oid) -> Option<Value> {
+        self.agent.state().vars.remove(oid)
+    }
+
+    /// Inject a fault in the responses to the next `count` requests.
+    /// Faults are applied in order, one per request. SNMPv3 discovery
+    /// and other reports are not affected.
+    pub fn inject(&self, fault: Fault, count: usize) {
+        self.agent
+            .state()
+            .faults
+            .extend(std::iter::repeat_n(fault, count));
+    }
+
+    pub fn clear_faults(&self) {
+        self.agent.state().faults.clear();
+    }
+
+    /// The number of requests received (excluding those answered by
+    /// a report).
+    pub fn requests(&self) -> usize {
+        self.agent.state().requests
+    }
+}
+
+impl Drop for MockAgent {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Agent {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    async fn serve(self: Arc<Self>, socket: Arc<UdpSocket>) {
+        let mut buf = vec![0; 65536];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            match self.handle(&buf[..len]) {
+                Some((data, delay)) if delay.is_zero() => {
+                    let _ = socket.send_to(&data, peer).await;
+                }
+                Some((data, delay)) => {
+                    let socket = socket.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        let _ = socket.send_to(&data, peer).await;
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn handle(&self, packet: &[u8]) -> Option<Reply> {
+        match Message::decode(packet).ok()? {
+            Message::Community {
+                version,
+                community,
+                pdu,
+            } => {
+                if community != self.community {
+                    return None;
+                }
+                let (pdu, delay) = self.process(version, &pdu)?;
+                let message = Message::Community {
+                    version,
+                    community,
+                    pdu,
+                };
+                Some((message.encode().ok()?, delay))
+            }
+            Message::V3 {
+                header,
+                security_params,
+                data,
+            } => self.handle_v3(packet, &header, &security_params, data),
+        }
+    }
+
+    /* Message processing (RFC 3412) and USM (RFC 3414, section 3.2). */
+    fn handle_v3(
+        &self,
+        packet: &[u8],
+        header: &V3Header,
+        security_params: &[u8],
+        data: ScopedPduData,
+    ) -> Option<Reply> {
+        if header.security_model != SECURITY_MODEL_USM {
+            return None;
+        }
+        let params = UsmSecurityParams::decode(security_params).ok()?;
+        let auth = header.flags & V3Header::FLAG_AUTH != 0;
+        let privacy = header.flags & V3Header::FLAG_PRIV != 0;
+        if privacy && !auth {
+            return None;
+        }
+
+        if params.engine_id != self.engine_id.as_slice() {
+            return self.report(header, &params, &data, UNKNOWN_ENGINE_IDS, None);
+        }
+        let user = self.state().users.get(&params.user_name).cloned();
+        let user = match &user {
+            Some(user) => user,
+            None => return self.report(header, &params, &data, UNKNOWN_USER_NAMES, None),
+        };
+        if (auth && user.auth.is_none()) || (privacy && user.privacy.is_none()) {
+            return self.report(header, &params, &data, UNSUPPORTED_SEC_LEVELS, None);
+        }
+
+        if let Some((protocol, key)) = user.auth.as_ref().filter(|_| auth) {
+            let digest = zero_auth_params(packet, &params.auth_params)
+                .and_then(|message| usm::authenticate_message(protocol, key, &message).ok());
+            if digest.as_ref() != Some(&params.auth_params) {
+                return self.report(header, &params, &data, WRONG_DIGESTS, None);
+            }
+            if params.engine_boots != self.engine_boots as i64
+                || (params.engine_time - self.engine_time() as i64).abs() > TIME_WINDOW
+            {
+                return self.report(header, &params, &data, NOT_IN_TIME_WINDOWS, Some(user));
+            }
+        }
+
+        let scoped = match (data, user.privacy.as_ref().filter(|_| privacy)) {
+            (ScopedPduData::Plain(scoped), None) => scoped,
+            (ScopedPduData::Encrypted(data), Some((protocol, key))) => {
+                match usm::decrypt_scoped_pdu(
+                    protocol,
+                    key,
+                    &params.priv_params,
+                    params.engine_boots as u32,
+                    params.engine_time as u32,
+                    &data,
+                )
+                .and_then(|plain| ScopedPdu::decode_padded(&plain))
+                {
+                    Ok(scoped) => scoped,
+                    Err(_) => {
+                        let data = ScopedPduData::Encrypted(data);
+                        return self.report(header, &params, &data, DECRYPTION_ERRORS, None);
+                    }
+                }
+            }
+            _ => return None,
+        };
+
+        let (pdu, delay) = self.process(Version::V3, &scoped.pdu)?;
+        let response = ScopedPdu { pdu, ..scoped };
+        let flags = header.flags & (V3Header::FLAG_AUTH | V3Header::FLAG_PRIV);
+        let data = self.encode_v3(header.msg_id, flags, &params.user_name, user, response)?;
+        Some((data, delay))
+    }
+
+    /* Send a report, if the request is reportable. Only
+     * notInTimeWindow reports are authenticated, so that managers can
+     * synchronize their clock. */
+    fn report(
+        &self,
+        header: &V3Header,
+        params: &UsmSecurityParams,
+        data: &ScopedPduData,
+        stat: u64,
+        user: Option<&LocalizedKeys>,
+    ) -> Option<Reply> {
+        if header.flags & V3Header::FLAG_REPORTABLE == 0 {
+            return None;
+        }
+        let count = {
+            let mut state = self.state();
+            let count = state.usm_stats.entry(stat).or_insert(0);
+            *count += 1;
+            *count
+        };
+        let (request_id, context_name) = match data {
+            ScopedPduData::Plain(scoped) => (scoped.pdu.request_id, scoped.context_name.clone()),
+            ScopedPduData::Encrypted(_) => (0, Vec::new()),
+        };
+        let mut pdu = BerPdu::new(Msg::Report, request_id);
+        pdu.varbinds.push(VarBind {
+            oid: Oid::from_slice(&USM_STATS).join([stat, 0]),
+            value: Ok(Value::Counter(count)),
+        });
+        let scoped = ScopedPdu {
+            context_engine_id: self.engine_id.as_slice().to_vec(),
+            context_name,
+            pdu,
+        };
+        let no_auth = LocalizedKeys::default();
+        let (flags, user) = match user {
+            Some(user) => (V3Header::FLAG_AUTH, user),
+            None => (0, &no_auth),
+        };
+        let data = self.encode_v3(header.msg_id, flags, &params.user_name, user, scoped)?;
+        Some((data, Duration::ZERO))
+    }
+
+    fn encode_v3(
+        &self,
+        msg_id: i64,
+        flags: u8,
+        user_name: &[u8],
+        user: &LocalizedKeys,
+        scoped: ScopedPdu,
+    ) -> Option<Vec<u8>> {
+        let engine_time = self.engine_time();
+        let mut params = UsmSecurityParams {
+            engine_id: self.engine_id.as_slice().to_vec(),
+            engine_boots: self.engine_boots as i64,
+            engine_time: engine_time as i64,
+            user_name: user_name.to_vec(),
+            auth_params: Vec::new(),
+            priv_params: Vec::new(),
+        };
+
+        let data = match user
+            .privacy
+            .as_ref()
+            .filter(|_| flags & V3Header::FLAG_PRIV != 0)
+        {
+            Some((protocol, key)) => {
+                let salt = self.salt.fetch_add(1, Ordering::Relaxed);
+                params.priv_params = match protocol {
+                    V3PrivProtocol::DES => {
+                        [self.engine_boots.to_be_bytes(), (salt as u32).to_be_bytes()].concat()
+                    }
+                    V3PrivProtocol::AES => salt.to_be_bytes().to_vec(),
+                };
+                ScopedPduData::Encrypted(
+                    usm::encrypt_scoped_pdu(
+                        protocol,
+                        key,
+                        &params.priv_params,
+                        self.engine_boots,
+                        engine_time,
+                        &scoped.encode().ok()?,
+                    )
+                    .ok()?,
+                )
+            }
+            None => ScopedPduData::Plain(scoped),
+        };
+
+        let header = V3Header {
+            msg_id,
+            max_size: 65507,
+            flags,
+            security_model: SECURITY_MODEL_USM,
+        };
+        match user
+            .auth
+            .as_ref()
+            .filter(|_| flags & V3Header::FLAG_AUTH != 0)
+        {
+            Some((protocol, key)) => {
+                /* The digest is computed with zeroed authentication
+                 * parameters, which are then replaced in place. */
+                params.auth_params = vec![0; 12];
+                let mut message = Message::V3 {
+                    header,
+                    security_params: params.encode(),
+                    data,
+                };
+                let digest =
+                    usm::authenticate_message(protocol, key, &message.encode().ok()?).ok()?;
+                params.auth_params = digest;
+                if let Message::V3 {
+                    security_params, ..
+                } = &mut message
+                {
+                    *security_params = params.encode();
+                }
+                message.encode().ok()
+            }
+            None => Message::V3 {
+                header,
+                security_params: params.encode(),
+                data,
+            }
+            .encode()
+            .ok(),
+        }
+    }
+
+    fn engine_time(&self) -> u32 {
+        self.started.elapsed().as_secs() as u32
+    }
+
+    /* Answer a request pdu, applying the next fault. */
+    fn process(&self, version: Version, request: &BerPdu) -> Option<(BerPdu, Duration)> {
+        match (request.command, version) {
+            (Msg::GetBulk, Version::V1) => return None,
+            (Msg::Get | Msg::GetNext | Msg::GetBulk | Msg::Set, _) => {}
+            _ => return None,
+        }
+
+        let mut state = self.state();
+        state.requests += 1;
+        let mut delay = Duration::ZERO;
+        let mut increasing = true;
+        let fault = state.faults.pop_front();
+        match fault {
+            Some(Fault::Drop) => return None,
+            Some(Fault::Delay(d)) => delay = d,
+            Some(Fault::TooBig) => return Some((too_big(version, request), delay)),
+            Some(Fault::NonIncreasing) => increasing = false,
+            Some(Fault::Reorder | Fault::DropVarBind) | None => {}
+        }
+
+        let mut response = match request.command {
+            Msg::Get => state.get(version, request),
+            Msg::GetNext => state.get_next(version, request, increasing),
+            Msg::GetBulk => state.get_bulk(request, increasing, self.max_size),
+            _ => state.set(&self.engine_id, version, request),
+        };
+        match fault {
+            Some(Fault::Reorder) => response.varbinds.reverse(),
+            Some(Fault::DropVarBind) => {
+                response.varbinds.pop();
+            }
+            _ => {}
+        }
+        match response.encode().ok()?.len() > self.max_size {
+            true => Some((too_big(version, request), delay)),
+            false => Some((response, delay)),
+        }
+    }
+}
+
+impl State {
+    fn get(&self, version: Version, request: &BerPdu) -> BerPdu {
+        let mut response = BerPdu::new(Msg::Response, request.request_id);
+        for (i, var) in request.varbinds.iter().enumerate() {
+            let value = match self.vars.get(&var.oid) {
+                Some(value) => Ok(value.clone()),
+                None if version == Version::V1 => return error(request, NO_SUCH_NAME, i),
+                None => Err(self.no_such(&var.oid)),
+            };
+            response.varbinds.push(VarBind {
+                oid: var.oid.clone(),
+                value,
+            });
+        }
+        response
+    }
+
+    fn get_next(&self, version: Version, request: &BerPdu, increasing: bool) -> BerPdu {
+        let mut response = BerPdu::new(Msg::Response, request.request_id);
+        for (i, var) in request.varbinds.iter().enumerate() {
+            match self.next(&var.oid, increasing) {
+                Some(next) => response.varbinds.push(next),
+                None if version == Version::V1 => return error(request, NO_SUCH_NAME, i),
+                None => response.varbinds.push(end_of_mib_view(&var.oid)),
+            }
+        }
+        response
+    }
+
+    /* Non-repeaters and max-repetitions are in the error fields. */
+    fn get_bulk(&self, request: &BerPdu, increasing: bool, max_size: usize) -> BerPdu {
+        let mut response = BerPdu::new(Msg::Response, request.request_id);
+        let non_repeaters = (request.error_status.max(0) as usize).min(request.varbinds.len());
+        let max_repetitions = request.error_index.max(0) as usize;
+
+        for var in &request.varbinds[..non_repeaters] {
+            response.varbinds.push(
+                self.next(&var.oid, increasing)
+                    .unwrap_or_else(|| end_of_mib_view(&var.oid)),
+            );
+        }
+
+        let mut oids: Vec<Oid> = request.varbinds[non_repeaters..]
+            .iter()
+            .map(|var| var.oid.clone())
+            .collect();
+        for _ in 0..max_repetitions {
+            if oids.is_empty() || fits(&response, max_size) == Some(false) {
+                break;
+            }
+            let mut ended = true;
+            for oid in oids.iter_mut() {
+                match self.next(oid, increasing) {
+                    Some(next) => {
+                        *oid = next.oid.clone();
+                        response.varbinds.push(next);
+                        ended = false;
+                    }
+                    None => response.varbinds.push(end_of_mib_view(oid)),
+                }
+            }
+            if ended {
+                break;
+            }
+        }
+
+        /* Drop repetitions that do not fit (RFC 3416, section 4.2.3). */
+        while response.varbinds.len() > non_repeaters && fits(&response, max_size) == Some(false) {
+            response.varbinds.pop();
+        }
+        response
+    }
+
+    /* Only existing variables can be set, to a value of the same type. */
+    fn set(&mut self, engine_id: &EngineId, version: Version, request: &BerPdu) -> BerPdu {
+        let usm_user_entry = Oid::from_slice(&USM_USER_ENTRY);
+        if request
+            .varbinds
+            .iter()
+            .any(|var| usm_user_entry.contains(&var.oid))
+        {
+            return self.set_usm(engine_id, request);
+        }
+        for (i, var) in request.varbinds.iter().enumerate() {
+            match (self.vars.get(&var.oid), &var.value) {
+                (Some(old), Ok(new)) if mem::discriminant(old) == mem::discriminant(new) => {}
+                (None, _) if version == Version::V1 => return error(request, NO_SUCH_NAME, i),
+                (None, _) => return error(request, NO_CREATION, i),
+                (Some(_), _) if version == Version::V1 => return error(request, BAD_VALUE, i),
+                (Some(_), _) => return error(request, WRONG_TYPE, i),
+            }
+        }
+        for var in &request.varbinds {
+            if let Ok(value) = &var.value {
+                self.vars.insert(var.oid.clone(), value.clone());
+            }
+        }
+        let mut response = BerPdu::new(Msg::Response, request.request_id);
+        response.varbinds = request.varbinds.clone();
+        response
+    }
+
+    /* The usmUserTable (RFC 3414, section 5): users are created by
+     * cloning another user, have their keys changed with KeyChange
+     * values, and are destroyed. Changes are applied if all varbinds
+     * succeed. The own KeyChange columns are not restricted to the
+     * user itself. */
+    fn set_usm(&mut self, engine_id: &EngineId, request: &BerPdu) -> BerPdu {
+        let mut users = self.users.clone();
+        for (i, var) in request.varbinds.iter().enumerate() {
+            let result = match (usm_user(engine_id, &var.oid), &var.value) {
+                (Some((column, name)), Ok(value)) => {
+                    set_usm_column(&mut users, engine_id, column, name, value)
+                }
+                _ => Err(NO_CREATION),
+            };
+            if let Err(status) = result {
+                return error(request, status, i);
+            }
+        }
+        self.users = users;
+        let mut response = BerPdu::new(Msg::Response, request.request_id);
+        response.varbinds = request.varbinds.clone();
+        response
+    }
+
+    fn next(&self, oid: &Oid, increasing: bool) -> Option<VarBind> {
+        let (next, value) = self
+            .vars
+            .range((Bound::Excluded(oid), Bound::Unbounded))
+            .next()?;
+        Some(VarBind {
+            oid: match increasing {
+                true => next.clone(),
+                false => oid.clone(),
+            },
+            value: Ok(value.clone()),
+        })
+    }
+
+    /* NoSuchInstance if the object (the oid without its last
+     * sub-identifier) has other instances. */
+    fn no_such(&self, oid: &Oid) -> ErrType {
+        let object = Oid::from_slice(&oid.as_slice()[..oid.len().saturating_sub(1)]);
+        match self
+            .vars
+            .range(&object..)
+            .next()
+            .filter(|(next, _)| !object.is_empty() && object.contains(next))
+        {
+            Some(_) => ErrType::NoSuchInstance,
+            None => ErrType::NoSuchObject,
+        }
+    }
+}
+
+fn set_usm_column(
+    users: &mut HashMap<Vec<u8>, LocalizedKeys>,
+    engine_id: &EngineId,
+    column: u64,
+    name: Vec<u8>,
+    value: &Value,
+) -> std::result::Result<(), i64> {
+    match (column, value) {
+        (USM_USER_STATUS, Value::Integer(ROW_STATUS_CREATE_AND_GO)) => {
+            if users.contains_key(&name) {
+                return Err(INCONSISTENT_VALUE);
+            }
+            users.insert(name, LocalizedKeys::default());
+        }
+        (USM_USER_STATUS, Value::Integer(ROW_STATUS_DESTROY)) => {
+            users.remove(&name);
+        }
+        (USM_USER_CLONE_FROM, Value::Oid(template)) => {
+            let template = usm_user(engine_id, template)
+                .filter(|(column, _)| *column == USM_USER_SECURITY_NAME)
+                .and_then(|(_, template)| users.get(&template).cloned())
+                .ok_or(INCONSISTENT_NAME)?;
+            let user = users.get_mut(&name).ok_or(INCONSISTENT_NAME)?;
+            /* A user is cloned only once. */
+            if user.auth.is_none() {
+                *user = template;
+            }
+        }
+        (USM_USER_AUTH_KEY_CHANGE | USM_USER_OWN_AUTH_KEY_CHANGE, Value::OctetStr(kc)) => {
+            let user = users.get_mut(&name).ok_or(INCONSISTENT_NAME)?;
+            let (protocol, key) = user.auth.as_mut().ok_or(INCONSISTENT_VALUE)?;
+            *key = usm::decode_keychange(protocol, key, kc).map_err(|_| WRONG_VALUE)?;
+        }
+        /* Privacy keys are changed with the auth protocol's hash. */
+        (USM_USER_PRIV_KEY_CHANGE | USM_USER_OWN_PRIV_KEY_CHANGE, Value::OctetStr(kc)) => {
+            let user = users.get_mut(&name).ok_or(INCONSISTENT_NAME)?;
+            match (&user.auth, &mut user.privacy) {
+                (Some((protocol, _)), Some((_, key))) => {
+                    *key = usm::decode_keychange(protocol, key, kc).map_err(|_| WRONG_VALUE)?;
+                }
+                _ => return Err(INCONSISTENT_VALUE),
+            }
+        }
+        (USM_USER_STATUS, _) => return Err(WRONG_VALUE),
+        _ => return Err(NOT_WRITABLE),
+    }
+    Ok(())
+}
+
+/* The column and user name of a usmUserTable instance for the agent's
+ * engine. The index is the engine id and the user name, as
+ * length-prefixed octet strings. */
+fn usm_user(engine_id: &EngineId, oid: &Oid) -> Option<(u64, Vec<u8>)> {
+    let (&column, index) = oid
+        .as_slice()
+        .strip_prefix(&USM_USER_ENTRY[..])?
+        .split_first()?;
+    let (engine, index) = index_octets(index)?;
+    let (name, index) = index_octets(index)?;
+    (engine == engine_id.as_slice() && index.is_empty()).then_some((column, name))
+}
+
+fn index_octets(index: &[u64]) -> Option<(Vec<u8>, &[u64])> {
+    let (&len, index) = index.split_first()?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= index.len())?;
+    let octets = index[..len]
+        .iter()
+        .map(|b| u8::try_from(*b).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some((octets, &index[len..]))
+}
+
+fn error(request: &BerPdu, status: i64, index: usize) -> BerPdu {
+    let mut response = BerPdu::new(Msg::Response, request.request_id);
+    response.error_status = status;
+    response.error_index = index as i64 + 1;
+    response.varbinds = request.varbinds.clone();
+    response
+}
+
+/* SNMPv2 tooBig responses have an empty variable list. */
+fn too_big(version: Version, request: &BerPdu) -> BerPdu {
+    let mut response = BerPdu::new(Msg::Response, request.request_id);
+    response.error_status = TOO_BIG;
+    if version == Version::V1 {
+        response.varbinds = request.varbinds.clone();
+    }
+    response
+}
+
+fn end_of_mib_view(oid: &Oid) -> VarBind {
+    VarBind {
+        oid: oid.clone(),
+        value: Err(ErrType::EndOfMibView),
+    }
+}
+
+fn fits(pdu: &BerPdu, max_size: usize) -> Option<bool> {
+    Some(pdu.encode().ok()?.len() <= max_size)
+}
+
+/* Locate the authentication parameters in the received message and
+ * zero them, to verify the digest on the original encoding. */
+fn zero_auth_params(packet: &[u8], auth_params: &[u8]) -> Option<Vec<u8>> {
+    if auth_params.len() != 12 {
+        return None;
+    }
+    let pattern = [&[0x04, 12][..], auth_params].concat();
+    let start = packet
+        .windows(pattern.len())
+        .position(|window| window == pattern)?
+        + 2;
+    let mut message = packet.to_vec();
+    message[start..start + 12].fill(0);
+    Some(message)
+}
+
+fn io_error(err: std::io::Error) -> Error {
+    Error::General(format!("Mock agent: {}", err))
+}
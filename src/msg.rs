@@ -12,38 +12,39 @@
  * under the License.                                                         * 
  ******************************************************************************/
 
-use super::api;
 use super::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/* The discriminants are the BER tags of the PDUs (RFC 3416), which
+ * are also the values of net-snmp's SNMP_MSG_* constants. */
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
 #[repr(u32)]
 pub enum Msg {
-    Get = api::SNMP_MSG_GET,
-    GetNext = api::SNMP_MSG_GETNEXT,
-    Response = api::SNMP_MSG_RESPONSE,
-    Set = api::SNMP_MSG_SET,
-    GetBulk = api::SNMP_MSG_GETBULK,
-    Inform = api::SNMP_MSG_INFORM,
-    Trap = api::SNMP_MSG_TRAP,
-    Trap2 = api::SNMP_MSG_TRAP2,
-    Report = api::SNMP_MSG_REPORT,
+    Get = 0xa0,
+    GetNext = 0xa1,
+    Response = 0xa2,
+    Set = 0xa3,
+    GetBulk = 0xa5,
+    Inform = 0xa6,
+    Trap = 0xa4,
+    Trap2 = 0xa7,
+    Report = 0xa8,
 }
 
 impl TryFrom<u32> for Msg {
     type Error = Error;
     fn try_from(val: u32) -> Result<Self> {
         match val {
-            api::SNMP_MSG_GET => Ok(Self::Get),
-            api::SNMP_MSG_GETNEXT => Ok(Self::GetNext),
-            api::SNMP_MSG_RESPONSE => Ok(Self::Response),
-            api::SNMP_MSG_SET => Ok(Self::Set),
-            api::SNMP_MSG_GETBULK => Ok(Self::GetBulk),
-            api::SNMP_MSG_INFORM => Ok(Self::Inform),
-            api::SNMP_MSG_TRAP => Ok(Self::Trap),
-            api::SNMP_MSG_TRAP2 => Ok(Self::Trap2),
-            api::SNMP_MSG_REPORT => Ok(Self::Report),
+            0xa0 => Ok(Self::Get),
+            0xa1 => Ok(Self::GetNext),
+            0xa2 => Ok(Self::Response),
+            0xa3 => Ok(Self::Set),
+            0xa5 => Ok(Self::GetBulk),
+            0xa6 => Ok(Self::Inform),
+            0xa4 => Ok(Self::Trap),
+            0xa7 => Ok(Self::Trap2),
+            0xa8 => Ok(Self::Report),
             _ => Err(Error::InvalidMsg(val)),
         }
     }
@@ -15,12 +15,15 @@
 use std::ffi::CStr;
 use std::ops::{Deref, DerefMut};
 use std::os::raw::c_char;
+use std::time::Instant;
 use std::{fmt, mem};
 
 use super::api;
 use super::error::{Error, Result};
-use super::pdu::Pdu;
-use super::session::{SessionInfo, SessionPtr, SyncQuery};
+use super::logging::RequestLog;
+use super::pdu::{Pdu, PduPtr};
+use super::session::{check_response, SessionInfo, SessionPtr, SyncQuery};
+use super::version::Version;
 
 /// An owned, heap allocated open session opened via the original multi-session API (not thread safe).
 pub struct MultiSession(*mut api::snmp_session);
@@ -52,28 +55,42 @@ impl MultiSessionPtr {
     pub fn as_raw(&self) -> *const api::snmp_session {
         &self.0 as *const api::snmp_session
     }
+
+    pub(crate) fn transport(&self) -> *mut api::netsnmp_transport {
+        unsafe { api::snmp_sess_transport(api::snmp_sess_pointer(self.as_raw() as *mut _)) }
+    }
 }
 
 impl SyncQuery for MultiSessionPtr {
     fn synch_response(&mut self, pdu: Pdu) -> Result<Pdu> {
-        unsafe {
+        check_response(self.synch_request(pdu)?)
+    }
+
+    fn synch_request(&mut self, pdu: Pdu) -> Result<Pdu> {
+        let log = RequestLog::new(self.peername(), &pdu);
+        log.sync(|| unsafe {
             let mut response = mem::zeroed();
+            let start = Instant::now();
+            self.session().record(|stats| stats.requests += 1);
             let status = api::snmp_synch_response(&mut self.0, pdu.as_raw(), &mut response);
 
             if status == (api::STAT_SUCCESS as i32) {
                 mem::forget(pdu);
-                if (*response).errstat == (api::SNMP_ERR_NOERROR as i64) {
-                    Ok(Pdu::from_raw(response))
-                } else {
-                    let errstr = api::snmp_errstring((*response).errstat as i32);
-                    Err(Error::Packet(
-                        CStr::from_ptr(errstr).to_string_lossy().into_owned(),
-                    ))
-                }
+                self.session().record(|stats| {
+                    stats.record_pdu(PduPtr::from_ptr(response));
+                    stats.latency.observe(start.elapsed());
+                });
+                Ok(Pdu::from_raw(response))
             } else {
+                let errno = self.0.s_snmp_errno;
+                self.session().record(|stats| stats.record_error(errno));
                 Err(self.get_error())
             }
-        }
+        })
+    }
+
+    fn query_version(&self) -> Option<Version> {
+        self.version().ok()
     }
 
     fn send(&mut self, pdu: Pdu) -> Result<()> {
@@ -143,6 +160,8 @@ impl Drop for MultiSession {
             if !self.session().0.callback_magic.is_null() {
                 //mem::drop(Box::from_raw((*self.0).callback_magic));
             }
+            let transport = self.transport();
+            self.session_mut().free_ext(transport);
             api::snmp_close(self.0);
         }
     }
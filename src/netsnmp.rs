@@ -12,11 +12,13 @@
  * under the License.                                                         * 
  ******************************************************************************/
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::mem;
+use std::path::Path;
 
 use super::api;
 use super::error::Result;
+use super::logging;
 use super::session_builder::SessionBuilder;
 use super::transport::Transport;
 
@@ -40,6 +42,20 @@ impl NetSNMP {
         NetSNMP { _app: app }
     }
 
+    /// Initialize the library, reading configuration files and (d)tls
+    /// certificates from `dir` as well as from the default path.
+    /// Certificates go in `dir/tls/certs`, CA certificates in
+    /// `dir/tls/ca-certs` and private keys in `dir/tls/private`.
+    pub fn init_with_config_dir<P: AsRef<Path>>(app: &str, dir: P) -> Result<NetSNMP> {
+        let default = unsafe { CStr::from_ptr(api::get_configuration_directory()) }
+            .to_string_lossy()
+            .into_owned();
+        let path = CString::new(format!("{}:{}", dir.as_ref().display(), default))?;
+        /* The certificate store is loaded by init_snmp. */
+        unsafe { api::set_configuration_directory(path.as_ptr()) };
+        Ok(NetSNMP::init(app))
+    }
+
     pub fn session(&self) -> SessionBuilder {
         SessionBuilder::new(self)
     }
@@ -89,6 +105,18 @@ impl NetSNMP {
         }
     }
 
+    /// Route net-snmp's log messages (including debug output) to the
+    /// `log` crate, or to `tracing` with the "tracing" feature,
+    /// instead of stderr.
+    pub fn route_logs(&self) {
+        logging::route_logs();
+    }
+
+    /// Enable debug output for specific tokens (eg. "usm", "snmp_api").
+    pub fn set_debug_tokens(&self, tokens: &[&str]) -> Result<()> {
+        logging::register_debug_tokens(tokens)
+    }
+
     pub fn set_debug(&self, enable: bool) {
         unsafe {
             api::snmp_set_do_debugging(match enable {
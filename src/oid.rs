@@ -14,10 +14,14 @@
 
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+#[cfg(feature = "netsnmp")]
 use std::ffi::CString;
+use std::fmt;
+#[cfg(feature = "netsnmp")]
+use std::slice;
 use std::str::FromStr;
-use std::{fmt, slice};
 
+#[cfg(feature = "netsnmp")]
 use super::api;
 use super::error::{Error, Result};
 
@@ -48,7 +52,7 @@ impl Oid {
     }
 
     pub fn in_table(&self, table: &Oid) -> Oid {
-        Oid(self.0.iter().skip(table.0.len()).map(|e| *e).collect())
+        Oid(self.0.iter().skip(table.0.len()).copied().collect())
     }
 
     pub fn join<T: IntoIterator<Item = u64>>(&self, oid: T) -> Oid {
@@ -57,6 +61,16 @@ impl Oid {
         new
     }
 
+    /// Append a variable-length octet string index (length-prefixed,
+    /// RFC 2578 section 7.7).
+    pub fn join_octets(&self, octets: &[u8]) -> Oid {
+        let mut new = self.clone();
+        new.0.push(octets.len() as u64);
+        new.0.extend(octets.iter().map(|b| *b as u64));
+        new
+    }
+
+    #[cfg(feature = "netsnmp")]
     pub fn from_symbol<T: AsRef<str>>(sym: T) -> Self {
         unsafe {
             let mut oid = [0; api::MAX_OID_LEN as usize];
@@ -67,8 +81,38 @@ impl Oid {
         }
     }
 
+    /// Resolve a symbolic oid (eg. "IF-MIB::ifDescr.1") using the
+    /// loaded MIBs; fails if it can not be resolved.
+    #[cfg(feature = "netsnmp")]
+    pub fn parse_symbol<T: AsRef<str>>(sym: T) -> Result<Self> {
+        let mut oid = [0; api::MAX_OID_LEN as usize];
+        let mut len = api::MAX_OID_LEN as usize;
+        let symb = CString::new(sym.as_ref())?;
+        match unsafe { api::read_objid(symb.as_ptr(), oid.as_mut_ptr(), &mut len) } {
+            0 => Err(Error::OidParseError),
+            _ => Ok(Self(oid[..len].to_vec())),
+        }
+    }
+
+    /// The symbolic name (eg. "IF-MIB::ifDescr.1") according to the
+    /// loaded MIBs, or None if no module defines this oid.
+    #[cfg(feature = "netsnmp")]
+    pub fn to_symbol(&self) -> Option<String> {
+        let mut buf = [0u8; 1024];
+        let len = unsafe {
+            api::snprint_objid(
+                buf.as_mut_ptr() as *mut _,
+                buf.len(),
+                self.0.as_ptr(),
+                self.0.len(),
+            )
+        };
+        let sym = std::str::from_utf8(buf.get(..usize::try_from(len).ok()?)?).ok()?;
+        sym.contains("::").then(|| sym.to_string())
+    }
+
     pub fn contains(&self, oid: &Oid) -> bool {
-        oid.0.len() >= self.0.len() && &oid.0[..self.0.len()] == &self.0[..]
+        oid.0.len() >= self.0.len() && oid.0[..self.0.len()] == self.0[..]
     }
 
     pub fn len(&self) -> usize {
@@ -101,9 +145,9 @@ impl TryFrom<String> for Oid {
     }
 }
 
-impl Into<String> for Oid {
-    fn into(self) -> String {
-        self.0
+impl From<Oid> for String {
+    fn from(oid: Oid) -> String {
+        oid.0
             .iter()
             .map(|e| e.to_string())
             .collect::<Vec<String>>()
@@ -111,9 +155,9 @@ impl Into<String> for Oid {
     }
 }
 
-impl Into<Oid> for &Oid {
-    fn into(self) -> Oid {
-        self.clone()
+impl From<&Oid> for Oid {
+    fn from(oid: &Oid) -> Oid {
+        oid.clone()
     }
 }
 
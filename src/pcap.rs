@@ -0,0 +1,475 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use super::auth::{V3Auth, V3Key, V3Level};
+use super::ber::{
+    Message, ScopedPdu, ScopedPduData, UsmSecurityParams, V3Header, SECURITY_MODEL_USM,
+};
+use super::engine::EngineId;
+use super::error::{Error, Result};
+use super::usm;
+
+/* Link-layer header types (https://www.tcpdump.org/linktypes.html). */
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IPPROTO_UDP: u8 = 17;
+
+/* pcapng block types. */
+const PCAPNG_SHB: u32 = 0x0a0d0d0a;
+const PCAPNG_IDB: u32 = 0x00000001;
+const PCAPNG_SPB: u32 = 0x00000003;
+const PCAPNG_EPB: u32 = 0x00000006;
+
+/// An SNMP message found in a capture.
+#[derive(Debug)]
+pub struct CapturedMessage {
+    /// Packet number in the capture (starting at 1).
+    pub packet: usize,
+    /// Capture time, since the unix epoch.
+    pub timestamp: Duration,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    /// The decoded message; encrypted SNMPv3 scoped pdus are replaced
+    /// by the decrypted one if credentials are available.
+    pub message: Result<Message>,
+    pub decrypted: bool,
+}
+
+/// Extracts SNMP messages from pcap and pcapng captures.
+pub struct PcapDecoder {
+    ports: Vec<u16>,
+    credentials: Vec<V3Auth>,
+    /* Localized credentials, by engine id and user. */
+    localized: HashMap<(EngineId, Vec<u8>), Option<V3Level>>,
+}
+
+/// A captured packet, before link-layer decoding.
+struct Packet<'a> {
+    timestamp: Duration,
+    linktype: u32,
+    data: &'a [u8],
+}
+
+impl PcapDecoder {
+    /// A decoder for the standard ports (161 and 162).
+    pub fn new() -> Self {
+        Self {
+            ports: vec![161, 162],
+            credentials: Vec::new(),
+            localized: HashMap::new(),
+        }
+    }
+
+    /// Select the UDP ports carrying SNMP traffic.
+    pub fn set_ports(mut self, ports: &[u16]) -> Self {
+        self.ports = ports.to_vec();
+        self
+    }
+
+    /// Add SNMPv3 credentials, used to decrypt messages of their user.
+    pub fn add_credentials(mut self, auth: V3Auth) -> Self {
+        self.credentials.push(auth);
+        self.localized.clear();
+        self
+    }
+
+    pub fn decode_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<CapturedMessage>> {
+        let data = fs::read(path.as_ref())
+            .map_err(|e| Error::Pcap(format!("{}: {}", path.as_ref().display(), e)))?;
+        self.decode_bytes(&data)
+    }
+
+    pub fn decode<R: Read>(&mut self, mut reader: R) -> Result<Vec<CapturedMessage>> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(|e| Error::Pcap(e.to_string()))?;
+        self.decode_bytes(&data)
+    }
+
+    pub fn decode_bytes(&mut self, data: &[u8]) -> Result<Vec<CapturedMessage>> {
+        let packets = match data.get(..4) {
+            Some([0x0a, 0x0d, 0x0d, 0x0a]) => read_pcapng(data)?,
+            Some(_) => read_pcap(data)?,
+            None => return Err(Error::Pcap(String::from("file too short"))),
+        };
+        let mut messages = Vec::new();
+        for (i, packet) in packets.iter().enumerate() {
+            if let Some((source, destination, payload)) = udp_payload(packet) {
+                if self.ports.contains(&source.port()) || self.ports.contains(&destination.port()) {
+                    let (message, decrypted) = self.decode_message(payload);
+                    messages.push(CapturedMessage {
+                        packet: i + 1,
+                        timestamp: packet.timestamp,
+                        source,
+                        destination,
+                        message,
+                        decrypted,
+                    });
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    fn decode_message(&mut self, payload: &[u8]) -> (Result<Message>, bool) {
+        match Message::decode(payload) {
+            Ok(Message::V3 {
+                header,
+                security_params,
+                data: ScopedPduData::Encrypted(data),
+            }) => match self.decrypt(&header, &security_params, &data) {
+                Some(Ok(scoped)) => (
+                    Ok(Message::V3 {
+                        header,
+                        security_params,
+                        data: ScopedPduData::Plain(scoped),
+                    }),
+                    true,
+                ),
+                Some(Err(e)) => (Err(e), false),
+                None => (
+                    Ok(Message::V3 {
+                        header,
+                        security_params,
+                        data: ScopedPduData::Encrypted(data),
+                    }),
+                    false,
+                ),
+            },
+            result => (result, false),
+        }
+    }
+
+    /// Decrypt a scoped pdu; None if no matching credentials are known.
+    fn decrypt(
+        &mut self,
+        header: &V3Header,
+        security_params: &[u8],
+        data: &[u8],
+    ) -> Option<Result<ScopedPdu>> {
+        if header.security_model != SECURITY_MODEL_USM || header.flags & V3Header::FLAG_PRIV == 0 {
+            return None;
+        }
+        let params = match UsmSecurityParams::decode(security_params) {
+            Ok(params) => params,
+            Err(e) => return Some(Err(e)),
+        };
+        let engine_id = EngineId::from_slice(&params.engine_id);
+        let level = self.localized_level(&engine_id, &params.user_name)?;
+        let (protocol, kul) = match &level {
+            V3Level::AuthPriv { privacy, .. } => match &privacy.key {
+                V3Key::LocalizedKey(kul) => (&privacy.protocol, kul),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(
+            usm::decrypt_scoped_pdu(
+                protocol,
+                kul,
+                &params.priv_params,
+                params.engine_boots as u32,
+                params.engine_time as u32,
+                data,
+            )
+            .and_then(|plain| ScopedPdu::decode_padded(&plain)),
+        )
+    }
+
+    fn localized_level(&mut self, engine_id: &EngineId, user: &[u8]) -> Option<V3Level> {
+        let credentials = &self.credentials;
+        self.localized
+            .entry((engine_id.clone(), user.to_vec()))
+            .or_insert_with(|| {
+                credentials.iter().find_map(|auth| match &auth.level {
+                    V3Level::AuthPriv { auth: params, .. } if params.user.as_bytes() == user => {
+                        auth.level.localize(engine_id).ok()
+                    }
+                    _ => None,
+                })
+            })
+            .clone()
+    }
+}
+
+impl Default for PcapDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/* Capture file formats. */
+
+fn read_pcap(data: &[u8]) -> Result<Vec<Packet<'_>>> {
+    let mut reader = ByteReader::new(data, false);
+    let (big_endian, nanos) = match reader.bytes(4)? {
+        [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+        [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+        [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+        [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+        _ => return Err(Error::Pcap(String::from("not a pcap or pcapng file"))),
+    };
+    reader.big_endian = big_endian;
+    reader.bytes(16)?; /* version, thiszone, sigfigs, snaplen */
+    let linktype = reader.u32()? & 0x0fffffff;
+
+    let mut packets = Vec::new();
+    while !reader.is_empty() {
+        let secs = reader.u32()? as u64;
+        let frac = reader.u32()?;
+        let incl_len = reader.u32()? as usize;
+        reader.u32()?; /* orig_len */
+        let timestamp = match nanos {
+            true => Duration::new(secs, frac),
+            false => Duration::new(secs, 0) + Duration::from_micros(frac as u64),
+        };
+        packets.push(Packet {
+            timestamp,
+            linktype,
+            data: reader.bytes(incl_len)?,
+        });
+    }
+    Ok(packets)
+}
+
+fn read_pcapng(data: &[u8]) -> Result<Vec<Packet<'_>>> {
+    let mut reader = ByteReader::new(data, false);
+    /* Link type and timestamp resolution by interface (per section). */
+    let mut interfaces: Vec<(u32, u64)> = Vec::new();
+    let mut packets = Vec::new();
+
+    while !reader.is_empty() {
+        let block_start = reader.pos;
+        let block_type = reader.u32()?;
+        if block_type == PCAPNG_SHB {
+            /* The byte-order magic follows the block length. */
+            reader.big_endian = match data.get(block_start + 8..block_start + 12) {
+                Some([0x1a, 0x2b, 0x3c, 0x4d]) => true,
+                Some([0x4d, 0x3c, 0x2b, 0x1a]) => false,
+                _ => return Err(Error::Pcap(String::from("invalid section header"))),
+            };
+            interfaces.clear();
+        }
+        let block_len = reader.u32()? as usize;
+        if block_len < 12 || block_len % 4 != 0 {
+            return Err(Error::Pcap(String::from("invalid block length")));
+        }
+        let mut body = ByteReader::new(reader.bytes(block_len - 12)?, reader.big_endian);
+        reader.u32()?; /* trailing block length */
+
+        match block_type {
+            PCAPNG_IDB => {
+                let linktype = body.u16()? as u32;
+                body.bytes(6)?; /* reserved, snaplen */
+                let mut units_per_sec = 1_000_000;
+                while body.remaining() >= 4 {
+                    let code = body.u16()?;
+                    let len = body.u16()? as usize;
+                    let value = body.bytes(len)?;
+                    body.bytes((4 - len % 4) % 4)?;
+                    match (code, value) {
+                        (0, _) => break,
+                        /* if_tsresol */
+                        (9, [res]) => {
+                            units_per_sec = match res & 0x80 {
+                                0 => 10u64.checked_pow(*res as u32),
+                                _ => 1u64.checked_shl((res & 0x7f) as u32),
+                            }
+                            .ok_or_else(|| Error::Pcap(String::from("invalid if_tsresol")))?;
+                        }
+                        _ => {}
+                    }
+                }
+                interfaces.push((linktype, units_per_sec));
+            }
+            PCAPNG_EPB => {
+                let interface = body.u32()? as usize;
+                let ts = (body.u32()? as u64) << 32 | body.u32()? as u64;
+                let captured_len = body.u32()? as usize;
+                body.u32()?; /* original length */
+                let (linktype, units_per_sec) = *interfaces
+                    .get(interface)
+                    .ok_or_else(|| Error::Pcap(String::from("unknown interface")))?;
+                packets.push(Packet {
+                    timestamp: Duration::from_secs(ts / units_per_sec)
+                        + Duration::from_nanos(
+                            ((ts % units_per_sec) as u128 * 1_000_000_000 / units_per_sec as u128)
+                                as u64,
+                        ),
+                    linktype,
+                    data: body.bytes(captured_len)?,
+                });
+            }
+            PCAPNG_SPB => {
+                let original_len = body.u32()? as usize;
+                let (linktype, _) = *interfaces
+                    .first()
+                    .ok_or_else(|| Error::Pcap(String::from("unknown interface")))?;
+                packets.push(Packet {
+                    timestamp: Duration::ZERO,
+                    linktype,
+                    data: body.bytes(original_len.min(body.remaining()))?,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(packets)
+}
+
+/* Network layers. */
+
+fn udp_payload<'a>(packet: &Packet<'a>) -> Option<(SocketAddr, SocketAddr, &'a [u8])> {
+    let data = packet.data;
+    let ip = match packet.linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?);
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                offset += 4;
+                ethertype = u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?);
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => data.get(offset + 2..)?,
+                _ => return None,
+            }
+        }
+        LINKTYPE_NULL => data.get(4..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => data,
+        LINKTYPE_LINUX_SLL => data.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => data.get(20..)?,
+        _ => return None,
+    };
+    let (source, destination, udp) = match ip.first()? >> 4 {
+        4 => ipv4_udp(ip)?,
+        6 => ipv6_udp(ip)?,
+        _ => return None,
+    };
+    let source_port = u16::from_be_bytes(udp.get(0..2)?.try_into().ok()?);
+    let destination_port = u16::from_be_bytes(udp.get(2..4)?.try_into().ok()?);
+    let len = u16::from_be_bytes(udp.get(4..6)?.try_into().ok()?) as usize;
+    Some((
+        SocketAddr::new(source, source_port),
+        SocketAddr::new(destination, destination_port),
+        udp.get(8..len.max(8).min(udp.len()))?,
+    ))
+}
+
+fn ipv4_udp(ip: &[u8]) -> Option<(IpAddr, IpAddr, &[u8])> {
+    let header_len = ((ip.first()? & 0x0f) as usize) * 4;
+    let total_len = u16::from_be_bytes(ip.get(2..4)?.try_into().ok()?) as usize;
+    let fragment = u16::from_be_bytes(ip.get(6..8)?.try_into().ok()?);
+    /* Fragmented datagrams are not reassembled. */
+    if fragment & 0x3fff != 0 || *ip.get(9)? != IPPROTO_UDP {
+        return None;
+    }
+    let source = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(12..16)?).ok()?);
+    let destination = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(16..20)?).ok()?);
+    Some((
+        IpAddr::V4(source),
+        IpAddr::V4(destination),
+        ip.get(header_len..total_len.min(ip.len()))?,
+    ))
+}
+
+fn ipv6_udp(ip: &[u8]) -> Option<(IpAddr, IpAddr, &[u8])> {
+    let source = Ipv6Addr::from(<[u8; 16]>::try_from(ip.get(8..24)?).ok()?);
+    let destination = Ipv6Addr::from(<[u8; 16]>::try_from(ip.get(24..40)?).ok()?);
+    let mut next_header = *ip.get(6)?;
+    let mut payload = ip.get(40..)?;
+    loop {
+        match next_header {
+            IPPROTO_UDP => break,
+            /* Hop-by-hop, routing and destination options. */
+            0 | 43 | 60 => {
+                next_header = *payload.first()?;
+                payload = payload.get((*payload.get(1)? as usize + 1) * 8..)?;
+            }
+            /* Fragments are not reassembled. */
+            _ => return None,
+        }
+    }
+    Some((IpAddr::V6(source), IpAddr::V6(destination), payload))
+}
+
+/// Reads integers with the byte order of the capture file.
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8], big_endian: bool) -> Self {
+        Self {
+            data,
+            pos: 0,
+            big_endian,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        match self.data.get(self.pos..self.pos.saturating_add(len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => Err(Error::Pcap(String::from("truncated capture"))),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = <[u8; 2]>::try_from(self.bytes(2)?).unwrap();
+        Ok(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = <[u8; 4]>::try_from(self.bytes(4)?).unwrap();
+        Ok(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+}
@@ -13,13 +13,22 @@
  ******************************************************************************/
 
 use std::convert::TryFrom;
+use std::ffi::CStr;
 use std::ops::{Deref, DerefMut};
-use std::{mem, slice};
+use std::os::raw::{c_char, c_void};
+use std::{mem, ptr, slice};
 
 use super::api;
+use super::ber::{
+    BerPdu, Message, ScopedPdu, ScopedPduData, TrapV1, UsmSecurityParams, V3Header, VarBind,
+    OPAQUE_DOUBLE, OPAQUE_FLOAT, SECURITY_MODEL_USM,
+};
 use super::error::{Error, Result};
 use super::msg::Msg;
 use super::oid::Oid;
+use super::types::{ErrType, VarType};
+use super::value::Value;
+use super::variable::VariablePtr;
 use super::varlist::{VarListIter, VarListPtr};
 use super::version::Version;
 
@@ -61,6 +70,14 @@ impl Pdu {
         Self::new(Msg::GetNext)
     }
 
+    pub fn set() -> Self {
+        Self::new(Msg::Set)
+    }
+
+    pub fn response() -> Self {
+        Self::new(Msg::Response)
+    }
+
     pub fn get_bulk(non_repeaters: i64, max_repetitions: i64) -> Self {
         let pdu = Self::new(Msg::GetBulk);
         unsafe {
@@ -76,6 +93,188 @@ impl Pdu {
             self
         }
     }
+
+    /// Add a variable with a value (for set requests).
+    pub fn add_var(self, oid: &Oid, value: &Value) -> Result<Self> {
+        let (typ, ptr, len) = match value {
+            Value::Integer(v) => (VarType::Integer, as_void(v), mem::size_of_val(v)),
+            Value::Counter(v) => (VarType::Counter, as_void(v), mem::size_of_val(v)),
+            Value::Gauge(v) => (VarType::Gauge, as_void(v), mem::size_of_val(v)),
+            Value::TimeTicks(v) => (VarType::TimeTicks, as_void(v), mem::size_of_val(v)),
+            Value::IpAddress(v) => (VarType::IpAddress, as_void(v), mem::size_of_val(v)),
+            Value::OctetStr(v) => (VarType::OctetStr, v.as_ptr() as *const c_void, v.len()),
+            Value::BitStr(v) => (VarType::BitStr, v.as_ptr() as *const c_void, v.len()),
+            Value::Oid(v) => (
+                VarType::Oid,
+                v.as_ptr() as *const c_void,
+                mem::size_of_val(v.as_slice()),
+            ),
+            Value::Null => (VarType::Null, ptr::null(), 0),
+            Value::Counter64(v) => return self.add_counter64(oid, VarType::Counter64, *v),
+            Value::Unsigned64(v) => return self.add_counter64(oid, VarType::Unsigned64, *v),
+            Value::Integer64(v) => return self.add_counter64(oid, VarType::Integer64, *v as u64),
+            Value::Float(v) => {
+                return self.add_raw_var(oid, OPAQUE_FLOAT, as_void(v), mem::size_of_val(v))
+            }
+            Value::Double(v) => {
+                return self.add_raw_var(oid, OPAQUE_DOUBLE, as_void(v), mem::size_of_val(v))
+            }
+            /* Netsnmp cannot hold booleans, opaques of unknown type,
+             * sequences or sets in a pdu. */
+            Value::Boolean(_)
+            | Value::Sequence
+            | Value::Set
+            | Value::MacAddress(_)
+            | Value::Opaque => {
+                return Err(Error::UnsupportedValue(format!("{:?}", value)));
+            }
+        };
+        self.add_raw_var(oid, typ as u8, ptr, len)
+    }
+
+    /// Add a copy of a variable (eg. from another pdu).
+    pub fn add_variable(self, var: &VariablePtr) -> Result<Self> {
+        let raw = unsafe { &*var.as_ptr() };
+        self.add_raw_var(
+            &var.get_name(),
+            raw.type_,
+            unsafe { raw.val.string } as *const c_void,
+            raw.val_len,
+        )
+    }
+
+    /// Add an exception (noSuchObject, noSuchInstance, endOfMibView).
+    pub fn add_exception(self, oid: &Oid, err: &ErrType) -> Result<Self> {
+        let typ = match err {
+            ErrType::NoSuchObject => api::SNMP_NOSUCHOBJECT,
+            ErrType::NoSuchInstance => api::SNMP_NOSUCHINSTANCE,
+            ErrType::EndOfMibView => api::SNMP_ENDOFMIBVIEW,
+            ErrType::Undefined | ErrType::NotImplemented(_) => {
+                return Err(Error::UnsupportedValue(format!("{:?}", err)));
+            }
+        };
+        self.add_raw_var(oid, typ as u8, ptr::null(), 0)
+    }
+
+    /// Build a netsnmp pdu from a decoded pdu. Values that netsnmp
+    /// cannot represent (booleans, opaques of unknown type and values
+    /// decoded as `ErrType::NotImplemented`) fail with
+    /// `Error::UnsupportedValue`.
+    pub fn from_ber(ber: &BerPdu) -> Result<Self> {
+        let mut pdu = Self::new(ber.command);
+        unsafe {
+            let raw = &mut *pdu.0;
+            raw.reqid = ber.request_id;
+            raw.errstat = ber.error_status;
+            raw.errindex = ber.error_index;
+            if let Some(trap) = &ber.trap {
+                raw.enterprise =
+                    api::snmp_duplicate_objid(trap.enterprise.as_ptr(), trap.enterprise.len());
+                raw.enterprise_length = trap.enterprise.len();
+                raw.agent_addr = trap.agent_addr;
+                raw.trap_type = trap.generic_trap;
+                raw.specific_type = trap.specific_trap;
+                raw.time = trap.time_stamp;
+            }
+        }
+        for varbind in &ber.varbinds {
+            pdu = match &varbind.value {
+                Ok(value) => pdu.add_var(&varbind.oid, value)?,
+                Err(err) => pdu.add_exception(&varbind.oid, err)?,
+            };
+        }
+        Ok(pdu)
+    }
+
+    /// Build a netsnmp pdu from a decoded message. Of the SNMPv3
+    /// security parameters, only the user and engine id are kept.
+    /// Values are converted as in `from_ber`.
+    pub fn from_message(msg: &Message) -> Result<Self> {
+        match msg {
+            Message::Community {
+                version,
+                community,
+                pdu,
+            } => {
+                let pdu = Self::from_ber(pdu)?;
+                unsafe {
+                    let raw = &mut *pdu.0;
+                    raw.version = *version as i64;
+                    set_raw_bytes(&mut raw.community, &mut raw.community_len, community)?;
+                }
+                Ok(pdu)
+            }
+            Message::V3 {
+                header,
+                security_params,
+                data: ScopedPduData::Plain(scoped),
+            } => {
+                let pdu = Self::from_ber(&scoped.pdu)?;
+                unsafe {
+                    let raw = &mut *pdu.0;
+                    raw.version = api::SNMP_VERSION_3 as i64;
+                    raw.msgid = header.msg_id;
+                    raw.msgMaxSize = header.max_size;
+                    raw.securityModel = header.security_model as i32;
+                    raw.securityLevel =
+                        match header.flags & (V3Header::FLAG_AUTH | V3Header::FLAG_PRIV) {
+                            0 => api::SNMP_SEC_LEVEL_NOAUTH,
+                            V3Header::FLAG_AUTH => api::SNMP_SEC_LEVEL_AUTHNOPRIV,
+                            _ => api::SNMP_SEC_LEVEL_AUTHPRIV,
+                        } as i32;
+                    set_raw_bytes(
+                        &mut raw.contextEngineID,
+                        &mut raw.contextEngineIDLen,
+                        &scoped.context_engine_id,
+                    )?;
+                    let mut context_name = raw.contextName as *mut u8;
+                    set_raw_bytes(
+                        &mut context_name,
+                        &mut raw.contextNameLen,
+                        &scoped.context_name,
+                    )?;
+                    raw.contextName = context_name as *mut c_char;
+                    if header.security_model == SECURITY_MODEL_USM {
+                        let usm = UsmSecurityParams::decode(security_params)?;
+                        set_raw_bytes(
+                            &mut raw.securityEngineID,
+                            &mut raw.securityEngineIDLen,
+                            &usm.engine_id,
+                        )?;
+                        let mut security_name = raw.securityName as *mut u8;
+                        set_raw_bytes(
+                            &mut security_name,
+                            &mut raw.securityNameLen,
+                            &usm.user_name,
+                        )?;
+                        raw.securityName = security_name as *mut c_char;
+                    }
+                }
+                Ok(pdu)
+            }
+            Message::V3 { .. } => Err(Error::Ber(String::from(
+                "can not convert an encrypted scoped pdu",
+            ))),
+        }
+    }
+
+    fn add_counter64(self, oid: &Oid, typ: VarType, value: u64) -> Result<Self> {
+        let value = api::counter64 {
+            high: value >> 32,
+            low: value & 0xffffffff,
+        };
+        self.add_raw_var(oid, typ as u8, as_void(&value), mem::size_of_val(&value))
+    }
+
+    fn add_raw_var(self, oid: &Oid, typ: u8, ptr: *const c_void, len: usize) -> Result<Self> {
+        /* The value is copied by netsnmp. */
+        match unsafe { api::snmp_pdu_add_variable(self.0, oid.as_ptr(), oid.len(), typ, ptr, len) }
+            .is_null()
+        {
+            true => Err(Error::General(format!("Failed to add variable {}", oid))),
+            false => Ok(self),
+        }
+    }
 }
 
 impl PduPtr {
@@ -107,6 +306,30 @@ impl PduPtr {
         self.0.command = msg as i32;
     }
 
+    pub fn request_id(&self) -> i64 {
+        self.0.reqid
+    }
+
+    pub fn error_status(&self) -> i64 {
+        self.0.errstat
+    }
+
+    pub fn error_index(&self) -> i64 {
+        self.0.errindex
+    }
+
+    /// The error reported in a response pdu, if any.
+    pub fn error(&self) -> Option<Error> {
+        match self.0.errstat == api::SNMP_ERR_NOERROR as i64 {
+            true => None,
+            false => Some(Error::Packet(unsafe {
+                CStr::from_ptr(api::snmp_errstring(self.0.errstat as i32))
+                    .to_string_lossy()
+                    .into_owned()
+            })),
+        }
+    }
+
     pub fn clear_error(&mut self) {
         self.0.errstat = 0;
         self.0.errindex = 0;
@@ -151,6 +374,96 @@ impl PduPtr {
         unsafe { VarListPtr::from_ptr(self.0.variables).into_iter() }
     }
 
+    /// Convert to a pure-Rust pdu.
+    pub fn to_ber(&self) -> Result<BerPdu> {
+        let command = self.command()?;
+        Ok(BerPdu {
+            command,
+            request_id: self.0.reqid,
+            error_status: self.0.errstat,
+            error_index: self.0.errindex,
+            trap: match command {
+                Msg::Trap => Some(TrapV1 {
+                    enterprise: self.enterprise(),
+                    agent_addr: self.0.agent_addr,
+                    generic_trap: self.0.trap_type,
+                    specific_trap: self.0.specific_type,
+                    time_stamp: self.0.time,
+                }),
+                _ => None,
+            },
+            varbinds: self
+                .variables()
+                .map(|var| VarBind {
+                    oid: var.get_name(),
+                    value: var.get_value(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Convert to a pure-Rust message. SNMPv3 messages are built
+    /// with a plain scoped pdu and empty authentication parameters:
+    /// they are not authenticated or encrypted.
+    pub fn to_message(&self) -> Result<Message> {
+        let pdu = self.to_ber()?;
+        match self.version()? {
+            version @ (Version::V1 | Version::V2c) => Ok(Message::Community {
+                version,
+                community: raw_bytes(self.0.community, self.0.community_len),
+                pdu,
+            }),
+            Version::V3 => {
+                let mut flags = match self.0.securityLevel as u32 {
+                    api::SNMP_SEC_LEVEL_AUTHPRIV => V3Header::FLAG_AUTH | V3Header::FLAG_PRIV,
+                    api::SNMP_SEC_LEVEL_AUTHNOPRIV => V3Header::FLAG_AUTH,
+                    _ => 0,
+                };
+                if matches!(
+                    pdu.command,
+                    Msg::Get | Msg::GetNext | Msg::GetBulk | Msg::Set | Msg::Inform
+                ) {
+                    flags |= V3Header::FLAG_REPORTABLE;
+                }
+                let security_params = match self.0.securityModel as i64 == SECURITY_MODEL_USM {
+                    true => UsmSecurityParams {
+                        engine_id: raw_bytes(self.0.securityEngineID, self.0.securityEngineIDLen),
+                        engine_boots: 0,
+                        engine_time: 0,
+                        user_name: raw_bytes(
+                            self.0.securityName as *const u8,
+                            self.0.securityNameLen,
+                        ),
+                        auth_params: Vec::new(),
+                        priv_params: Vec::new(),
+                    }
+                    .encode(),
+                    false => Vec::new(),
+                };
+                Ok(Message::V3 {
+                    header: V3Header {
+                        msg_id: self.0.msgid,
+                        max_size: self.0.msgMaxSize,
+                        flags,
+                        security_model: self.0.securityModel as i64,
+                    },
+                    security_params,
+                    data: ScopedPduData::Plain(ScopedPdu {
+                        context_engine_id: raw_bytes(
+                            self.0.contextEngineID,
+                            self.0.contextEngineIDLen,
+                        ),
+                        context_name: raw_bytes(
+                            self.0.contextName as *const u8,
+                            self.0.contextNameLen,
+                        ),
+                        pdu,
+                    }),
+                })
+            }
+        }
+    }
+
     pub fn transport_data(&self) -> &mut [u8] {
         unsafe {
             slice::from_raw_parts_mut(
@@ -181,3 +494,32 @@ impl Drop for Pdu {
         }
     }
 }
+
+fn as_void<T>(value: &T) -> *const c_void {
+    value as *const T as *const c_void
+}
+
+fn raw_bytes(ptr: *const u8, len: usize) -> Vec<u8> {
+    match ptr.is_null() {
+        true => Vec::new(),
+        false => unsafe { slice::from_raw_parts(ptr, len).to_vec() },
+    }
+}
+
+/* Buffers in the pdu are freed by snmp_free_pdu. */
+unsafe fn set_raw_bytes(field: &mut *mut u8, len: &mut usize, value: &[u8]) -> Result<()> {
+    let ptr = match value.is_empty() {
+        true => ptr::null_mut(),
+        false => {
+            let ptr = api::netsnmp_memdup(value.as_ptr() as *const c_void, value.len());
+            if ptr.is_null() {
+                return Err(Error::General(String::from("Out of memory")));
+            }
+            ptr as *mut u8
+        }
+    };
+    api::free(*field as *mut c_void);
+    *field = ptr;
+    *len = value.len();
+    Ok(())
+}
@@ -0,0 +1,251 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* A pool of open sessions, to reuse sockets, engine discovery and key
+ * derivation over polling cycles. Sessions are cached per (peer, auth)
+ * and handed out as exclusive leases, which return the session to the
+ * pool when dropped. */
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::auth::Auth;
+use super::error::Result;
+use super::netsnmp::NetSNMP;
+use super::retry::RetryPolicy;
+use super::session::SessionInfo;
+use super::single_session::SingleSession;
+
+/// Configuration of a session pool.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    /// Maximum number of open sessions per target. Further leases
+    /// wait until a session is returned.
+    pub max_sessions: usize,
+    /// Sessions unused for this long are closed.
+    pub idle_timeout: Duration,
+    /// Number of consecutive leases in which requests failed (timed
+    /// out or were answered by a report) and none were answered,
+    /// after which a session is closed, to be re-opened on the next
+    /// lease.
+    pub max_errors: u32,
+    /// Retransmission policy for the sessions.
+    pub retry: RetryPolicy,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions: 4,
+            idle_timeout: Duration::from_secs(300),
+            max_errors: 3,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// A pool of open sessions, keyed by peer and authentication. Clones
+/// share the same sessions.
+#[derive(Clone)]
+pub struct SessionPool(Arc<PoolState>);
+
+/// Exclusive use of a pooled session. The session is returned to the
+/// pool when the lease is dropped, unless it failed too often or a
+/// request on it was interrupted.
+pub struct SessionLease {
+    session: Option<SingleSession>,
+    errors: u32,
+    /* Answered and failed requests at the start of the lease. */
+    answered: u64,
+    failed: u64,
+    target: Target,
+    pool: Arc<PoolState>,
+    _permit: OwnedSemaphorePermit,
+}
+
+struct PoolState {
+    snmp: Arc<NetSNMP>,
+    config: PoolConfig,
+    targets: Mutex<HashMap<Target, TargetSessions>>,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+struct Target {
+    peer: String,
+    auth: Auth,
+}
+
+struct TargetSessions {
+    /* Limits the number of sessions (idle or leased). */
+    permits: Arc<Semaphore>,
+    idle: Vec<IdleSession>,
+}
+
+struct IdleSession {
+    session: SingleSession,
+    errors: u32,
+    since: Instant,
+}
+
+impl SessionPool {
+    pub fn new(snmp: Arc<NetSNMP>, config: PoolConfig) -> Self {
+        Self(Arc::new(PoolState {
+            snmp,
+            config,
+            targets: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn config(&self) -> &PoolConfig {
+        &self.0.config
+    }
+
+    /// Lease a session to `peer`, reusing an idle session if there is
+    /// one. Waits if `max_sessions` sessions to the target are leased.
+    pub async fn lease(&self, peer: &str, auth: &Auth) -> Result<SessionLease> {
+        let target = Target {
+            peer: peer.to_string(),
+            auth: auth.clone(),
+        };
+        let permits = {
+            let mut targets = self.0.targets.lock().unwrap();
+            self.0.evict(&mut targets);
+            targets
+                .entry(target.clone())
+                .or_insert_with(|| TargetSessions {
+                    permits: Arc::new(Semaphore::new(self.0.config.max_sessions)),
+                    idle: Vec::new(),
+                })
+                .permits
+                .clone()
+        };
+        let permit = permits
+            .acquire_owned()
+            .await
+            .expect("pool semaphores are never closed");
+        let idle = self
+            .0
+            .targets
+            .lock()
+            .unwrap()
+            .get_mut(&target)
+            .and_then(|sessions| sessions.idle.pop());
+        let (session, errors) = match idle {
+            Some(idle) => (idle.session, idle.errors),
+            None => (self.0.open(&target)?, 0),
+        };
+        let (answered, failed) = outcomes(&session);
+        Ok(SessionLease {
+            session: Some(session),
+            errors,
+            answered,
+            failed,
+            target,
+            pool: self.0.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Close sessions that have been idle for longer than the idle
+    /// timeout. This is also done on every lease.
+    pub fn evict_idle(&self) {
+        self.0.evict(&mut self.0.targets.lock().unwrap());
+    }
+
+    /// The number of idle sessions in the pool.
+    pub fn idle_sessions(&self) -> usize {
+        let targets = self.0.targets.lock().unwrap();
+        targets.values().map(|sessions| sessions.idle.len()).sum()
+    }
+}
+
+impl PoolState {
+    fn open(&self, target: &Target) -> Result<SingleSession> {
+        self.snmp
+            .session()
+            .set_peer(target.peer.as_bytes())?
+            .set_auth(&target.auth)?
+            .set_retry_policy(self.config.retry.clone())
+            .set_async_probe(true)
+            .open_single()
+    }
+
+    fn evict(&self, targets: &mut HashMap<Target, TargetSessions>) {
+        let now = Instant::now();
+        targets.retain(|_, sessions| {
+            sessions
+                .idle
+                .retain(|idle| now.duration_since(idle.since) < self.config.idle_timeout);
+            /* Keep the target while its semaphore is in use, by leases
+             * or by tasks waiting for one. */
+            !sessions.idle.is_empty() || Arc::strong_count(&sessions.permits) > 1
+        });
+    }
+}
+
+impl SessionLease {
+    /// Close the session instead of returning it to the pool.
+    pub fn discard(mut self) {
+        self.session = None;
+    }
+}
+
+impl Deref for SessionLease {
+    type Target = SingleSession;
+    fn deref(&self) -> &Self::Target {
+        self.session.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for SessionLease {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.session.as_mut().unwrap()
+    }
+}
+
+impl Drop for SessionLease {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            let (answered, failed) = outcomes(&session);
+            if answered > self.answered {
+                self.errors = 0;
+            } else if failed > self.failed {
+                self.errors += 1;
+            }
+            /* An interrupted request may still be outstanding. */
+            if self.errors < self.pool.config.max_errors && !session.session().in_flight() {
+                let mut targets = self.pool.targets.lock().unwrap();
+                /* The target is not evicted while the permit is held. */
+                if let Some(sessions) = targets.get_mut(&self.target) {
+                    sessions.idle.push(IdleSession {
+                        session,
+                        errors: self.errors,
+                        since: Instant::now(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/* The number of answered and failed requests on a session. */
+fn outcomes(session: &SingleSession) -> (u64, u64) {
+    let stats = session.stats();
+    (stats.responses, stats.timeouts + stats.reports.total())
+}
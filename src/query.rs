@@ -0,0 +1,197 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Version-aware queries. SNMPv1 has no GETBULK, and reports a missing
+ * variable with a noSuchName error for the whole request instead of an
+ * exception for the varbind. On SNMPv1 sessions, queries are emulated
+ * with GET and GETNEXT requests, and missing variables are returned as
+ * noSuchObject (GET) or endOfMibView (GETNEXT) exceptions, so that the
+ * result looks like a SNMPv2 response. */
+
+use std::mem;
+
+use super::api;
+use super::error::{Error, Result};
+use super::msg::Msg;
+use super::oid::Oid;
+use super::pdu::{Pdu, PduPtr};
+use super::types::ErrType;
+use super::variable::{Variable, VariablePtr};
+
+/// A high-level query.
+pub(crate) enum Query<'a> {
+    Get(&'a [Oid]),
+    GetNext(&'a [Oid]),
+    GetBulk(&'a [Oid], &'a [Oid], usize),
+}
+
+impl Query<'_> {
+    /// The request for SNMPv2c and SNMPv3 sessions.
+    pub(crate) fn pdu(&self) -> Pdu {
+        let (pdu, oids): (Pdu, Vec<&Oid>) = match self {
+            Query::Get(oids) => (Pdu::get(), oids.iter().collect()),
+            Query::GetNext(oids) => (Pdu::get_next(), oids.iter().collect()),
+            Query::GetBulk(gets, walks, repetitions) => (
+                Pdu::get_bulk(gets.len() as i64, *repetitions as i64),
+                gets.iter().chain(walks.iter()).collect(),
+            ),
+        };
+        oids.into_iter().fold(pdu, |pdu, oid| pdu.add_oid(oid))
+    }
+
+    /// The emulation for SNMPv1 sessions.
+    pub(crate) fn v1(&self) -> V1Query {
+        match self {
+            Query::Get(oids) => V1Query::new(Msg::Get, oids.to_vec(), 0, 0),
+            Query::GetNext(oids) => V1Query::new(Msg::GetNext, oids.to_vec(), 0, 0),
+            Query::GetBulk(gets, _, 0) => V1Query::new(Msg::GetNext, gets.to_vec(), 0, 0),
+            Query::GetBulk(gets, walks, repetitions) => V1Query::new(
+                Msg::GetNext,
+                gets.iter().chain(walks.iter()).cloned().collect(),
+                walks.len(),
+                repetitions - 1,
+            ),
+        }
+    }
+}
+
+/// A query in terms of SNMPv1 requests: send `request()` and pass the
+/// response to `response()` until there are no more requests.
+pub(crate) struct V1Query {
+    command: Msg,
+    /* The oids of the current round, with their results. */
+    round: Vec<(Oid, Option<V1Var>)>,
+    /* The results of the completed rounds. */
+    vars: Vec<V1Var>,
+    /* The number of repeaters and the remaining rounds for them
+     * (GETBULK emulation). */
+    repeaters: usize,
+    repetitions: usize,
+    /* A response with an error other than noSuchName. */
+    failed: Option<Pdu>,
+}
+
+enum V1Var {
+    Var(Variable),
+    Exception(Oid, ErrType),
+}
+
+impl V1Query {
+    fn new(command: Msg, oids: Vec<Oid>, repeaters: usize, repetitions: usize) -> Self {
+        Self {
+            command,
+            round: oids.into_iter().map(|oid| (oid, None)).collect(),
+            vars: Vec::new(),
+            repeaters,
+            repetitions,
+            failed: None,
+        }
+    }
+
+    /// The next request to send, or None if the query is complete.
+    pub(crate) fn request(&self) -> Option<Pdu> {
+        if self.failed.is_some() {
+            return None;
+        }
+        let mut oids = self.pending().map(|i| &self.round[i].0).peekable();
+        oids.peek()?;
+        let pdu = match self.command {
+            Msg::Get => Pdu::get(),
+            _ => Pdu::get_next(),
+        };
+        Some(oids.fold(pdu, |pdu, oid| pdu.add_oid(oid)))
+    }
+
+    pub(crate) fn response(&mut self, response: &PduPtr) -> Result<()> {
+        let pending: Vec<usize> = self.pending().collect();
+        match response.error_status() as u32 {
+            api::SNMP_ERR_NOERROR => {
+                let vars: Vec<_> = response.variables().collect();
+                if vars.len() != pending.len() {
+                    return Err(Error::VarBindCount(pending.len(), vars.len()));
+                }
+                for (i, var) in pending.into_iter().zip(vars) {
+                    self.round[i].1 = Some(V1Var::Var(VariablePtr::to_owned(var)));
+                }
+            }
+            /* The error index is 1-based. */
+            api::SNMP_ERR_NOSUCHNAME
+                if (1..=pending.len() as i64).contains(&response.error_index()) =>
+            {
+                let (oid, result) = &mut self.round[pending[response.error_index() as usize - 1]];
+                *result = Some(V1Var::Exception(
+                    oid.clone(),
+                    match self.command {
+                        Msg::Get => ErrType::NoSuchObject,
+                        _ => ErrType::EndOfMibView,
+                    },
+                ));
+            }
+            _ => self.failed = Some(PduPtr::to_owned(response)),
+        }
+        if self.pending().next().is_none() {
+            self.next_round();
+        }
+        Ok(())
+    }
+
+    /// The response to the query, in SNMPv2 style.
+    pub(crate) fn finish(self) -> Result<Pdu> {
+        if let Some(failed) = self.failed {
+            return Ok(failed);
+        }
+        let mut pdu = Pdu::response();
+        for var in &self.vars {
+            pdu = match var {
+                V1Var::Var(var) => pdu.add_variable(var)?,
+                V1Var::Exception(oid, err) => pdu.add_exception(oid, err)?,
+            };
+        }
+        Ok(pdu)
+    }
+
+    fn pending(&self) -> impl Iterator<Item = usize> + '_ {
+        self.round
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, result))| result.is_none())
+            .map(|(i, _)| i)
+    }
+
+    /* Continue the repeaters from the last row. Columns that reached
+     * the end of the mib view repeat the exception, as SNMPv2 agents
+     * do; no more requests are sent when all columns have ended. */
+    fn next_round(&mut self) {
+        let round = mem::take(&mut self.round);
+        self.vars
+            .extend(round.into_iter().filter_map(|(_, result)| result));
+        if self.repetitions == 0 || self.repeaters == 0 {
+            return;
+        }
+        let row = &self.vars[self.vars.len() - self.repeaters..];
+        if row.iter().all(|var| matches!(var, V1Var::Exception(..))) {
+            return;
+        }
+        self.round = row
+            .iter()
+            .map(|var| match var {
+                V1Var::Var(var) => (var.get_name(), None),
+                V1Var::Exception(oid, err) => {
+                    (oid.clone(), Some(V1Var::Exception(oid.clone(), *err)))
+                }
+            })
+            .collect();
+        self.repetitions -= 1;
+    }
+}
@@ -0,0 +1,96 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Retransmission policy for requests on the async (single session)
+/// path. Attempt `n` (starting at 0) waits `timeout * multiplier^n`,
+/// capped at `max_timeout`, before the request is resent or, after
+/// `retries` resends, fails with `Error::Timeout`. The timeout of each
+/// attempt is varied randomly by up to `jitter` (a fraction) in either
+/// direction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub timeout: Duration,
+    pub multiplier: f64,
+    pub max_timeout: Duration,
+    pub retries: u32,
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// A fixed timeout for every attempt (netsnmp's behaviour).
+    pub fn fixed(timeout: Duration, retries: u32) -> Self {
+        Self {
+            timeout,
+            multiplier: 1.0,
+            max_timeout: timeout,
+            retries,
+            jitter: 0.0,
+        }
+    }
+
+    /// A timeout that grows by `multiplier` after every attempt, up
+    /// to `max_timeout`.
+    pub fn exponential(
+        timeout: Duration,
+        multiplier: f64,
+        max_timeout: Duration,
+        retries: u32,
+    ) -> Self {
+        Self {
+            timeout,
+            multiplier,
+            max_timeout,
+            retries,
+            jitter: 0.0,
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The timeout of attempt `attempt` (0 for the initial request).
+    pub fn attempt_timeout(&self, attempt: u32) -> Duration {
+        let base = self.timeout.as_secs_f64() * self.multiplier.max(1.0).powi(attempt as i32);
+        let base = base.min(self.max_timeout.max(self.timeout).as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0) * (2.0 * random() - 1.0);
+        /* Saturate rather than panic on absurd configurations. */
+        Duration::try_from_secs_f64(base * (1.0 + jitter)).unwrap_or(Duration::MAX)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// The netsnmp defaults: five retries after one second each.
+    fn default() -> Self {
+        Self::fixed(Duration::from_secs(1), 5)
+    }
+}
+
+/* A timeout in microseconds for netsnmp, saturating. */
+pub(crate) fn timeout_micros(timeout: Duration) -> i64 {
+    i64::try_from(timeout.as_micros()).unwrap_or(i64::MAX)
+}
+
+/* A random number in [0, 1), from std's randomly seeded hasher. */
+fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
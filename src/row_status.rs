@@ -0,0 +1,30 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Helpers for writing conceptual rows of MIB tables, shared by the
+ * USM and VACM administration. */
+
+use super::oid::Oid;
+
+/* RowStatus (RFC 2579). */
+pub(crate) const ROW_STATUS_CREATE_AND_GO: i64 = 4;
+pub(crate) const ROW_STATUS_DESTROY: i64 = 6;
+
+/// The instance of `column` in the row with `index` of the table
+/// with entry oid `entry`.
+pub(crate) fn column(entry: &[u64], column: u64, index: &Oid) -> Oid {
+    Oid::from_slice(entry)
+        .join([column])
+        .join(index.as_slice().iter().copied())
+}
@@ -12,18 +12,38 @@
  * under the License.                                                         * 
  ******************************************************************************/
 
+use std::convert::TryFrom;
 use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::slice;
 
 use super::api;
 use super::error::{Error, Result};
 use super::oid::Oid;
 use super::pdu::Pdu;
+use super::query::Query;
+use super::retry::RetryPolicy;
+use super::stats::{self, SessionStats, StatsHandle};
+use super::usm::UsmUser;
+use super::vacm::{SecurityLevel, SecurityModel};
+use super::validate::{validate_get, validate_next, VarResults};
+use super::value::Value;
 use super::variable::{Variable, VariablePtr};
+use super::version::Version;
 
 /// A smart pointer to a session (functionality shared between multi and single session API).
 /// Only usable for read-only (session information) operations.
 pub struct SessionPtr(pub(crate) api::snmp_session);
 
+/* State of an open session that netsnmp has no room for. */
+struct SessionExt {
+    stats: StatsHandle,
+    retry: Option<RetryPolicy>,
+    /* Set while an async request is outstanding; remains set if the
+     * request future is dropped. */
+    in_flight: bool,
+}
+
 /// Session information (shared between single and multi API).
 pub trait SessionInfo {
     fn session(&self) -> &SessionPtr;
@@ -41,12 +61,130 @@ pub trait SessionInfo {
         self.session().localname()
     }
 
+    fn security_name(&self) -> Option<String> {
+        self.session().security_name()
+    }
+
     fn has_error(&self) -> bool {
         self.session().has_error()
     }
+
+    fn version(&self) -> Result<Version> {
+        self.session().version()
+    }
+
+    /// A snapshot of the session statistics.
+    fn stats(&self) -> SessionStats {
+        self.session().stats()
+    }
+
+    /// A metrics collector for the session, to be registered in a
+    /// prometheus-client registry. The labels are added to every
+    /// metric.
+    #[cfg(feature = "prometheus")]
+    fn collector(&self, labels: Vec<(String, String)>) -> Option<stats::SessionCollector> {
+        Some(stats::SessionCollector::new(
+            self.session().stats_handle()?,
+            labels,
+        ))
+    }
+
+    /// Add a user to the session-local USM user list. Users in this
+    /// list are not visible to other sessions, so sessions to agents
+    /// with the same engine id can use different credentials.
+    fn add_usm_user(&mut self, user: UsmUser) -> Result<()> {
+        self.session_mut().add_usm_user(user)
+    }
 }
 
 impl SessionPtr {
+    /* Only valid on open sessions: netsnmp resets the list on open
+     * and frees it on close (patch 4). */
+    pub(crate) fn add_usm_user(&mut self, user: UsmUser) -> Result<()> {
+        let user = user.into_raw();
+        let list =
+            unsafe { api::usm_add_user_to_list(user, self.0.securityInfo as *mut api::usmUser) };
+        match list.is_null() {
+            true => {
+                unsafe { api::usm_free_user(user) };
+                Err(Error::Usm(String::from("Failed to register user.")))
+            }
+            false => {
+                self.0.securityInfo = list as *mut c_void;
+                Ok(())
+            }
+        }
+    }
+
+    /* The rust-side state of open sessions is kept in a box
+     * referenced from myvoid. */
+    pub(crate) unsafe fn init_ext(
+        &mut self,
+        transport: *mut api::netsnmp_transport,
+        retry: Option<RetryPolicy>,
+    ) {
+        let stats = StatsHandle::default();
+        stats::count_transport(transport, &stats);
+        self.0.myvoid = Box::into_raw(Box::new(SessionExt {
+            stats,
+            retry,
+            in_flight: false,
+        })) as *mut c_void;
+    }
+
+    pub(crate) unsafe fn free_ext(&mut self, transport: *mut api::netsnmp_transport) {
+        stats::uncount_transport(transport);
+        if !self.0.myvoid.is_null() {
+            drop(Box::from_raw(self.0.myvoid as *mut SessionExt));
+            self.0.myvoid = std::ptr::null_mut();
+        }
+    }
+
+    fn ext(&self) -> Option<&SessionExt> {
+        unsafe { (self.0.myvoid as *const SessionExt).as_ref() }
+    }
+
+    fn ext_mut(&mut self) -> Option<&mut SessionExt> {
+        unsafe { (self.0.myvoid as *mut SessionExt).as_mut() }
+    }
+
+    pub(crate) fn stats_handle(&self) -> Option<StatsHandle> {
+        self.ext().map(|ext| ext.stats.clone())
+    }
+
+    pub(crate) fn record<F: FnOnce(&mut SessionStats)>(&self, update: F) {
+        if let Some(ext) = self.ext() {
+            update(&mut ext.stats.lock().unwrap());
+        }
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        match self.ext() {
+            Some(ext) => ext.stats.lock().unwrap().clone(),
+            None => SessionStats::default(),
+        }
+    }
+
+    pub(crate) fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.ext()?.retry.as_ref()
+    }
+
+    pub(crate) fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        if let Some(ext) = self.ext_mut() {
+            ext.retry = policy;
+        }
+    }
+
+    pub(crate) fn in_flight(&self) -> bool {
+        self.ext().is_some_and(|ext| ext.in_flight)
+    }
+
+    pub(crate) fn set_in_flight(&mut self, in_flight: bool) {
+        if let Some(ext) = self.ext_mut() {
+            ext.in_flight = in_flight;
+        }
+    }
+
     pub fn is_authoritative(&self) -> bool {
         self.0.isAuthoritative > 0
     }
@@ -69,6 +207,58 @@ impl SessionPtr {
         }
     }
 
+    pub fn security_name(&self) -> Option<String> {
+        match self.0.securityName.is_null() {
+            true => None,
+            false => Some(
+                String::from_utf8_lossy(unsafe {
+                    slice::from_raw_parts(self.0.securityName as *const u8, self.0.securityNameLen)
+                })
+                .into_owned(),
+            ),
+        }
+    }
+
+    pub fn version(&self) -> Result<Version> {
+        Version::try_from(self.0.version as u32)
+    }
+
+    /// The SNMPv3 security model, if set.
+    pub fn security_model(&self) -> Option<SecurityModel> {
+        match self.0.securityModel as u32 {
+            api::SNMP_SEC_MODEL_USM => Some(SecurityModel::Usm),
+            api::SNMP_SEC_MODEL_TSM => Some(SecurityModel::Tsm),
+            _ => None,
+        }
+    }
+
+    /// The SNMPv3 security level, if set.
+    pub fn security_level(&self) -> Option<SecurityLevel> {
+        match self.0.securityLevel as u32 {
+            api::SNMP_SEC_LEVEL_NOAUTH => Some(SecurityLevel::NoAuthNoPriv),
+            api::SNMP_SEC_LEVEL_AUTHNOPRIV => Some(SecurityLevel::AuthNoPriv),
+            api::SNMP_SEC_LEVEL_AUTHPRIV => Some(SecurityLevel::AuthPriv),
+            _ => None,
+        }
+    }
+
+    /// The key/value pairs passed to the transport when it is opened
+    /// (eg. the (d)tls certificates).
+    pub fn transport_config(&self) -> Vec<(String, String)> {
+        let mut config: Vec<(String, String)> = Vec::new();
+        let container = self.0.transport_configuration;
+        if let Some(for_each) = unsafe { container.as_ref() }.and_then(|c| c.for_each) {
+            unsafe {
+                for_each(
+                    container,
+                    Some(collect_transport_config),
+                    &mut config as *mut Vec<(String, String)> as *mut c_void,
+                )
+            };
+        }
+        config
+    }
+
     pub fn has_error(&self) -> bool {
         self.0.s_errno != 0 || self.0.s_snmp_errno != 0
     }
@@ -79,41 +269,95 @@ pub trait SyncQuery {
     fn send(&mut self, pdu: Pdu) -> Result<()>;
     fn get_error(&mut self) -> Error;
 
+    /// Send a request and wait for the response, which may carry an
+    /// error status. The default implementation returns an error status
+    /// as an error, like `synch_response`.
+    fn synch_request(&mut self, pdu: Pdu) -> Result<Pdu> {
+        self.synch_response(pdu)
+    }
+
+    /// The version to build queries for. Queries are emulated with
+    /// `synch_request` on SNMPv1; by default, the native requests are
+    /// sent.
+    fn query_version(&self) -> Option<Version> {
+        None
+    }
+
+    /* The query functions below are emulated on SNMPv1 sessions, see
+     * query.rs. */
+
     fn get(&mut self, oid: &Oid) -> Result<Option<Variable>> {
-        let pdu = Pdu::get().add_oid(oid);
-        Ok(self
-            .synch_response(pdu)?
-            .variables()
-            .next()
-            .map(VariablePtr::to_owned))
+        let oids = slice::from_ref(oid);
+        let response = synch_query(self, Query::Get(oids))?;
+        validate_get(oids, &response)?;
+        Ok(response.variables().next().map(VariablePtr::to_owned))
+    }
+
+    /// Get the values of `oids`, validating the response against the
+    /// request.
+    fn get_values(&mut self, oids: &[Oid]) -> Result<VarResults> {
+        validate_get(oids, &synch_query(self, Query::Get(oids))?)
     }
 
     fn get_next(&mut self, oid: &Oid) -> Result<Option<Variable>> {
-        let pdu = Pdu::get_next().add_oid(oid.into());
-        Ok(self
-            .synch_response(pdu)?
+        Ok(synch_query(self, Query::GetNext(slice::from_ref(oid)))?
             .variables()
-            .into_iter()
             .next()
-            .map(|var| var.to_owned()))
+            .map(VariablePtr::to_owned))
+    }
+
+    /// Get the successors of `oids` in a single GETNEXT request,
+    /// validating the response against the request.
+    fn get_next_many(&mut self, oids: &[Oid]) -> Result<VarResults> {
+        validate_next(oids, &synch_query(self, Query::GetNext(oids))?)
     }
 
     fn get_bulk(&mut self, gets: &[Oid], walks: &[Oid], repetitions: usize) -> Result<Pdu> {
-        let mut pdu = Pdu::get_bulk(gets.len() as i64, repetitions as i64);
-        for oid in gets {
-            pdu = pdu.add_oid(oid);
-        }
-        for oid in walks {
-            pdu = pdu.add_oid(oid);
-        }
-        self.synch_response(pdu)
+        synch_query(self, Query::GetBulk(gets, walks, repetitions))
     }
 
+    /// Raw (unvalidated) response to a GETBULK request with a single
+    /// repetition for `oids`; see `get_values` for validated results.
     fn get_many(&mut self, oids: &[Oid]) -> Result<Pdu> {
-        let mut pdu = Pdu::get_bulk(oids.len() as i64, 1);
-        for oid in oids {
-            pdu = pdu.add_oid(oid);
+        synch_query(self, Query::GetBulk(oids, &[], 1))
+    }
+
+    fn set(&mut self, vars: &[(Oid, Value)]) -> Result<Pdu> {
+        let mut pdu = Pdu::set();
+        for (oid, value) in vars {
+            pdu = pdu.add_var(oid, value)?;
         }
         self.synch_response(pdu)
     }
 }
+
+/* Send a query, emulated with SNMPv1 requests on SNMPv1 sessions. */
+fn synch_query<S: SyncQuery + ?Sized>(session: &mut S, query: Query) -> Result<Pdu> {
+    match session.query_version() {
+        Some(Version::V1) => {
+            let mut v1 = query.v1();
+            while let Some(pdu) = v1.request() {
+                v1.response(&session.synch_request(pdu)?)?;
+            }
+            check_response(v1.finish()?)
+        }
+        _ => session.synch_response(query.pdu()),
+    }
+}
+
+/* An error status in the response is returned as an error. */
+pub(crate) fn check_response(response: Pdu) -> Result<Pdu> {
+    match response.error() {
+        Some(err) => Err(err),
+        None => Ok(response),
+    }
+}
+
+unsafe extern "C" fn collect_transport_config(data: *mut c_void, context: *mut c_void) {
+    let config = &*(data as *const api::netsnmp_transport_config);
+    let list = &mut *(context as *mut Vec<(String, String)>);
+    list.push((
+        CStr::from_ptr(config.key).to_string_lossy().into_owned(),
+        CStr::from_ptr(config.value).to_string_lossy().into_owned(),
+    ));
+}
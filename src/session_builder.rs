@@ -15,39 +15,70 @@
 use std::ffi::{CStr, CString};
 use std::ops::{Deref, DerefMut};
 use std::os::raw::{c_char, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::Duration;
 use std::{fmt, mem};
 
+use zeroize::Zeroize;
+
 use super::api;
+use super::engine::EngineInfo;
 use super::error::{Error, Result};
 use super::netsnmp::NetSNMP;
+use super::retry::{self, RetryPolicy};
 use super::version::Version;
 //use super::callback_op::CallbackOp;
-use super::auth::{Auth, V3AuthParams, V3AuthProtocol, V3Level, V3PrivParams, V3PrivProtocol};
+use super::auth::{Auth, TsmAuth, V3AuthParams, V3Key, V3Level, V3PrivParams};
+use super::key::Key;
 use super::multi_session::MultiSession;
-use super::session::SessionPtr;
+use super::session::{SessionInfo, SessionPtr};
 use super::single_session::SingleSession;
-use super::transport::{Transport, TransportPtr};
+use super::transport::{is_stream_address, Transport, TransportPtr};
+use super::usm::{self, UsmUser};
 
 /// A stack-allocated, unopened session.
-pub struct SessionBuilder(api::snmp_session);
+pub struct SessionBuilder(api::snmp_session, BuilderState);
+
+/// Settings that are only applied when the session is opened.
+#[derive(Default)]
+struct BuilderState {
+    tsm_domain: Option<&'static str>,
+    usm_users: Vec<UsmUser<'static>>,
+    retry: Option<RetryPolicy>,
+}
 
 impl SessionBuilder {
     pub fn new(_snmp: &NetSNMP) -> Self {
         unsafe {
             let mut session = mem::zeroed();
             api::snmp_sess_init(&mut session);
-            Self(session)
+            Self(session, BuilderState::default())
         }
     }
 
     /* Builder-style functions to set options */
 
     pub fn set_peer(mut self, peer: &[u8]) -> Result<Self> {
+        self.replace_peer(peer)?;
+        self.apply_tsm_domain()?;
+        Ok(self)
+    }
+
+    /// Connect to a local agent over a unix domain (stream) socket.
+    pub fn set_unix_socket<P: AsRef<Path>>(self, path: P) -> Result<Self> {
+        let mut peer = b"unix:".to_vec();
+        peer.extend_from_slice(path.as_ref().as_os_str().as_bytes());
+        self.set_peer(&peer)
+    }
+
+    fn replace_peer(&mut self, peer: &[u8]) -> Result<()> {
+        let peer = CString::new(peer)?.into_raw();
         if !self.0.peername.is_null() {
             mem::drop(unsafe { CString::from_raw(self.0.peername) });
         }
-        self.0.peername = CString::new(peer)?.into_raw();
-        Ok(self)
+        self.0.peername = peer;
+        Ok(())
     }
 
     pub fn set_port(mut self, port: u16) -> Self {
@@ -68,9 +99,8 @@ impl SessionBuilder {
         match auth {
             Auth::V2c(params) => {
                 self.0.version = api::SNMP_VERSION_2c as i64;
-                let community: Box<[u8]> = Box::from(params.community.as_bytes());
-                self.0.community_len = community.len();
-                self.0.community = Box::into_raw(community) as *mut u8;
+                unsafe { free_secret(self.0.community, self.0.community_len) };
+                (self.0.community, self.0.community_len) = alloc_bytes(params.community.as_bytes());
                 Ok(self)
             }
 
@@ -80,11 +110,103 @@ impl SessionBuilder {
                     V3Level::NoAuthNoPriv => Ok(self),
                     V3Level::AuthNoPriv { auth } => self.set_v3_auth(auth),
                     V3Level::AuthPriv { auth, privacy } => {
-                        self.set_v3_auth(auth)?.set_v3_priv(privacy)
+                        self.set_v3_auth(auth)?.set_v3_priv(auth, privacy)
                     }
                 }
             }
+
+            Auth::Tsm(params) => self.set_tsm(params),
+        }
+    }
+
+    fn set_tsm(mut self, params: &TsmAuth) -> Result<Self> {
+        self.0.version = api::SNMP_VERSION_3 as i64;
+        self.0.securityModel = api::SNMP_SEC_MODEL_TSM as i32;
+        /* The (d)tls transport always authenticates and encrypts. */
+        self.0.securityLevel = api::SNMP_SEC_LEVEL_AUTHPRIV as i32;
+
+        let name = CString::new(params.security_name.as_bytes())?;
+        if !self.0.securityName.is_null() {
+            mem::drop(unsafe { CString::from_raw(self.0.securityName) });
+        }
+        self.0.securityNameLen = name.to_bytes().len();
+        self.0.securityName = name.into_raw();
+
+        if let Some(context) = &params.context {
+            let context = CString::new(context.as_bytes())?;
+            if !self.0.contextName.is_null() {
+                mem::drop(unsafe { CString::from_raw(self.0.contextName) });
+            }
+            self.0.contextNameLen = context.to_bytes().len();
+            self.0.contextName = context.into_raw();
+        }
+
+        self.add_transport_config("localCert", &params.local_cert)?;
+        if let Some(cert) = &params.peer_cert {
+            self.add_transport_config("peerCert", cert)?;
+        }
+        if let Some(cert) = &params.trust_cert {
+            self.add_transport_config("trustCert", cert)?;
+        }
+        if let Some(hostname) = &params.peer_hostname {
+            self.add_transport_config("their_hostname", hostname)?;
+        }
+
+        self.1.tsm_domain = Some(params.transport.domain());
+        self.apply_tsm_domain()?;
+        Ok(self)
+    }
+
+    /// Prefix the peer with the (d)tls transport domain, unless it
+    /// already has one. Applied whenever the peer or auth is set.
+    fn apply_tsm_domain(&mut self) -> Result<()> {
+        if let Some(domain) = self.1.tsm_domain {
+            if let Some(peer) = self.peername() {
+                let has_domain = match peer.split_once(':') {
+                    Some((prefix, _)) => ["dtlsudp", "tls", "tlstcp"]
+                        .iter()
+                        .any(|domain| prefix.eq_ignore_ascii_case(domain)),
+                    None => false,
+                };
+                if !has_domain {
+                    self.replace_peer(format!("{}:{}", domain, peer).as_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a key/value pair to the transport configuration, which is
+    /// passed to the transport before it is opened (see snmpcmd -T).
+    fn add_transport_config(&mut self, key: &str, value: &str) -> Result<()> {
+        let c_key = CString::new(key)?;
+        let c_value = CString::new(value)?;
+        unsafe {
+            if self.0.transport_configuration.is_null() {
+                let name = CString::new("transport_configuration:fifo")?;
+                api::netsnmp_container_init_list();
+                self.0.transport_configuration =
+                    api::netsnmp_container_find(name.as_ptr() as *mut c_char);
+                if self.0.transport_configuration.is_null() {
+                    return Err(Error::General(String::from(
+                        "Failed to create transport configuration",
+                    )));
+                }
+            }
+            let config = api::netsnmp_transport_create_config(
+                c_key.as_ptr() as *mut c_char,
+                c_value.as_ptr() as *mut c_char,
+            );
+            if config.is_null()
+                || api::CONTAINER_INSERT(self.0.transport_configuration, config as *mut c_void) != 0
+            {
+                return Err(Error::General(format!(
+                    "Failed to set transport option {}",
+                    key
+                )));
+            }
         }
+        Ok(())
     }
 
     fn set_v3_auth(mut self, params: &V3AuthParams) -> Result<Self> {
@@ -93,83 +215,127 @@ impl SessionBuilder {
         self.0.securityName = user.into_raw() as *mut i8;
 
         self.0.securityLevel = api::SNMP_SEC_LEVEL_AUTHNOPRIV as i32;
-        self.0.securityAuthKeyLen = api::USM_AUTH_KU_LEN as usize;
 
-        match params.protocol {
-            V3AuthProtocol::MD5 => {
-                self.0.securityAuthProto =
-                    unsafe { api::usmHMACMD5AuthProtocol.as_slice() as *const _ as *mut _ };
-                self.0.securityAuthProtoLen = api::USM_AUTH_PROTO_MD5_LEN as usize;
+        let (proto, proto_len) = usm::auth_protocol(&params.protocol);
+        self.0.securityAuthProto = proto;
+        self.0.securityAuthProtoLen = proto_len;
+
+        match &params.key {
+            V3Key::Password(password) => {
+                let ku = usm::generate_ku(&params.protocol, password.as_bytes())?;
+                copy_key(
+                    &ku,
+                    &mut self.0.securityAuthKey,
+                    &mut self.0.securityAuthKeyLen,
+                )?;
             }
-            V3AuthProtocol::SHA => {
-                self.0.securityAuthProto =
-                    unsafe { api::usmHMACSHA1AuthProtocol.as_slice() as *const _ as *mut _ };
-                self.0.securityAuthProtoLen = api::USM_AUTH_PROTO_SHA_LEN as usize;
+            V3Key::MasterKey(ku) => {
+                copy_key(
+                    ku,
+                    &mut self.0.securityAuthKey,
+                    &mut self.0.securityAuthKeyLen,
+                )?;
             }
-        }
-
-        let mut password: Box<[u8]> = Box::from(params.password.as_bytes());
-        let password_len = password.len();
-
-        if unsafe {
-            api::generate_Ku(
-                self.0.securityAuthProto,
-                self.0.securityAuthProtoLen as u32,
-                &mut password[0],
-                password_len,
-                &mut self.0.securityAuthKey[0] as *mut u8,
-                &mut self.0.securityAuthKeyLen,
-            ) != (api::SNMPERR_SUCCESS as i32)
-        } {
-            Err(Error::KeyError)?;
+            V3Key::LocalizedKey(kul) => unsafe {
+                free_secret(self.0.securityAuthLocalKey, self.0.securityAuthLocalKeyLen);
+                (self.0.securityAuthLocalKey, self.0.securityAuthLocalKeyLen) =
+                    alloc_bytes(kul.as_slice());
+            },
         }
 
         Ok(self)
     }
 
-    fn set_v3_priv(mut self, params: &V3PrivParams) -> Result<Self> {
+    fn set_v3_priv(mut self, auth: &V3AuthParams, params: &V3PrivParams) -> Result<Self> {
         self.0.securityLevel = api::SNMP_SEC_LEVEL_AUTHPRIV as i32;
-        self.0.securityPrivKeyLen = api::USM_PRIV_KU_LEN as usize;
 
-        match params.protocol {
-            V3PrivProtocol::DES => {
-                self.0.securityPrivProto =
-                    unsafe { &api::usmDESPrivProtocol.as_slice() as *const _ as *mut _ };
-                self.0.securityPrivProtoLen = api::USM_PRIV_PROTO_DES_LEN as usize;
+        let (proto, proto_len) = usm::priv_protocol(&params.protocol);
+        self.0.securityPrivProto = proto;
+        self.0.securityPrivProtoLen = proto_len;
+
+        /* Privacy keys are derived using the authentication protocol. */
+        match &params.key {
+            V3Key::Password(password) => {
+                let ku = usm::generate_ku(&auth.protocol, password.as_bytes())?;
+                copy_key(
+                    &ku,
+                    &mut self.0.securityPrivKey,
+                    &mut self.0.securityPrivKeyLen,
+                )?;
             }
-            V3PrivProtocol::AES => {
-                self.0.securityPrivProto =
-                    unsafe { api::usmAESPrivProtocol.as_slice() as *const _ as *mut _ };
-                self.0.securityPrivProtoLen = api::USM_PRIV_PROTO_AES_LEN as usize;
+            V3Key::MasterKey(ku) => {
+                copy_key(
+                    ku,
+                    &mut self.0.securityPrivKey,
+                    &mut self.0.securityPrivKeyLen,
+                )?;
             }
+            V3Key::LocalizedKey(kul) => unsafe {
+                free_secret(self.0.securityPrivLocalKey, self.0.securityPrivLocalKeyLen);
+                (self.0.securityPrivLocalKey, self.0.securityPrivLocalKeyLen) =
+                    alloc_bytes(kul.as_slice());
+            },
         }
 
-        let mut password: Box<[u8]> = Box::from(params.password.as_bytes());
-        let password_len = password.len();
-
-        if unsafe {
-            api::generate_Ku(
-                self.0.securityAuthProto,
-                self.0.securityAuthProtoLen as u32,
-                &mut password[0],
-                password_len,
-                &mut self.0.securityPrivKey[0] as *mut u8,
-                &mut self.0.securityPrivKeyLen,
-            ) != (api::SNMPERR_SUCCESS as i32)
-        } {
-            Err(Error::KeyError)?;
+        Ok(self)
+    }
+
+    /// Seed the session with cached engine information (SNMPv3), so
+    /// that no engine id probe is needed.
+    pub fn set_engine(mut self, engine: &EngineInfo) -> Result<Self> {
+        if engine.engine_id.is_empty() {
+            return Err(Error::General(String::from("Empty engine id")));
+        }
+        if !self.0.securityEngineID.is_null() {
+            mem::drop(unsafe {
+                Box::from_raw(std::slice::from_raw_parts_mut(
+                    self.0.securityEngineID,
+                    self.0.securityEngineIDLen,
+                ))
+            });
         }
+        let engine_id: Box<[u8]> = Box::from(engine.engine_id.as_slice());
+        self.0.securityEngineIDLen = engine_id.len();
+        self.0.securityEngineID = Box::into_raw(engine_id) as *mut u8;
 
+        /* Engine times are kept in a library-wide cache. */
+        unsafe {
+            api::set_enginetime(
+                self.0.securityEngineID,
+                self.0.securityEngineIDLen as u32,
+                engine.engine_boots,
+                engine.engine_time,
+                0,
+            );
+        }
         Ok(self)
     }
 
+    /// Add a user to the session-local USM user list (SNMPv3). Users
+    /// are registered when the session is opened.
+    pub fn add_usm_user(mut self, user: UsmUser<'static>) -> Self {
+        self.1.usm_users.push(user);
+        self
+    }
+
     pub fn set_retries(mut self, retries: u64) -> Self {
         self.0.retries = retries as i32;
         self
     }
 
-    pub fn set_timeout(mut self, timeout: f64) -> Self {
-        self.0.timeout = (timeout * 1000000.0).round() as i64;
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.0.timeout = retry::timeout_micros(timeout);
+        self
+    }
+
+    /// Set the retransmission policy for async requests on single
+    /// sessions. Synchronous requests and multi sessions use netsnmp's
+    /// fixed-interval retransmission with the initial timeout and the
+    /// number of retries of the policy.
+    pub fn set_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.0.timeout = retry::timeout_micros(policy.timeout);
+        self.0.retries = policy.retries as i32;
+        self.1.retry = Some(policy);
         self
     }
 
@@ -228,12 +394,11 @@ impl SessionBuilder {
     }*/
 
     /* Actions */
-
     pub fn open_single(mut self) -> Result<SingleSession> {
         // the tutorial says we need to run the SOCK_STARTUP macro on windows
         let session = unsafe { api::snmp_sess_open(&mut self.0) };
         if session.is_null() {
-            Err(self.get_error())
+            Err(self.open_error())
         } else {
             /* The callback closure is allocated by rust and thus not cloned
              * by snmp_open. On success, the ownership is transfered to the
@@ -242,9 +407,29 @@ impl SessionBuilder {
              * session builder. The callback closure will be dropped when the
              * session goed out of scope. */
             //self.0.callback_magic = std::ptr::null_mut();
+            self.0.transport_configuration = std::ptr::null_mut();
             let mut session = unsafe { SingleSession::from_raw(session) };
+            unsafe {
+                let transport = session.transport().as_ptr() as *mut api::netsnmp_transport;
+                session
+                    .session_mut()
+                    .init_ext(transport, self.1.retry.take());
+            }
+            for user in mem::take(&mut self.1.usm_users) {
+                session.add_usm_user(user)?;
+            }
+            let async_probe = self.0.flags & api::SNMP_FLAGS_DONT_PROBE as u64 != 0;
+            /* With a seeded engine id, no probe is needed, but the usm
+             * user for the session is only created by the post-probe
+             * hook, which is not run in async probe mode. */
+            if async_probe
+                && self.0.version == api::SNMP_VERSION_3 as i64
+                && self.0.securityEngineIDLen > 0
+            {
+                session.create_usm_user()?;
+            }
             /* This is not copied fvrom the original by netsnmp. */
-            session.set_async_probe(self.0.flags & api::SNMP_FLAGS_DONT_PROBE as u64 != 0);
+            session.set_async_probe(async_probe);
             Ok(session)
         }
     }
@@ -253,7 +438,7 @@ impl SessionBuilder {
         // the tutorial says we need to run the SOCK_STARTUP macro on windows
         let session = unsafe { api::snmp_open(&mut self.0) };
         if session.is_null() {
-            Err(self.get_error())
+            Err(self.open_error())
         } else {
             /* The callback closure is allocated by rust and thus not cloned
              * by snmp_open. On success, the ownership is transfered to the
@@ -262,7 +447,16 @@ impl SessionBuilder {
              * session builder. The callback closure will be dropped when the
              * session goed out of scope. */
             self.0.callback_magic = std::ptr::null_mut();
-            Ok(unsafe { MultiSession::from_raw(session) })
+            self.0.transport_configuration = std::ptr::null_mut();
+            let mut session = unsafe { MultiSession::from_raw(session) };
+            unsafe {
+                let transport = session.transport();
+                session.session_mut().init_ext(transport, None);
+            }
+            for user in mem::take(&mut self.1.usm_users) {
+                session.add_usm_user(user)?;
+            }
+            Ok(session)
         }
     }
 
@@ -277,11 +471,27 @@ impl SessionBuilder {
             true => Err(self.get_error()),
             false => {
                 self.0.callback_magic = std::ptr::null_mut();
-                Ok((unsafe { MultiSession::from_raw(session) }, transport))
+                self.0.transport_configuration = std::ptr::null_mut();
+                let mut session = unsafe { MultiSession::from_raw(session) };
+                unsafe { session.session_mut().init_ext(transport.as_mut_ptr(), None) };
+                for user in mem::take(&mut self.1.usm_users) {
+                    session.add_usm_user(user)?;
+                }
+                Ok((session, transport))
             }
         }
     }
 
+    /// Stream transports connect when the session is opened; report
+    /// failures to do so as connection errors.
+    fn open_error(&mut self) -> Error {
+        let err = self.get_error();
+        match self.peername() {
+            Some(peer) if is_stream_address(&peer) => Error::Connect(format!("{}: {}", peer, err)),
+            _ => err,
+        }
+    }
+
     pub fn get_error(&mut self) -> Error {
         unsafe {
             let mut errstr: *mut c_char = mem::zeroed();
@@ -322,19 +532,74 @@ impl Drop for SessionBuilder {
         if !self.0.peername.is_null() {
             mem::drop(unsafe { CString::from_raw(self.0.peername) });
         }
-        if !self.0.community.is_null() {
+        unsafe { free_secret(self.0.community, self.0.community_len) };
+        if !self.0.securityName.is_null() {
+            mem::drop(unsafe { CString::from_raw(self.0.securityName) });
+        }
+        if !self.0.contextName.is_null() {
+            mem::drop(unsafe { CString::from_raw(self.0.contextName) });
+        }
+        if !self.0.securityEngineID.is_null() {
             mem::drop(unsafe {
                 Box::from_raw(std::slice::from_raw_parts_mut(
-                    self.0.community,
-                    self.0.community_len,
+                    self.0.securityEngineID,
+                    self.0.securityEngineIDLen,
                 ))
             });
         }
-        if !self.0.securityName.is_null() {
-            mem::drop(unsafe { CString::from_raw(self.0.securityName) });
+        /* Netsnmp has its own copy of the credentials once opened. */
+        self.0.securityAuthKey.zeroize();
+        self.0.securityPrivKey.zeroize();
+        unsafe {
+            free_secret(self.0.securityAuthLocalKey, self.0.securityAuthLocalKeyLen);
+            free_secret(self.0.securityPrivLocalKey, self.0.securityPrivLocalKeyLen);
+        }
+        /* Netsnmp copies the pointer into the opened session, which
+         * needs it to reconnect; it is cleared here once opened. */
+        if !self.0.transport_configuration.is_null() {
+            unsafe {
+                api::CONTAINER_CLEAR(
+                    self.0.transport_configuration,
+                    Some(free_transport_config),
+                    std::ptr::null_mut(),
+                );
+                api::CONTAINER_FREE(self.0.transport_configuration);
+            }
         }
         /*if !self.0.callback_magic.is_null() {
             mem::drop(unsafe { Box::from_raw(self.0.callback_magic) })
         }*/
     }
 }
+
+unsafe extern "C" fn free_transport_config(data: *mut c_void, _context: *mut c_void) {
+    let config = data as *mut api::netsnmp_transport_config;
+    api::free((*config).key as *mut c_void);
+    api::free((*config).value as *mut c_void);
+    api::free(config as *mut c_void);
+}
+
+/// Copy a master key into a fixed-size session key buffer.
+fn copy_key(key: &Key, buf: &mut [u8], len: &mut usize) -> Result<()> {
+    if key.is_empty() || key.len() > buf.len() {
+        return Err(Error::KeyError);
+    }
+    buf[..key.len()].copy_from_slice(key.as_slice());
+    *len = key.len();
+    Ok(())
+}
+
+/* Byte buffers owned by the builder (netsnmp copies them on open). */
+
+fn alloc_bytes(bytes: &[u8]) -> (*mut u8, usize) {
+    let bytes: Box<[u8]> = Box::from(bytes);
+    let len = bytes.len();
+    (Box::into_raw(bytes) as *mut u8, len)
+}
+
+unsafe fn free_secret(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        let mut bytes = Box::from_raw(std::slice::from_raw_parts_mut(ptr, len));
+        bytes.zeroize();
+    }
+}
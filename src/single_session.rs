@@ -13,15 +13,15 @@
  ******************************************************************************/
 
 use std::convert::TryFrom;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::future::Future;
 use std::ops::{Deref, DerefMut};
 use std::os::raw::{c_char, c_int, c_void};
 use std::os::unix::io::RawFd;
 use std::pin::{pin, Pin};
 use std::task::{Context, Poll};
-use std::time::Duration;
-use std::{fmt, mem};
+use std::time::{Duration, Instant};
+use std::{fmt, mem, slice};
 
 use pin_project::pin_project;
 use tokio::io::unix::AsyncFd;
@@ -31,13 +31,21 @@ use tokio::time::Sleep;
 
 use super::api;
 use super::callback_op::CallbackOp;
+use super::engine::{EngineId, EngineInfo};
 use super::error::{Error, Result};
+use super::logging::RequestLog;
 use super::msg::Msg;
 use super::oid::Oid;
 use super::pdu::{Pdu, PduPtr};
-use super::session::{SessionInfo, SessionPtr, SyncQuery};
+use super::query::Query;
+use super::retry::{self, RetryPolicy};
+use super::session::{check_response, SessionInfo, SessionPtr, SyncQuery};
+use super::stats;
 use super::transport::TransportPtr;
+use super::validate::{validate_get, validate_next, VarResults};
+use super::value::Value;
 use super::variable::{Variable, VariablePtr};
+use super::version::Version;
 
 /// An owned, heap allocated open session opened via the single session API (seems to be thread safe).
 pub struct SingleSession(*mut api::session_list);
@@ -68,6 +76,32 @@ pub struct SessionRead<'a> {
 //     writable: AsyncFd<RawFd>,
 // }
 
+/* Restores the timeout and retries of a session when dropped, also
+ * if the request future is cancelled. */
+struct RestoreTimeout {
+    session: *mut api::snmp_session,
+    saved: (i64, i32),
+}
+
+unsafe impl Send for RestoreTimeout {}
+
+impl RestoreTimeout {
+    fn new(session: &mut api::snmp_session) -> Self {
+        Self {
+            saved: (session.timeout, session.retries),
+            session,
+        }
+    }
+}
+
+impl Drop for RestoreTimeout {
+    fn drop(&mut self) {
+        unsafe {
+            ((*self.session).timeout, (*self.session).retries) = self.saved;
+        }
+    }
+}
+
 impl SingleSession {
     pub unsafe fn from_raw(ptr: *mut api::session_list) -> Self {
         Self(ptr)
@@ -109,87 +143,306 @@ impl SingleSessionPtr {
         }
     }
 
-    /* Asynchronous queries. */
+    /* Asynchronous queries. These are emulated on SNMPv1 sessions,
+     * see query.rs. */
 
     pub async fn get_async(&mut self, oid: &Oid) -> Result<Option<Variable>> {
-        let pdu = Pdu::get().add_oid(oid);
-        Ok(self
-            .async_response(pdu)
-            .await?
-            .variables()
-            .next()
-            .map(VariablePtr::to_owned))
+        let oids = slice::from_ref(oid);
+        let response = self.query(Query::Get(oids)).await?;
+        validate_get(oids, &response)?;
+        Ok(response.variables().next().map(VariablePtr::to_owned))
+    }
+
+    /// Get the values of `oids`, validating the response against the
+    /// request.
+    pub async fn get_values_async(&mut self, oids: &[Oid]) -> Result<VarResults> {
+        validate_get(oids, &self.query(Query::Get(oids)).await?)
     }
 
     pub async fn get_next_async(&mut self, oid: &Oid) -> Result<Option<Variable>> {
-        let pdu = Pdu::get().add_oid(oid);
         Ok(self
-            .async_response(pdu)
+            .query(Query::GetNext(slice::from_ref(oid)))
             .await?
             .variables()
             .next()
             .map(VariablePtr::to_owned))
     }
 
+    /// Get the successors of `oids` in a single GETNEXT request,
+    /// validating the response against the request.
+    pub async fn get_next_many_async(&mut self, oids: &[Oid]) -> Result<VarResults> {
+        validate_next(oids, &self.query(Query::GetNext(oids)).await?)
+    }
+
     pub async fn get_bulk_async(
         &mut self,
         gets: &[Oid],
         walks: &[Oid],
         repetitions: usize,
     ) -> Result<Pdu> {
-        let mut pdu = Pdu::get_bulk(gets.len() as i64, repetitions as i64);
-        for oid in gets {
-            pdu = pdu.add_oid(oid);
-        }
-        for oid in walks {
-            pdu = pdu.add_oid(oid);
-        }
-        self.async_response(pdu).await
+        self.query(Query::GetBulk(gets, walks, repetitions)).await
     }
 
     pub async fn get_many_async(&mut self, oids: &[Oid]) -> Result<Pdu> {
-        let mut pdu = Pdu::get_bulk(oids.len() as i64, 1);
-        for oid in oids {
-            pdu = pdu.add_oid(oid);
+        self.query(Query::GetBulk(oids, &[], 1)).await
+    }
+
+    async fn query(&mut self, query: Query<'_>) -> Result<Pdu> {
+        match self.version()? {
+            Version::V1 => {
+                let mut v1 = query.v1();
+                while let Some(pdu) = v1.request() {
+                    v1.response(&self.async_response(pdu).await?)?;
+                }
+                v1.finish()
+            }
+            _ => self.async_response(query.pdu()).await,
+        }
+    }
+
+    pub async fn set_async(&mut self, vars: &[(Oid, Value)]) -> Result<Pdu> {
+        let mut pdu = Pdu::set();
+        for (oid, value) in vars {
+            pdu = pdu.add_var(oid, value)?;
         }
-        self.async_response(pdu).await
+        let response = self.async_response(pdu).await?;
+        match response.error() {
+            Some(err) => Err(err),
+            None => Ok(response),
+        }
+    }
+
+    pub async fn async_response(&mut self, pdu: Pdu) -> Result<Pdu> {
+        let policy = self.session().retry_policy().cloned();
+        let log = RequestLog::new(self.peername(), &pdu);
+        log.instrument(self.exchange(pdu, policy.as_ref())).await
+    }
+
+    /// Send a request using the given retransmission policy instead of
+    /// that of the session.
+    pub async fn async_response_with(&mut self, pdu: Pdu, policy: &RetryPolicy) -> Result<Pdu> {
+        let log = RequestLog::new(self.peername(), &pdu);
+        log.instrument(self.exchange(pdu, Some(policy))).await
     }
 
-    pub async fn async_response(&mut self, mut pdu: Pdu) -> Result<Pdu> {
+    /// The retransmission policy for async requests, if any.
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.session().retry_policy()
+    }
+
+    /// Set the retransmission policy for async requests. Without a
+    /// policy, netsnmp's retransmission is used.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.session_mut().set_retry_policy(policy);
+    }
+
+    async fn exchange(&mut self, mut pdu: Pdu, policy: Option<&RetryPolicy>) -> Result<Pdu> {
+        let start = Instant::now();
         if unsafe {
             (*self.session()).0.flags & api::SNMP_FLAGS_DONT_PROBE as u64 != 0
                 && api::snmp_sess_needs_probe(&mut self.0, pdu.as_mut()) != 0
         } {
-            self.writable().await;
+            self.probe_async().await?;
+        }
 
-            if unsafe { api::snmp_sess_send_probe(&mut self.0) == 0 } {
-                self.set_async_probe(true);
-                return Err(Error::General(String::from("Engineid probe failed.")));
+        if !self.transport().is_open() {
+            self.reconnect()?;
+        }
+
+        self.session().record(|stats| stats.requests += 1);
+        self.session_mut().set_in_flight(true);
+        let result = match policy {
+            Some(policy) => self.request_with_policy(pdu, policy).await,
+            None => self.request(pdu).await,
+        };
+        self.session_mut().set_in_flight(false);
+        match &result {
+            Ok(pdu) if !matches!(pdu.command(), Ok(Msg::Report)) => {
+                let latency = start.elapsed();
+                self.session()
+                    .record(|stats| stats.latency.observe(latency));
             }
+            Err(Error::Timeout) => self.session().record(|stats| stats.timeouts += 1),
+            _ => {}
+        }
+        result
+    }
 
-            let (status, response) = match self.read().await {
-                Ok(pdu) => match pdu.command() {
-                    Ok(Msg::Report) => (api::STAT_ERROR as i32, std::ptr::null_mut()),
-                    _ => (api::STAT_SUCCESS as i32, pdu.into_raw()),
-                },
-                Err(_) => (api::STAT_TIMEOUT as i32, std::ptr::null_mut()),
-            };
-
-            if unsafe {
-                api::snmp_sess_process_probe_response(&mut self.0, status, response)
-                    != api::SNMPERR_SUCCESS as i32
-            } {
-                self.set_async_probe(true);
-                return Err(Error::General(String::from("Engineid probe failed.")));
+    async fn request(&mut self, pdu: Pdu) -> Result<Pdu> {
+        /* Stream transports can be closed by the peer between requests;
+         * keep a copy so that the request can be resent on a fresh
+         * connection. */
+        let retry = match self.transport().is_stream() {
+            true => Some(PduPtr::to_owned(&pdu)),
+            false => None,
+        };
+
+        self.writable().await;
+        self.send(pdu)?;
+        match (self.read().await, retry) {
+            (Err(Error::Disconnected), Some(pdu)) => {
+                self.reconnect()?;
+                self.writable().await;
+                self.send(pdu)?;
+                self.session().record(|stats| stats.retries += 1);
+                self.read().await
             }
+            (result, _) => result,
+        }
+    }
 
-            /* This is disabled after probe... */
-            self.set_async_probe(true);
+    /* The policy is enforced here: netsnmp's retransmission is disabled
+     * and a copy of the request is sent for every attempt, with the
+     * timeout of that attempt. */
+    async fn request_with_policy(&mut self, pdu: Pdu, policy: &RetryPolicy) -> Result<Pdu> {
+        let _restore = RestoreTimeout::new(&mut self.session_mut().0);
+        self.session_mut().0.retries = 0;
+
+        let mut attempt = 0;
+        loop {
+            self.session_mut().0.timeout = retry::timeout_micros(policy.attempt_timeout(attempt));
+            match self.request(PduPtr::to_owned(&pdu)).await {
+                Err(Error::Timeout) if attempt < policy.retries => {
+                    self.session().record(|stats| stats.retries += 1);
+                    attempt += 1;
+                }
+                result => break result,
+            }
         }
+    }
+
+    /// Discover the engine id, boots and time of an SNMPv3 peer
+    /// without blocking. If the engine id is already known (probed
+    /// before or seeded from a cached `EngineInfo`), no request is sent.
+    pub async fn discover_engine_async(&mut self) -> Result<EngineInfo> {
+        if self.session().0.version != api::SNMP_VERSION_3 as i64 {
+            return Err(Error::General(String::from(
+                "Engine discovery requires SNMPv3",
+            )));
+        }
+        if self.session().0.securityEngineIDLen == 0 {
+            let flag = self.session().0.flags & api::SNMP_FLAGS_DONT_PROBE as u64 != 0;
+            let result = self.probe_async().await;
+            self.set_async_probe(flag);
+            result?;
+        }
+        self.engine_info()
+    }
 
+    /// The engine information of the peer, as known to netsnmp.
+    pub fn engine_info(&self) -> Result<EngineInfo> {
+        let session = &self.session().0;
+        if session.securityEngineID.is_null() || session.securityEngineIDLen == 0 {
+            return Err(Error::General(String::from("Engine id not discovered")));
+        }
+        let engine_id = unsafe {
+            EngineId::from_slice(slice::from_raw_parts(
+                session.securityEngineID,
+                session.securityEngineIDLen,
+            ))
+        };
+        let mut engine_boots = 0;
+        let mut engine_time = 0;
+        /* Boots and time from the probe report are not authenticated. */
+        unsafe {
+            api::get_enginetime(
+                session.securityEngineID,
+                session.securityEngineIDLen as u32,
+                &mut engine_boots,
+                &mut engine_time,
+                0,
+            );
+        }
+        Ok(EngineInfo {
+            engine_id,
+            engine_boots,
+            engine_time,
+        })
+    }
+
+    async fn probe_async(&mut self) -> Result<()> {
         self.writable().await;
-        self.send(pdu)?;
-        self.read().await
+
+        if unsafe { api::snmp_sess_send_probe(&mut self.0) == 0 } {
+            self.set_async_probe(true);
+            return Err(Error::General(String::from("Engineid probe failed.")));
+        }
+        self.session().record(|stats| stats.requests += 1);
+
+        self.session_mut().set_in_flight(true);
+        let response = self.read().await;
+        self.session_mut().set_in_flight(false);
+        let (status, response) = match response {
+            Ok(pdu) => match pdu.command() {
+                Ok(Msg::Report) => (api::STAT_ERROR as i32, std::ptr::null_mut()),
+                _ => (api::STAT_SUCCESS as i32, pdu.into_raw()),
+            },
+            Err(err) => {
+                if matches!(err, Error::Timeout) {
+                    self.session().record(|stats| stats.timeouts += 1);
+                }
+                (api::STAT_TIMEOUT as i32, std::ptr::null_mut())
+            }
+        };
+
+        if unsafe {
+            api::snmp_sess_process_probe_response(&mut self.0, status, response)
+                != api::SNMPERR_SUCCESS as i32
+        } {
+            self.set_async_probe(true);
+            return Err(Error::General(String::from("Engineid probe failed.")));
+        }
+
+        /* This is disabled after probe... */
+        self.set_async_probe(true);
+        Ok(())
+    }
+
+    /// Reopen the transport after the connection was closed by the peer.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let peer = self
+            .peername()
+            .ok_or_else(|| Error::Connect(String::from("<no peer>")))?;
+        let c_app = CString::new("snmp")?;
+        let c_peer = CString::new(peer.as_str())?;
+        /* Same as snmp_sess_open: create the transport, apply the
+         * transport configuration (eg. tls certificates), then open. */
+        let transport = unsafe {
+            api::netsnmp_tdomain_transport_full(
+                c_app.as_ptr() as *mut c_char,
+                c_peer.as_ptr() as *mut c_char,
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if transport.is_null() {
+            return Err(Error::Connect(peer));
+        }
+        unsafe {
+            let session = &mut self.session_mut().0 as *mut api::snmp_session;
+            if api::netsnmp_sess_config_and_open_transport(session, transport)
+                != api::SNMPERR_SUCCESS as i32
+            {
+                return Err(Error::Connect(peer));
+            }
+        }
+        unsafe {
+            let old = mem::replace(&mut self.0.transport, transport);
+            stats::uncount_transport(old);
+            if let Some(stats) = self.session().stats_handle() {
+                stats::count_transport(transport, &stats);
+            }
+            if !old.is_null() {
+                if (*old).sock >= 0 {
+                    if let Some(close) = (*old).f_close {
+                        close(old);
+                    }
+                }
+                api::netsnmp_transport_free(old);
+            }
+        }
+        Ok(())
     }
 
     fn select_info(&mut self) -> (i32, Duration) {
@@ -230,17 +483,41 @@ impl SingleSessionPtr {
         self.session_mut().0.callback = Some(SingleSessionPtr::async_read_callback);
         self.session_mut().0.callback_magic =
             &mut result as *mut Option<Result<Pdu>> as *mut c_void;
-        let fd = AsyncFd::new(self.transport().socket_fd()).unwrap();
+        if !self.transport().is_open() {
+            return Err(Error::Disconnected);
+        }
+        let fd = AsyncFd::new(self.transport().socket_fd())
+            .map_err(|e| Error::General(format!("Failed to register socket: {}", e)))?;
 
         loop {
             let (_, timeout) = self.select_info();
             tokio::select! {
-            _ = tokio::time::sleep(timeout) => self.check_timeout(),
-            _ = fd.readable() => self.async_read()
+            _ = tokio::time::sleep(timeout) => {
+                self.check_timeout();
+                /* Without a result, netsnmp has resent the request. */
+                if result.is_none() {
+                    self.session().record(|stats| stats.retries += 1);
+                }
+            }
+            guard = fd.readable() => {
+                self.async_read();
+                /* A stream transport may deliver a message in several
+                 * chunks, which netsnmp buffers internally. Only clear
+                 * readiness when the socket has been drained, so that
+                 * we are woken up for the next chunk. */
+                if let Ok(mut guard) = guard {
+                    if !self.transport().has_pending_data() {
+                        guard.clear_ready();
+                    }
+                }
+            }
             }
             if let Some(r) = result {
                 return r;
             }
+            if !self.transport().is_open() {
+                return Err(Error::Disconnected);
+            }
 
             /*let result = unsafe { Box::from_raw(self.session().0.callback_magic
                             as *mut Option<Result<Pdu>>) };
@@ -292,9 +569,22 @@ impl SingleSessionPtr {
         }
     }
 
+    /* Create the usm user from the session's credentials, as the
+     * post-probe hook does, without sending a probe. The engine id
+     * must be known. */
+    pub(crate) fn create_usm_user(&mut self) -> Result<()> {
+        let session = &mut self.session_mut().0 as *mut api::snmp_session;
+        if unsafe { api::usm_create_user_from_session(session) } != api::SNMPERR_SUCCESS as i32 {
+            return Err(Error::Usm(String::from(
+                "Failed to create the usm user for the session",
+            )));
+        }
+        Ok(())
+    }
+
     extern "C" fn async_read_callback(
         op: c_int,
-        _session: *mut api::netsnmp_session,
+        session: *mut api::netsnmp_session,
         _reqid: c_int,
         pdu: *mut api::netsnmp_pdu,
         magic: *mut c_void,
@@ -303,10 +593,15 @@ impl SingleSessionPtr {
         let mut result = unsafe { Box::from_raw(magic as *mut Option<Result<Pdu>>) };
         match CallbackOp::try_from(op) {
             Ok(CallbackOp::ReceivedMessage) => unsafe {
-                *result = Some(Ok(PduPtr::from_ptr(pdu).to_owned()));
+                let pdu = PduPtr::from_ptr(pdu);
+                session_ptr(session).record(|stats| stats.record_pdu(pdu));
+                *result = Some(Ok(pdu.to_owned()));
             },
             Ok(CallbackOp::TimedOut) => {
-                *result = Some(Err(Error::General(String::from("timeout"))));
+                *result = Some(Err(Error::Timeout));
+            }
+            Ok(CallbackOp::Disconnect) => {
+                *result = Some(Err(Error::Disconnected));
             }
             _ => {}
         }
@@ -377,25 +672,35 @@ impl Future for SessionRead<'_> {
 
 impl SyncQuery for SingleSessionPtr {
     fn synch_response(&mut self, pdu: Pdu) -> Result<Pdu> {
-        unsafe {
+        check_response(self.synch_request(pdu)?)
+    }
+
+    fn synch_request(&mut self, pdu: Pdu) -> Result<Pdu> {
+        let log = RequestLog::new(self.peername(), &pdu);
+        log.sync(|| unsafe {
             let mut response = mem::zeroed();
+            let start = Instant::now();
+            self.session().record(|stats| stats.requests += 1);
             let status = api::snmp_sess_synch_response(&mut self.0, pdu.as_raw(), &mut response);
 
             if status == (api::STAT_SUCCESS as i32) {
                 mem::forget(pdu);
                 let res = Pdu::from_raw(response);
-                if (*response).errstat == (api::SNMP_ERR_NOERROR as i64) {
-                    Ok(res)
-                } else {
-                    let errstr = api::snmp_errstring((*response).errstat as i32);
-                    Err(Error::Packet(
-                        CStr::from_ptr(errstr).to_string_lossy().into_owned(),
-                    ))
-                }
+                self.session().record(|stats| {
+                    stats.record_pdu(&res);
+                    stats.latency.observe(start.elapsed());
+                });
+                Ok(res)
             } else {
+                let errno = self.session().0.s_snmp_errno;
+                self.session().record(|stats| stats.record_error(errno));
                 Err(self.get_error())
             }
-        }
+        })
+    }
+
+    fn query_version(&self) -> Option<Version> {
+        self.version().ok()
     }
 
     fn send(&mut self, pdu: Pdu) -> Result<()> {
@@ -470,7 +775,13 @@ impl Drop for SingleSession {
             if !self.session().0.callback_magic.is_null() {
                 //mem::drop(Box::from_raw((*self.0).callback_magic));
             }
+            let transport = self.transport().as_ptr() as *mut api::netsnmp_transport;
+            self.session_mut().free_ext(transport);
             api::snmp_sess_close(self.0);
         }
     }
 }
+
+unsafe fn session_ptr<'a>(session: *mut api::netsnmp_session) -> &'a SessionPtr {
+    &*(session as *const SessionPtr)
+}
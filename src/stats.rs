@@ -0,0 +1,352 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Per-session statistics. The counters live in an Arc, referenced from
+ * the `myvoid` field of the netsnmp session, so that they can be
+ * updated from netsnmp callbacks and read by metrics collectors. */
+
+use std::collections::BTreeMap;
+use std::os::raw::{c_int, c_void};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+
+#[cfg(feature = "prometheus")]
+use prometheus_client::collector::Collector;
+#[cfg(feature = "prometheus")]
+use prometheus_client::encoding::{DescriptorEncoder, EncodeMetric, NoLabelSet};
+#[cfg(feature = "prometheus")]
+use prometheus_client::metrics::{counter::ConstCounter, MetricType};
+use serde::{Deserialize, Serialize};
+
+use super::api;
+use super::msg::Msg;
+use super::pdu::PduPtr;
+
+/// Upper bounds of the latency histogram buckets.
+pub const LATENCY_BUCKETS: [Duration; 12] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+/// Statistics of a session. Retries are only counted on the async
+/// path; netsnmp retries internally for synchronous requests.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    pub requests: u64,
+    pub responses: u64,
+    pub timeouts: u64,
+    pub retries: u64,
+    pub reports: ReportStats,
+    pub bytes_out: u64,
+    pub bytes_in: u64,
+    pub latency: LatencyHistogram,
+}
+
+/// Report pdus received, by usmStats counter (RFC 3414).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReportStats {
+    pub unsupported_sec_levels: u64,
+    pub not_in_time_windows: u64,
+    pub unknown_user_names: u64,
+    pub unknown_engine_ids: u64,
+    pub wrong_digests: u64,
+    pub decryption_errors: u64,
+    pub other: u64,
+}
+
+/// Round-trip latency of answered requests. `counts[i]` is the number
+/// of responses with a latency up to `LATENCY_BUCKETS[i]` (and above
+/// the previous bound); the last count is for slower responses.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    pub counts: [u64; LATENCY_BUCKETS.len() + 1],
+    pub sum: Duration,
+    pub count: u64,
+}
+
+impl SessionStats {
+    pub(crate) fn record_pdu(&mut self, pdu: &PduPtr) {
+        match pdu.command() {
+            Ok(Msg::Report) => self.reports.record(pdu),
+            _ => self.responses += 1,
+        }
+    }
+
+    /// Count the failure of a synchronous request (from s_snmp_errno).
+    pub(crate) fn record_error(&mut self, errno: c_int) {
+        match errno {
+            api::SNMPERR_TIMEOUT => self.timeouts += 1,
+            api::SNMPERR_UNSUPPORTED_SEC_LEVEL => self.reports.unsupported_sec_levels += 1,
+            api::SNMPERR_NOT_IN_TIME_WINDOW => self.reports.not_in_time_windows += 1,
+            api::SNMPERR_UNKNOWN_USER_NAME => self.reports.unknown_user_names += 1,
+            api::SNMPERR_UNKNOWN_ENG_ID => self.reports.unknown_engine_ids += 1,
+            api::SNMPERR_AUTHENTICATION_FAILURE => self.reports.wrong_digests += 1,
+            api::SNMPERR_DECRYPTION_ERR => self.reports.decryption_errors += 1,
+            _ => {}
+        }
+    }
+}
+
+impl ReportStats {
+    pub fn total(&self) -> u64 {
+        self.unsupported_sec_levels
+            + self.not_in_time_windows
+            + self.unknown_user_names
+            + self.unknown_engine_ids
+            + self.wrong_digests
+            + self.decryption_errors
+            + self.other
+    }
+
+    fn record(&mut self, pdu: &PduPtr) {
+        /* usmStats.N.0 */
+        let counter = pdu
+            .variables()
+            .next()
+            .and_then(|var| match var.get_name().as_slice() {
+                [1, 3, 6, 1, 6, 3, 15, 1, 1, n, 0] => Some(*n),
+                _ => None,
+            });
+        match counter {
+            Some(1) => self.unsupported_sec_levels += 1,
+            Some(2) => self.not_in_time_windows += 1,
+            Some(3) => self.unknown_user_names += 1,
+            Some(4) => self.unknown_engine_ids += 1,
+            Some(5) => self.wrong_digests += 1,
+            Some(6) => self.decryption_errors += 1,
+            _ => self.other += 1,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += latency;
+        self.count += 1;
+    }
+}
+
+pub(crate) type StatsHandle = Arc<Mutex<SessionStats>>;
+
+/// Exports the statistics of a session as prometheus metrics.
+#[cfg(feature = "prometheus")]
+#[derive(Debug)]
+pub struct SessionCollector {
+    stats: StatsHandle,
+    labels: Vec<(String, String)>,
+}
+
+#[cfg(feature = "prometheus")]
+impl SessionCollector {
+    pub(crate) fn new(stats: StatsHandle, labels: Vec<(String, String)>) -> Self {
+        Self { stats, labels }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl Collector for SessionCollector {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::fmt::Result {
+        let stats = self.stats.lock().unwrap().clone();
+
+        let counters = [
+            ("snmp_requests", "Requests sent.", stats.requests),
+            ("snmp_responses", "Responses received.", stats.responses),
+            ("snmp_timeouts", "Requests that timed out.", stats.timeouts),
+            ("snmp_retries", "Requests that were resent.", stats.retries),
+            ("snmp_sent_bytes", "Bytes sent.", stats.bytes_out),
+            ("snmp_received_bytes", "Bytes received.", stats.bytes_in),
+        ];
+        for (name, help, value) in counters {
+            let mut metric = encoder.encode_descriptor(name, help, None, MetricType::Counter)?;
+            ConstCounter::new(value).encode(metric.encode_family(&self.labels)?)?;
+        }
+
+        let reports = [
+            ("unsupportedSecLevels", stats.reports.unsupported_sec_levels),
+            ("notInTimeWindows", stats.reports.not_in_time_windows),
+            ("unknownUserNames", stats.reports.unknown_user_names),
+            ("unknownEngineIDs", stats.reports.unknown_engine_ids),
+            ("wrongDigests", stats.reports.wrong_digests),
+            ("decryptionErrors", stats.reports.decryption_errors),
+            ("other", stats.reports.other),
+        ];
+        let mut metric = encoder.encode_descriptor(
+            "snmp_reports",
+            "Report pdus received, by usmStats counter.",
+            None,
+            MetricType::Counter,
+        )?;
+        for (counter, value) in reports {
+            let mut labels = self.labels.clone();
+            labels.push((String::from("counter"), String::from(counter)));
+            ConstCounter::new(value).encode(metric.encode_family(&labels)?)?;
+        }
+
+        let buckets: Vec<(f64, u64)> = LATENCY_BUCKETS
+            .iter()
+            .map(Duration::as_secs_f64)
+            .chain([f64::MAX])
+            .zip(stats.latency.counts)
+            .collect();
+        encoder
+            .encode_descriptor(
+                "snmp_response_latency_seconds",
+                "Round-trip latency of answered requests.",
+                None,
+                MetricType::Histogram,
+            )?
+            .encode_family(&self.labels)?
+            .encode_histogram::<NoLabelSet>(
+                stats.latency.sum.as_secs_f64(),
+                stats.latency.count,
+                &buckets,
+                None,
+            )
+    }
+}
+
+/* Byte counters: the send and receive functions of session transports
+ * are replaced by wrappers that count and call the original. */
+
+type SendFn = unsafe extern "C" fn(
+    *mut api::netsnmp_transport,
+    *const c_void,
+    c_int,
+    *mut *mut c_void,
+    *mut c_int,
+) -> c_int;
+type RecvFn = unsafe extern "C" fn(
+    *mut api::netsnmp_transport,
+    *mut c_void,
+    c_int,
+    *mut *mut c_void,
+    *mut c_int,
+) -> c_int;
+
+struct CountedTransport {
+    send: Option<SendFn>,
+    recv: Option<RecvFn>,
+    stats: StatsHandle,
+}
+
+/* Netsnmp transports have no room for user data, so the wrappers look
+ * up their counters by transport address on every send and receive.
+ * This takes a read lock on the map and clones the stats handle;
+ * the write lock is only taken when transports are opened, reopened
+ * or closed. The wrappers are called from C and must not unwind, so
+ * poisoned locks are used as they are: the guarded data is only ever
+ * replaced whole or incremented. */
+static TRANSPORTS: RwLock<BTreeMap<usize, CountedTransport>> = RwLock::new(BTreeMap::new());
+
+pub(crate) unsafe fn count_transport(transport: *mut api::netsnmp_transport, stats: &StatsHandle) {
+    if transport.is_null() {
+        return;
+    }
+    let mut transports = TRANSPORTS.write().unwrap_or_else(PoisonError::into_inner);
+    if transports.contains_key(&(transport as usize)) {
+        return;
+    }
+    transports.insert(
+        transport as usize,
+        CountedTransport {
+            send: (*transport).f_send,
+            recv: (*transport).f_recv,
+            stats: stats.clone(),
+        },
+    );
+    (*transport).f_send = Some(counting_send);
+    (*transport).f_recv = Some(counting_recv);
+}
+
+pub(crate) unsafe fn uncount_transport(transport: *mut api::netsnmp_transport) {
+    if let Some(counted) = TRANSPORTS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&(transport as usize))
+    {
+        (*transport).f_send = counted.send;
+        (*transport).f_recv = counted.recv;
+    }
+}
+
+unsafe extern "C" fn counting_send(
+    transport: *mut api::netsnmp_transport,
+    buf: *const c_void,
+    size: c_int,
+    opaque: *mut *mut c_void,
+    olength: *mut c_int,
+) -> c_int {
+    let (send, stats) = match TRANSPORTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&(transport as usize))
+    {
+        Some(counted) => (counted.send, counted.stats.clone()),
+        None => return -1,
+    };
+    let sent = match send {
+        Some(send) => send(transport, buf, size, opaque, olength),
+        None => -1,
+    };
+    if sent > 0 {
+        stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .bytes_out += sent as u64;
+    }
+    sent
+}
+
+unsafe extern "C" fn counting_recv(
+    transport: *mut api::netsnmp_transport,
+    buf: *mut c_void,
+    size: c_int,
+    opaque: *mut *mut c_void,
+    olength: *mut c_int,
+) -> c_int {
+    let (recv, stats) = match TRANSPORTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&(transport as usize))
+    {
+        Some(counted) => (counted.recv, counted.stats.clone()),
+        None => return -1,
+    };
+    let received = match recv {
+        Some(recv) => recv(transport, buf, size, opaque, olength),
+        None => -1,
+    };
+    if received > 0 {
+        stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .bytes_in += received as u64;
+    }
+    received
+}
@@ -89,6 +89,30 @@ impl TransportPtr {
         &self.0.sock
     }
 
+    /// Stream transports (tcp, tls, unix) need framing and can be disconnected.
+    pub fn is_stream(&self) -> bool {
+        self.0.flags & api::NETSNMP_TRANSPORT_FLAG_STREAM != 0
+    }
+
+    /// Netsnmp closes the socket (and sets it to -1) when the peer disconnects.
+    pub fn is_open(&self) -> bool {
+        self.0.sock >= 0
+    }
+
+    /// Check if there is more data waiting on the socket, without consuming it.
+    pub fn has_pending_data(&self) -> bool {
+        let mut buf = [0u8; 1];
+        self.0.sock >= 0
+            && unsafe {
+                api::recv(
+                    self.0.sock,
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len(),
+                    (api::MSG_PEEK | api::MSG_DONTWAIT) as i32,
+                ) > 0
+            }
+    }
+
     pub fn format_nolookup(&mut self, data: &mut [u8]) -> Option<String> {
         let flags = self.0.flags;
         self.0.flags &= !api::NETSNMP_TRANSPORT_FLAG_HOSTNAME;
@@ -129,6 +153,17 @@ impl TransportPtr {
     }
 }
 
+/// Check if an address string refers to a stream-based transport domain.
+pub(crate) fn is_stream_address(addr: &str) -> bool {
+    match addr.split_once(':') {
+        Some((domain, _)) => matches!(
+            domain.to_ascii_lowercase().as_str(),
+            "tcp" | "tcp6" | "tcpv6" | "tcpipv6" | "tls" | "tlstcp" | "unix"
+        ),
+        None => false,
+    }
+}
+
 impl<'a> Deref for Transport {
     type Target = TransportPtr;
     fn deref(&self) -> &Self::Target {
@@ -12,12 +12,17 @@
  * under the License.                                                         * 
  ******************************************************************************/
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
-use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::{mem, ptr, slice};
+
+use zeroize::{Zeroize, Zeroizing};
 
 use super::api;
-use super::auth::{V3AuthParams, V3AuthProtocol, V3Level, V3PrivParams, V3PrivProtocol};
+use super::auth::{V3AuthParams, V3AuthProtocol, V3Key, V3Level, V3PrivParams, V3PrivProtocol};
+use super::engine::EngineId;
+use super::key::Key;
 use super::{Error, Result};
 
 pub struct Usm<'a>(PhantomData<&'a ()>);
@@ -52,152 +57,444 @@ impl<'a> Usm<'a> {
 }
 
 impl<'a> UsmUser<'a> {
+    pub fn as_ptr(&self) -> *const api::usmUser {
+        self.0
+    }
+
+    pub(crate) fn into_raw(self) -> *mut api::usmUser {
+        let ptr = self.0;
+        mem::forget(self);
+        ptr
+    }
+
     pub fn set_name(self, name: &str) -> Result<Self> {
-        unsafe {
-            (*self.0).name = CString::new(name)
-                .map_err(|_| Error::Usm(String::from("Invalid name")))?
-                .into_raw();
-            Ok(self)
-        }
+        let name = CString::new(name).map_err(|_| Error::Usm(String::from("Invalid name")))?;
+        unsafe { set_string(&mut (*self.0).name, &name)? };
+        Ok(self)
     }
 
     pub fn set_sec_name(self, name: &str) -> Result<Self> {
-        unsafe {
-            (*self.0).secName = CString::new(name)
-                .map_err(|_| Error::Usm(String::from("Invalid security name")))?
-                .into_raw();
-            Ok(self)
-        }
+        let name =
+            CString::new(name).map_err(|_| Error::Usm(String::from("Invalid security name")))?;
+        unsafe { set_string(&mut (*self.0).secName, &name)? };
+        Ok(self)
     }
 
-    pub fn set_engine_id(self, id: &[u8]) -> Self {
+    pub fn set_engine_id(self, id: &EngineId) -> Result<Self> {
         unsafe {
-            let mut id = id.to_vec();
-            (*self.0).engineIDLen = id.len();
-            (*self.0).engineID = id.as_mut_ptr();
-            mem::forget(id); /* Do not free the id now! */
-            self
-        }
+            set_bytes(
+                &mut (*self.0).engineID,
+                &mut (*self.0).engineIDLen,
+                id.as_slice(),
+            )?
+        };
+        Ok(self)
     }
 
     pub fn set_auth(self, level: &V3Level) -> Result<Self> {
         match &level {
             V3Level::NoAuthNoPriv => Ok(self),
             V3Level::AuthNoPriv { auth } => self.set_v3_auth(auth),
-            V3Level::AuthPriv { auth, privacy } => self.set_v3_auth(auth)?.set_v3_priv(privacy),
+            V3Level::AuthPriv { auth, privacy } => {
+                self.set_v3_auth(auth)?.set_v3_priv(auth, privacy)
+            }
         }
     }
 
     fn set_v3_auth(self, params: &V3AuthParams) -> Result<Self> {
+        let user = CString::new(params.user.as_bytes())
+            .map_err(|_| Error::Usm(String::from("Invalid security name")))?;
+        let key = self.localized_key(&params.protocol, &params.key)?;
         unsafe {
-            (*self.0).secName = CString::new(params.user.clone()).unwrap().into_raw();
-
-            let (authproto, authproto_len) = match params.protocol {
-                V3AuthProtocol::MD5 => (
-                    api::usmHMACMD5AuthProtocol.as_ptr().cast_mut(),
-                    api::USM_AUTH_PROTO_MD5_LEN as usize,
-                ),
-                V3AuthProtocol::SHA => (
-                    api::usmHMACSHA1AuthProtocol.as_ptr().cast_mut(),
-                    api::USM_AUTH_PROTO_SHA_LEN as usize,
-                ),
-            };
-
-            (*self.0).authProtocol = authproto;
-            (*self.0).authProtocolLen = authproto_len;
-
-            let password = CString::new(params.password.as_str()).unwrap().into_bytes();
-
-            let mut ku: Vec<u8> = Vec::with_capacity(32);
-            let mut ku_len: usize = ku.capacity();
-
-            let mut authkey: Vec<u8> = Vec::with_capacity(32);
-            (*self.0).authKey = authkey.as_mut_ptr();
-            (*self.0).authKeyLen = authkey.capacity();
-
-            if api::generate_Ku(
-                (*self.0).authProtocol,
-                (*self.0).authProtocolLen as u32,
-                &password[0],
-                password.len(),
-                ku.as_mut_ptr(),
-                &mut ku_len,
-            ) != (api::SNMPERR_SUCCESS as i32)
-            {
-                return Err(Error::KeyError);
-            }
+            let user_ptr = &mut *self.0;
+            set_string(&mut user_ptr.secName, &user)?;
+            set_oid(
+                &mut user_ptr.authProtocol,
+                &mut user_ptr.authProtocolLen,
+                auth_protocol(&params.protocol),
+            )?;
+            set_key(
+                &mut user_ptr.authKey,
+                &mut user_ptr.authKeyLen,
+                key.as_slice(),
+            )?;
+        }
+        Ok(self)
+    }
 
-            if api::generate_kul(
-                (*self.0).authProtocol,
-                (*self.0).authProtocolLen as u32,
-                (*self.0).engineID,
-                (*self.0).engineIDLen,
-                ku.as_mut_ptr(),
-                ku_len,
-                (*self.0).authKey,
-                &mut (*self.0).authKeyLen,
-            ) != (api::SNMPERR_SUCCESS as i32)
-            {
-                return Err(Error::KeyError);
-            }
+    fn set_v3_priv(self, auth: &V3AuthParams, params: &V3PrivParams) -> Result<Self> {
+        /* Privacy keys are derived using the authentication protocol. */
+        let key = self.localized_key(&auth.protocol, &params.key)?;
+        unsafe {
+            let user_ptr = &mut *self.0;
+            set_oid(
+                &mut user_ptr.privProtocol,
+                &mut user_ptr.privProtocolLen,
+                priv_protocol(&params.protocol),
+            )?;
+            set_key(
+                &mut user_ptr.privKey,
+                &mut user_ptr.privKeyLen,
+                key.as_slice(),
+            )?;
+        }
+        Ok(self)
+    }
+
+    /// Zeroize the authentication and privacy keys. This is also done
+    /// when the user is dropped.
+    pub fn wipe_keys(&mut self) {
+        unsafe {
+            let user = &mut *self.0;
+            zeroize_raw(user.authKey, user.authKeyLen);
+            zeroize_raw(user.privKey, user.privKeyLen);
+        }
+    }
 
-            Ok(self)
+    /// Get the key localized to the user's engine id (set the engine id first!).
+    fn localized_key(&self, protocol: &V3AuthProtocol, key: &V3Key) -> Result<Key> {
+        match key {
+            V3Key::Password(password) => generate_kul(
+                protocol,
+                &self.engine_id()?,
+                &generate_ku(protocol, password.as_bytes())?,
+            ),
+            V3Key::MasterKey(ku) => generate_kul(protocol, &self.engine_id()?, ku),
+            V3Key::LocalizedKey(kul) => Ok(kul.clone()),
         }
     }
 
-    fn set_v3_priv(self, params: &V3PrivParams) -> Result<Self> {
+    fn engine_id(&self) -> Result<EngineId> {
         unsafe {
-            let (privproto, privproto_len) = match params.protocol {
-                V3PrivProtocol::DES => (
-                    api::usmDESPrivProtocol.as_ptr().cast_mut(),
-                    api::USM_PRIV_PROTO_DES_LEN as usize,
-                ),
-                V3PrivProtocol::AES => (
-                    api::usmAESPrivProtocol.as_ptr().cast_mut(),
-                    api::USM_PRIV_PROTO_AES_LEN as usize,
-                ),
-            };
-
-            (*self.0).privProtocol = privproto;
-            (*self.0).privProtocolLen = privproto_len;
-
-            let mut password = CString::new(params.password.as_str()).unwrap().into_bytes();
-
-            let mut ku: Vec<u8> = Vec::with_capacity(32);
-            let mut ku_len: usize = ku.capacity();
-
-            let mut privkey: Vec<u8> = Vec::with_capacity(32);
-            (*self.0).privKey = privkey.as_mut_ptr();
-            (*self.0).privKeyLen = privkey.capacity();
-            mem::forget(privkey);
-
-            if api::generate_Ku(
-                (*self.0).authProtocol,
-                (*self.0).authProtocolLen as u32,
-                &mut password[0],
+            let user = &*self.0;
+            match user.engineID.is_null() || user.engineIDLen == 0 {
+                true => Err(Error::Usm(String::from("engine id not set"))),
+                false => Ok(EngineId::from_slice(slice::from_raw_parts(
+                    user.engineID,
+                    user.engineIDLen,
+                ))),
+            }
+        }
+    }
+}
+
+/// Convert a password to a master key (Ku), as described in RFC 3414.
+/// This takes about a million hash iterations; cache the result if
+/// possible.
+pub fn generate_ku(protocol: &V3AuthProtocol, password: &[u8]) -> Result<Key> {
+    let (proto, proto_len) = auth_protocol(protocol);
+    let mut ku = Zeroizing::new(vec![0u8; api::USM_AUTH_KU_LEN as usize]);
+    let mut ku_len = ku.len();
+    if password.is_empty()
+        || unsafe {
+            api::generate_Ku(
+                proto,
+                proto_len as u32,
+                password.as_ptr() as *mut u8,
                 password.len(),
                 ku.as_mut_ptr(),
                 &mut ku_len,
-            ) != (api::SNMPERR_SUCCESS as i32)
-            {
-                return Err(Error::KeyError);
-            }
+            )
+        } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::KeyError);
+    }
+    ku.truncate(ku_len);
+    Ok(Key::from_slice(&ku))
+}
 
-            if api::generate_kul(
-                (*self.0).authProtocol,
-                (*self.0).authProtocolLen as u32,
-                (*self.0).engineID,
-                (*self.0).engineIDLen,
-                ku.as_mut_ptr(),
-                ku_len,
-                (*self.0).privKey,
-                &mut (*self.0).privKeyLen,
-            ) != (api::SNMPERR_SUCCESS as i32)
-            {
-                return Err(Error::KeyError);
+/// Localize a master key (Ku) to an engine id, giving Kul.
+pub fn generate_kul(protocol: &V3AuthProtocol, engine_id: &EngineId, ku: &Key) -> Result<Key> {
+    let (proto, proto_len) = auth_protocol(protocol);
+    let mut kul = Zeroizing::new(vec![0u8; api::USM_AUTH_KU_LEN as usize]);
+    let mut kul_len = kul.len();
+    if engine_id.is_empty()
+        || unsafe {
+            api::generate_kul(
+                proto,
+                proto_len as u32,
+                engine_id.as_slice().as_ptr() as *mut u8,
+                engine_id.len(),
+                ku.as_slice().as_ptr() as *mut u8,
+                ku.len(),
+                kul.as_mut_ptr(),
+                &mut kul_len,
+            )
+        } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::KeyError);
+    }
+    kul.truncate(kul_len);
+    Ok(Key::from_slice(&kul))
+}
+
+/// Build a KeyChange value (RFC 3414, section 5) that changes `old`
+/// into `new`. Both keys must be localized to the agent's engine id.
+/// Together with the old key, the value reveals the new key; it is
+/// zeroized when dropped.
+pub fn encode_keychange(
+    protocol: &V3AuthProtocol,
+    old: &Key,
+    new: &Key,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let (proto, proto_len) = auth_protocol(protocol);
+    let mut kc = Zeroizing::new(vec![0u8; 2 * new.len()]);
+    let mut kc_len = kc.len();
+    if old.len() != new.len()
+        || unsafe {
+            api::encode_keychange(
+                proto,
+                proto_len as u32,
+                old.as_slice().as_ptr() as *mut u8,
+                old.len(),
+                new.as_slice().as_ptr() as *mut u8,
+                new.len(),
+                kc.as_mut_ptr(),
+                &mut kc_len,
+            )
+        } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::KeyError);
+    }
+    kc.truncate(kc_len);
+    Ok(kc)
+}
+
+/// Apply a KeyChange value to `old`, as the agent does; the reverse
+/// of `encode_keychange`.
+pub fn decode_keychange(protocol: &V3AuthProtocol, old: &Key, kc: &[u8]) -> Result<Key> {
+    let (proto, proto_len) = auth_protocol(protocol);
+    let mut new = Zeroizing::new(vec![0u8; old.len()]);
+    let mut new_len = new.len();
+    if kc.len() != 2 * old.len()
+        || unsafe {
+            api::decode_keychange(
+                proto,
+                proto_len as u32,
+                old.as_slice().as_ptr() as *mut u8,
+                old.len(),
+                kc.as_ptr() as *mut u8,
+                kc.len(),
+                new.as_mut_ptr(),
+                &mut new_len,
+            )
+        } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::KeyError);
+    }
+    new.truncate(new_len);
+    Ok(Key::from_slice(&new))
+}
+
+/// Decrypt the scoped pdu of an SNMPv3 message (RFC 3414 section 8,
+/// RFC 3826), given the localized privacy key, the msgPrivacyParameters
+/// (salt) and the authoritative engine boots and time. The plaintext
+/// is zeroized when dropped.
+pub fn decrypt_scoped_pdu(
+    protocol: &V3PrivProtocol,
+    kul: &Key,
+    salt: &[u8],
+    engine_boots: u32,
+    engine_time: u32,
+    data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>> {
+    let (key, iv) = priv_key_iv(protocol, kul, salt, engine_boots, engine_time)?;
+    let (proto, proto_len) = priv_protocol(protocol);
+    let mut plain = Zeroizing::new(vec![0u8; data.len()]);
+    let mut plain_len = plain.len();
+    if unsafe {
+        api::sc_decrypt(
+            proto,
+            proto_len,
+            key.as_ptr() as *mut u8,
+            key.len() as u32,
+            iv.as_ptr() as *mut u8,
+            iv.len() as u32,
+            data.as_ptr() as *mut u8,
+            data.len() as u32,
+            plain.as_mut_ptr(),
+            &mut plain_len,
+        )
+    } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::Usm(String::from("Decryption failed")));
+    }
+    /* Truncating keeps the plaintext in the zeroizing buffer. */
+    plain.truncate(plain_len);
+    Ok(plain)
+}
+
+/// Encrypt an encoded scoped pdu; the reverse of `decrypt_scoped_pdu`.
+/// DES input is padded to the block size.
+pub fn encrypt_scoped_pdu(
+    protocol: &V3PrivProtocol,
+    kul: &Key,
+    salt: &[u8],
+    engine_boots: u32,
+    engine_time: u32,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let (key, iv) = priv_key_iv(protocol, kul, salt, engine_boots, engine_time)?;
+    let (proto, proto_len) = priv_protocol(protocol);
+    let mut encrypted = vec![0u8; data.len() + 8];
+    let mut encrypted_len = encrypted.len();
+    if unsafe {
+        api::sc_encrypt(
+            proto,
+            proto_len,
+            key.as_ptr() as *mut u8,
+            key.len() as u32,
+            iv.as_ptr() as *mut u8,
+            iv.len() as u32,
+            data.as_ptr() as *mut u8,
+            data.len() as u32,
+            encrypted.as_mut_ptr(),
+            &mut encrypted_len,
+        )
+    } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::Usm(String::from("Encryption failed")));
+    }
+    encrypted.truncate(encrypted_len);
+    Ok(encrypted)
+}
+
+/* The key is the first part of the localized key; the IV is derived
+ * from the salt and the rest of the key (DES, RFC 3414) or the engine
+ * boots and time (AES, RFC 3826). */
+fn priv_key_iv<'a>(
+    protocol: &V3PrivProtocol,
+    kul: &'a Key,
+    salt: &[u8],
+    engine_boots: u32,
+    engine_time: u32,
+) -> Result<(&'a [u8], Vec<u8>)> {
+    let kul = kul.as_slice();
+    if kul.len() < priv_key_len(protocol) || salt.len() != 8 {
+        return Err(Error::KeyError);
+    }
+    Ok(match protocol {
+        V3PrivProtocol::DES => (
+            &kul[..8],
+            kul[8..16].iter().zip(salt).map(|(a, b)| a ^ b).collect(),
+        ),
+        V3PrivProtocol::AES => (
+            &kul[..16],
+            [
+                &engine_boots.to_be_bytes()[..],
+                &engine_time.to_be_bytes(),
+                salt,
+            ]
+            .concat(),
+        ),
+    })
+}
+
+/// Compute the msgAuthenticationParameters (HMAC-96, RFC 3414
+/// sections 6 and 7) of a whole message, encoded with the
+/// authentication parameters set to zero.
+pub fn authenticate_message(
+    protocol: &V3AuthProtocol,
+    kul: &Key,
+    message: &[u8],
+) -> Result<Vec<u8>> {
+    let (proto, proto_len) = auth_protocol(protocol);
+    let mut mac = vec![0u8; 12];
+    let mut mac_len = mac.len();
+    if unsafe {
+        api::sc_generate_keyed_hash(
+            proto,
+            proto_len,
+            kul.as_slice().as_ptr() as *mut u8,
+            kul.len() as u32,
+            message.as_ptr() as *mut u8,
+            message.len() as u32,
+            mac.as_mut_ptr(),
+            &mut mac_len,
+        )
+    } != api::SNMPERR_SUCCESS as i32
+    {
+        return Err(Error::Usm(String::from("Authentication failed")));
+    }
+    mac.truncate(mac_len);
+    Ok(mac)
+}
+
+/// Keys localized to an agent's engine id.
+#[derive(Clone, Default)]
+pub(crate) struct LocalizedKeys {
+    pub auth: Option<(V3AuthProtocol, Key)>,
+    pub privacy: Option<(V3PrivProtocol, Key)>,
+}
+
+impl LocalizedKeys {
+    /// Collect the keys of an already localized level. The privacy
+    /// key is the first part of the localized key.
+    pub(crate) fn new(level: &V3Level) -> Result<Self> {
+        Ok(match level {
+            V3Level::NoAuthNoPriv => Self::default(),
+            V3Level::AuthNoPriv { auth } => Self {
+                auth: Some((auth.protocol.clone(), localized_key(&auth.key)?)),
+                privacy: None,
+            },
+            V3Level::AuthPriv { auth, privacy } => {
+                let key = localized_key(&privacy.key)?;
+                let len = priv_key_len(&privacy.protocol).min(key.len());
+                Self {
+                    auth: Some((auth.protocol.clone(), localized_key(&auth.key)?)),
+                    privacy: Some((
+                        privacy.protocol.clone(),
+                        Key::from_slice(&key.as_slice()[..len]),
+                    )),
+                }
             }
+        })
+    }
+}
+
+fn localized_key(key: &V3Key) -> Result<Key> {
+    match key {
+        V3Key::LocalizedKey(kul) => Ok(kul.clone()),
+        _ => Err(Error::KeyError),
+    }
+}
+
+/// The length of the privacy key (the first part of the localized key).
+pub(crate) fn priv_key_len(protocol: &V3PrivProtocol) -> usize {
+    match protocol {
+        V3PrivProtocol::DES => 16, /* DES key + pre-IV */
+        V3PrivProtocol::AES => 16, /* AES-128 */
+    }
+}
+
+/* Protocol oids are static in netsnmp. */
+
+pub(crate) fn auth_protocol(protocol: &V3AuthProtocol) -> (*mut api::oid, usize) {
+    unsafe {
+        match protocol {
+            V3AuthProtocol::MD5 => (
+                ptr::addr_of!(api::usmHMACMD5AuthProtocol) as *mut api::oid,
+                api::USM_AUTH_PROTO_MD5_LEN as usize,
+            ),
+            V3AuthProtocol::SHA => (
+                ptr::addr_of!(api::usmHMACSHA1AuthProtocol) as *mut api::oid,
+                api::USM_AUTH_PROTO_SHA_LEN as usize,
+            ),
+        }
+    }
+}
 
-            Ok(self)
+pub(crate) fn priv_protocol(protocol: &V3PrivProtocol) -> (*mut api::oid, usize) {
+    unsafe {
+        match protocol {
+            V3PrivProtocol::DES => (
+                ptr::addr_of!(api::usmDESPrivProtocol) as *mut api::oid,
+                api::USM_PRIV_PROTO_DES_LEN as usize,
+            ),
+            V3PrivProtocol::AES => (
+                ptr::addr_of!(api::usmAESPrivProtocol) as *mut api::oid,
+                api::USM_PRIV_PROTO_AES_LEN as usize,
+            ),
         }
     }
 }
@@ -211,8 +508,58 @@ impl<'a> Drop for Usm<'a> {
 
 impl<'a> Drop for UsmUser<'a> {
     fn drop(&mut self) {
-        unsafe {
-            api::usm_free_user(self.0);
-        }
+        self.wipe_keys();
+        unsafe { api::usm_free_user(self.0) };
+    }
+}
+
+/* Buffers referenced by the user struct are freed by usm_free_user,
+ * so they must be allocated by the C allocator. */
+
+unsafe fn set_string(field: &mut *mut c_char, value: &CStr) -> Result<()> {
+    let bytes = value.to_bytes_with_nul();
+    let ptr = api::netsnmp_memdup(bytes.as_ptr() as *const c_void, bytes.len());
+    if ptr.is_null() {
+        return Err(Error::Usm(String::from("Out of memory")));
+    }
+    api::free(*field as *mut c_void);
+    *field = ptr as *mut c_char;
+    Ok(())
+}
+
+unsafe fn set_bytes(field: &mut *mut u8, len: &mut usize, value: &[u8]) -> Result<()> {
+    let ptr = api::netsnmp_memdup(value.as_ptr() as *const c_void, value.len());
+    if ptr.is_null() {
+        return Err(Error::Usm(String::from("Out of memory")));
+    }
+    api::free(*field as *mut c_void);
+    *field = ptr as *mut u8;
+    *len = value.len();
+    Ok(())
+}
+
+unsafe fn set_key(field: &mut *mut u8, len: &mut usize, value: &[u8]) -> Result<()> {
+    zeroize_raw(*field, *len);
+    set_bytes(field, len, value)
+}
+
+unsafe fn set_oid(
+    field: &mut *mut api::oid,
+    len: &mut usize,
+    (value, value_len): (*mut api::oid, usize),
+) -> Result<()> {
+    let ptr = api::snmp_duplicate_objid(value, value_len);
+    if ptr.is_null() {
+        return Err(Error::Usm(String::from("Out of memory")));
+    }
+    api::free(*field as *mut c_void);
+    *field = ptr;
+    *len = value_len;
+    Ok(())
+}
+
+unsafe fn zeroize_raw(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        slice::from_raw_parts_mut(ptr, len).zeroize();
     }
 }
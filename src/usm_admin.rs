@@ -0,0 +1,269 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+use super::auth::{Auth, V3Auth, V3Level};
+use super::engine::EngineId;
+use super::error::{Error, Result};
+use super::msg::Msg;
+use super::netsnmp::NetSNMP;
+use super::oid::Oid;
+use super::pdu::Pdu;
+use super::row_status::{column, ROW_STATUS_CREATE_AND_GO, ROW_STATUS_DESTROY};
+use super::session::{check_response, SessionInfo};
+use super::single_session::SingleSessionPtr;
+use super::usm::{self, LocalizedKeys};
+use super::value::Value;
+
+/* usmUserEntry (RFC 3414, section 5). */
+pub(crate) const USM_USER_ENTRY: [u64; 11] = [1, 3, 6, 1, 6, 3, 15, 1, 2, 2, 1];
+pub(crate) const USM_USER_SECURITY_NAME: u64 = 3;
+pub(crate) const USM_USER_CLONE_FROM: u64 = 4;
+pub(crate) const USM_USER_AUTH_KEY_CHANGE: u64 = 6;
+pub(crate) const USM_USER_OWN_AUTH_KEY_CHANGE: u64 = 7;
+pub(crate) const USM_USER_PRIV_KEY_CHANGE: u64 = 9;
+pub(crate) const USM_USER_OWN_PRIV_KEY_CHANGE: u64 = 10;
+pub(crate) const USM_USER_STATUS: u64 = 13;
+
+/* sysUpTime.0, used to verify new credentials. */
+const SYS_UPTIME: [u64; 9] = [1, 3, 6, 1, 2, 1, 1, 3, 0];
+
+/// User management on an SNMPv3 agent through the usmUserTable
+/// (RFC 3414, section 5), using the credentials of an open session.
+pub struct UsmAdmin<'a> {
+    snmp: &'a NetSNMP,
+    session: &'a mut SingleSessionPtr,
+    engine_id: EngineId,
+}
+
+impl<'a> UsmAdmin<'a> {
+    /// The engine id of the agent is discovered if not yet known.
+    pub async fn new(snmp: &'a NetSNMP, session: &'a mut SingleSessionPtr) -> Result<Self> {
+        let engine_id = session.discover_engine_async().await?.engine_id;
+        Ok(Self {
+            snmp,
+            session,
+            engine_id,
+        })
+    }
+
+    /// Change the keys of an existing user with the KeyChange
+    /// procedure. The "own" variants are used when changing the keys
+    /// of the session's user; the session keeps using the old keys.
+    /// The new credentials are verified with a test request. If that
+    /// fails, the keys are changed back to the old ones, except for
+    /// the session's own user: the agent no longer accepts the
+    /// session's keys, so its new keys are left in place.
+    pub async fn change_keys(&mut self, old: &V3Level, new: &V3Level) -> Result<()> {
+        let user = user_name(new)?;
+        if user_name(old)? != user {
+            return Err(Error::Usm(String::from("User names do not match")));
+        }
+        let index = self.index(user);
+        let own = self.session.security_name().as_deref() == Some(user);
+        let vars = self.key_change_vars(&index, old, new, own)?;
+        if vars.is_empty() {
+            return self.verify(new).await;
+        }
+        self.session.set_async(&vars).await?;
+        match self.verify(new).await {
+            Err(err) if !own => {
+                let vars = self.key_change_vars(&index, new, old, false)?;
+                match self.session.set_async(&vars).await {
+                    Ok(_) => Err(err),
+                    Err(rollback) => Err(Error::Usm(format!(
+                        "{}; failed to restore the old keys: {}",
+                        err, rollback
+                    ))),
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Create a user by cloning `template` (usmUserCloneFrom), and
+    /// change its keys from the template's to the requested ones. The
+    /// user is removed again if this or the verification fails.
+    pub async fn clone_user(&mut self, template: &V3Level, new: &V3Level) -> Result<()> {
+        let user = user_name(new)?;
+        let index = self.index(user);
+        let template_index = self.index(user_name(template)?);
+
+        /* Check the keys before creating the row. */
+        let vars = self.key_change_vars(&index, template, new, false)?;
+
+        self.session
+            .set_async(&[
+                (
+                    column(&USM_USER_ENTRY, USM_USER_STATUS, &index),
+                    Value::Integer(ROW_STATUS_CREATE_AND_GO),
+                ),
+                (
+                    column(&USM_USER_ENTRY, USM_USER_CLONE_FROM, &index),
+                    Value::Oid(column(
+                        &USM_USER_ENTRY,
+                        USM_USER_SECURITY_NAME,
+                        &template_index,
+                    )),
+                ),
+            ])
+            .await?;
+
+        let mut result = Ok(());
+        if !vars.is_empty() {
+            result = self.session.set_async(&vars).await.map(|_| ());
+        }
+        if result.is_ok() {
+            result = self.verify(new).await;
+        }
+        if result.is_err() {
+            let _ = self.delete_user(user).await;
+        }
+        result
+    }
+
+    /// Delete a user (usmUserStatus = destroy).
+    pub async fn delete_user(&mut self, user: &str) -> Result<()> {
+        let index = self.index(user);
+        self.session
+            .set_async(&[(
+                column(&USM_USER_ENTRY, USM_USER_STATUS, &index),
+                Value::Integer(ROW_STATUS_DESTROY),
+            )])
+            .await?;
+        Ok(())
+    }
+
+    /// Send a test request to the agent with the given credentials.
+    pub async fn verify(&mut self, level: &V3Level) -> Result<()> {
+        let peer = self
+            .session
+            .peername()
+            .ok_or_else(|| Error::Usm(String::from("Session has no peer")))?;
+        let auth = Auth::V3(V3Auth {
+            level: level.localize(&self.engine_id)?,
+            context: None,
+            context_engine: None,
+            security_engine: None,
+            destination_engine: None,
+        });
+        let mut session = self
+            .snmp
+            .session()
+            .set_peer(peer.as_bytes())?
+            .set_auth(&auth)?
+            .set_engine(&self.session.engine_info()?)?
+            .set_async_probe(true)
+            .open_single()?;
+        let response = session
+            .async_response(Pdu::get().add_oid(&Oid::from_slice(&SYS_UPTIME)))
+            .await?;
+        match response.command()? {
+            Msg::Response => check_response(response).map(|_| ()),
+            _ => Err(Error::Usm(format!(
+                "Credentials for {} rejected by agent",
+                user_name(level).unwrap_or("<none>")
+            ))),
+        }
+    }
+
+    /// The usmUserTable index for a user of the agent's engine.
+    fn index(&self, user: &str) -> Oid {
+        Oid::empty()
+            .join_octets(self.engine_id.as_slice())
+            .join_octets(user.as_bytes())
+    }
+
+    fn key_change_vars(
+        &self,
+        index: &Oid,
+        old: &V3Level,
+        new: &V3Level,
+        own: bool,
+    ) -> Result<Vec<(Oid, Value)>> {
+        let old = self.localized_keys(old)?;
+        let new = self.localized_keys(new)?;
+        let mut vars = Vec::new();
+
+        let auth_protocol = match (old.auth, new.auth) {
+            (None, None) => return Ok(vars),
+            (Some((old_proto, old_key)), Some((new_proto, new_key))) => {
+                if old_proto != new_proto {
+                    return Err(Error::Usm(String::from(
+                        "The authentication protocol can not be changed",
+                    )));
+                }
+                if old_key != new_key {
+                    let col = match own {
+                        true => USM_USER_OWN_AUTH_KEY_CHANGE,
+                        false => USM_USER_AUTH_KEY_CHANGE,
+                    };
+                    let kc = usm::encode_keychange(&new_proto, &old_key, &new_key)?;
+                    vars.push((
+                        column(&USM_USER_ENTRY, col, index),
+                        Value::OctetStr(kc.to_vec()),
+                    ));
+                }
+                new_proto
+            }
+            _ => {
+                return Err(Error::Usm(String::from(
+                    "The security level can not be changed",
+                )))
+            }
+        };
+
+        match (old.privacy, new.privacy) {
+            (None, None) => {}
+            (Some((old_proto, old_key)), Some((new_proto, new_key))) => {
+                if old_proto != new_proto {
+                    return Err(Error::Usm(String::from(
+                        "The privacy protocol can not be changed",
+                    )));
+                }
+                /* Privacy keys use the auth protocol's hash. */
+                if old_key != new_key {
+                    let col = match own {
+                        true => USM_USER_OWN_PRIV_KEY_CHANGE,
+                        false => USM_USER_PRIV_KEY_CHANGE,
+                    };
+                    let kc = usm::encode_keychange(&auth_protocol, &old_key, &new_key)?;
+                    vars.push((
+                        column(&USM_USER_ENTRY, col, index),
+                        Value::OctetStr(kc.to_vec()),
+                    ));
+                }
+            }
+            _ => {
+                return Err(Error::Usm(String::from(
+                    "The security level can not be changed",
+                )))
+            }
+        }
+
+        Ok(vars)
+    }
+
+    fn localized_keys(&self, level: &V3Level) -> Result<LocalizedKeys> {
+        LocalizedKeys::new(&level.localize(&self.engine_id)?)
+    }
+}
+
+fn user_name(level: &V3Level) -> Result<&str> {
+    match level {
+        V3Level::NoAuthNoPriv => Err(Error::Usm(String::from(
+            "A user name is required (noAuthNoPriv has none)",
+        ))),
+        V3Level::AuthNoPriv { auth } | V3Level::AuthPriv { auth, .. } => Ok(&auth.user),
+    }
+}
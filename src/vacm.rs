@@ -0,0 +1,378 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use super::auth::{Auth, V3Level};
+use super::error::{Error, Result};
+use super::oid::Oid;
+use super::row_status::{column, ROW_STATUS_CREATE_AND_GO, ROW_STATUS_DESTROY};
+use super::single_session::SingleSessionPtr;
+use super::value::Value;
+
+/* View-based access control tables (RFC 3415, section 4). */
+const VACM_SECURITY_TO_GROUP_ENTRY: [u64; 10] = [1, 3, 6, 1, 6, 3, 16, 1, 2, 1];
+const VACM_GROUP_NAME: u64 = 3;
+const VACM_SECURITY_TO_GROUP_STATUS: u64 = 5;
+
+const VACM_ACCESS_ENTRY: [u64; 10] = [1, 3, 6, 1, 6, 3, 16, 1, 4, 1];
+const VACM_ACCESS_CONTEXT_MATCH: u64 = 4;
+const VACM_ACCESS_READ_VIEW_NAME: u64 = 5;
+const VACM_ACCESS_WRITE_VIEW_NAME: u64 = 6;
+const VACM_ACCESS_NOTIFY_VIEW_NAME: u64 = 7;
+const VACM_ACCESS_STATUS: u64 = 9;
+
+const VACM_VIEW_TREE_FAMILY_ENTRY: [u64; 11] = [1, 3, 6, 1, 6, 3, 16, 1, 5, 2, 1];
+const VACM_VIEW_TREE_FAMILY_MASK: u64 = 3;
+const VACM_VIEW_TREE_FAMILY_TYPE: u64 = 4;
+const VACM_VIEW_TREE_FAMILY_STATUS: u64 = 6;
+
+/* The size of vacmViewTreeFamilyMask. */
+const MAX_MASK_LEN: usize = 16;
+
+/// Security models (SnmpSecurityModel, RFC 3411).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecurityModel {
+    Any = 0,
+    V1 = 1,
+    V2c = 2,
+    Usm = 3,
+    Tsm = 4,
+}
+
+/// Security levels (SnmpSecurityLevel, RFC 3411).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecurityLevel {
+    NoAuthNoPriv = 1,
+    AuthNoPriv = 2,
+    AuthPriv = 3,
+}
+
+/// How the context name is matched by an access entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContextMatch {
+    Exact = 1,
+    Prefix = 2,
+}
+
+/// An access entry: the views a group gets for a security model
+/// and (minimum) security level.
+#[derive(Clone, Debug)]
+pub struct VacmAccess {
+    pub group: String,
+    pub context_prefix: String,
+    pub context_match: ContextMatch,
+    pub model: SecurityModel,
+    pub level: SecurityLevel,
+    pub read_view: Option<String>,
+    pub write_view: Option<String>,
+    pub notify_view: Option<String>,
+}
+
+/// A view tree family: a subtree, optionally with wildcarded
+/// sub-identifiers, that is included in or excluded from a view.
+#[derive(Clone, Debug)]
+pub struct VacmView {
+    pub name: String,
+    pub subtree: Oid,
+    /// Positions (0-based) of sub-identifiers in `subtree` that match
+    /// any value.
+    pub wildcards: Vec<usize>,
+    pub included: bool,
+}
+
+/// View-based access control configuration on an agent, through the
+/// VACM MIB tables (RFC 3415), using the credentials of an open
+/// session.
+pub struct VacmAdmin<'a> {
+    session: &'a mut SingleSessionPtr,
+}
+
+impl<'a> VacmAdmin<'a> {
+    pub fn new(session: &'a mut SingleSessionPtr) -> Self {
+        Self { session }
+    }
+
+    /// Map a security name to a group (vacmSecurityToGroupTable).
+    pub async fn add_group_member(
+        &mut self,
+        model: SecurityModel,
+        security_name: &str,
+        group: &str,
+    ) -> Result<()> {
+        let index = model.group_member_index(security_name);
+        self.session
+            .set_async(&[
+                (
+                    column(&VACM_SECURITY_TO_GROUP_ENTRY, VACM_GROUP_NAME, &index),
+                    Value::OctetStr(group.as_bytes().to_vec()),
+                ),
+                (
+                    column(
+                        &VACM_SECURITY_TO_GROUP_ENTRY,
+                        VACM_SECURITY_TO_GROUP_STATUS,
+                        &index,
+                    ),
+                    Value::Integer(ROW_STATUS_CREATE_AND_GO),
+                ),
+            ])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_group_member(
+        &mut self,
+        model: SecurityModel,
+        security_name: &str,
+    ) -> Result<()> {
+        let index = model.group_member_index(security_name);
+        self.destroy(column(
+            &VACM_SECURITY_TO_GROUP_ENTRY,
+            VACM_SECURITY_TO_GROUP_STATUS,
+            &index,
+        ))
+        .await
+    }
+
+    /// Create an access entry (vacmAccessTable).
+    pub async fn add_access(&mut self, access: &VacmAccess) -> Result<()> {
+        let index = access.index();
+        let view = |view: &Option<String>| {
+            Value::OctetStr(view.as_deref().unwrap_or("").as_bytes().to_vec())
+        };
+        self.session
+            .set_async(&[
+                (
+                    column(&VACM_ACCESS_ENTRY, VACM_ACCESS_CONTEXT_MATCH, &index),
+                    Value::Integer(access.context_match as i64),
+                ),
+                (
+                    column(&VACM_ACCESS_ENTRY, VACM_ACCESS_READ_VIEW_NAME, &index),
+                    view(&access.read_view),
+                ),
+                (
+                    column(&VACM_ACCESS_ENTRY, VACM_ACCESS_WRITE_VIEW_NAME, &index),
+                    view(&access.write_view),
+                ),
+                (
+                    column(&VACM_ACCESS_ENTRY, VACM_ACCESS_NOTIFY_VIEW_NAME, &index),
+                    view(&access.notify_view),
+                ),
+                (
+                    column(&VACM_ACCESS_ENTRY, VACM_ACCESS_STATUS, &index),
+                    Value::Integer(ROW_STATUS_CREATE_AND_GO),
+                ),
+            ])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_access(&mut self, access: &VacmAccess) -> Result<()> {
+        let index = access.index();
+        self.destroy(column(&VACM_ACCESS_ENTRY, VACM_ACCESS_STATUS, &index))
+            .await
+    }
+
+    /// Create a view tree family (vacmViewTreeFamilyTable).
+    pub async fn add_view(&mut self, view: &VacmView) -> Result<()> {
+        let index = view.index();
+        self.session
+            .set_async(&[
+                (
+                    column(
+                        &VACM_VIEW_TREE_FAMILY_ENTRY,
+                        VACM_VIEW_TREE_FAMILY_MASK,
+                        &index,
+                    ),
+                    Value::OctetStr(view.mask()?),
+                ),
+                (
+                    column(
+                        &VACM_VIEW_TREE_FAMILY_ENTRY,
+                        VACM_VIEW_TREE_FAMILY_TYPE,
+                        &index,
+                    ),
+                    Value::Integer(match view.included {
+                        true => 1,
+                        false => 2,
+                    }),
+                ),
+                (
+                    column(
+                        &VACM_VIEW_TREE_FAMILY_ENTRY,
+                        VACM_VIEW_TREE_FAMILY_STATUS,
+                        &index,
+                    ),
+                    Value::Integer(ROW_STATUS_CREATE_AND_GO),
+                ),
+            ])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_view(&mut self, view: &VacmView) -> Result<()> {
+        let index = view.index();
+        self.destroy(column(
+            &VACM_VIEW_TREE_FAMILY_ENTRY,
+            VACM_VIEW_TREE_FAMILY_STATUS,
+            &index,
+        ))
+        .await
+    }
+
+    /// Give the user of `auth` read-only access to `subtrees`, through
+    /// a group and view of the given names.
+    pub async fn grant_read_only(
+        &mut self,
+        auth: &Auth,
+        group: &str,
+        view: &str,
+        subtrees: &[Oid],
+    ) -> Result<()> {
+        let security_name = match auth {
+            Auth::V3(params) => match &params.level {
+                V3Level::AuthNoPriv { auth } | V3Level::AuthPriv { auth, .. } => auth.user.as_str(),
+                V3Level::NoAuthNoPriv => {
+                    return Err(Error::Vacm(String::from(
+                        "A user name is required (noAuthNoPriv has none)",
+                    )))
+                }
+            },
+            Auth::Tsm(params) => params.security_name.as_str(),
+            Auth::V2c(_) => {
+                return Err(Error::Vacm(String::from(
+                    "Community to security name mapping is not configurable through VACM",
+                )))
+            }
+        };
+
+        for subtree in subtrees {
+            self.add_view(&VacmView {
+                name: view.to_string(),
+                subtree: subtree.clone(),
+                wildcards: Vec::new(),
+                included: true,
+            })
+            .await?;
+        }
+        let model = SecurityModel::from(auth);
+        self.add_group_member(model, security_name, group).await?;
+        self.add_access(&VacmAccess {
+            group: group.to_string(),
+            context_prefix: String::new(),
+            context_match: ContextMatch::Exact,
+            model,
+            level: SecurityLevel::from(auth),
+            read_view: Some(view.to_string()),
+            write_view: None,
+            notify_view: None,
+        })
+        .await
+    }
+
+    async fn destroy(&mut self, status: Oid) -> Result<()> {
+        self.session
+            .set_async(&[(status, Value::Integer(ROW_STATUS_DESTROY))])
+            .await?;
+        Ok(())
+    }
+}
+
+impl SecurityModel {
+    /// The index of a vacmSecurityToGroupEntry for `security_name`.
+    pub fn group_member_index(self, security_name: &str) -> Oid {
+        Oid::from_slice(&[self as u64]).join_octets(security_name.as_bytes())
+    }
+}
+
+impl VacmAccess {
+    /// The index of the vacmAccessEntry.
+    pub fn index(&self) -> Oid {
+        Oid::empty()
+            .join_octets(self.group.as_bytes())
+            .join_octets(self.context_prefix.as_bytes())
+            .join([self.model as u64, self.level as u64])
+    }
+}
+
+impl VacmView {
+    /// The index of the vacmViewTreeFamilyEntry.
+    pub fn index(&self) -> Oid {
+        Oid::empty()
+            .join_octets(self.name.as_bytes())
+            .join([self.subtree.len() as u64])
+            .join(self.subtree.as_slice().iter().copied())
+    }
+
+    /// The vacmViewTreeFamilyMask: one bit per sub-identifier (most
+    /// significant first), cleared for wildcards. Empty if there are
+    /// no wildcards. The mask is at most 16 octets long (RFC 3415),
+    /// which covers subtrees of up to 128 sub-identifiers.
+    pub fn mask(&self) -> Result<Vec<u8>> {
+        if self.subtree.len() > 8 * MAX_MASK_LEN {
+            return Err(Error::Vacm(format!(
+                "Subtree {} has more than {} sub-identifiers",
+                self.subtree,
+                8 * MAX_MASK_LEN
+            )));
+        }
+        if let Some(pos) = self
+            .wildcards
+            .iter()
+            .find(|pos| **pos >= self.subtree.len())
+        {
+            return Err(Error::Vacm(format!(
+                "Wildcard position {} outside of subtree {}",
+                pos, self.subtree
+            )));
+        }
+        if self.wildcards.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut mask = vec![0xff; self.subtree.len().div_ceil(8)];
+        for pos in &self.wildcards {
+            mask[pos / 8] &= !(0x80 >> (pos % 8));
+        }
+        Ok(mask)
+    }
+}
+
+impl From<&Auth> for SecurityModel {
+    fn from(auth: &Auth) -> Self {
+        match auth {
+            Auth::V2c(_) => SecurityModel::V2c,
+            Auth::V3(_) => SecurityModel::Usm,
+            Auth::Tsm(_) => SecurityModel::Tsm,
+        }
+    }
+}
+
+impl From<&V3Level> for SecurityLevel {
+    fn from(level: &V3Level) -> Self {
+        match level {
+            V3Level::NoAuthNoPriv => SecurityLevel::NoAuthNoPriv,
+            V3Level::AuthNoPriv { .. } => SecurityLevel::AuthNoPriv,
+            V3Level::AuthPriv { .. } => SecurityLevel::AuthPriv,
+        }
+    }
+}
+
+impl From<&Auth> for SecurityLevel {
+    fn from(auth: &Auth) -> Self {
+        match auth {
+            Auth::V2c(_) => SecurityLevel::NoAuthNoPriv,
+            Auth::V3(params) => SecurityLevel::from(&params.level),
+            /* TSM sessions always use authPriv. */
+            Auth::Tsm(_) => SecurityLevel::AuthPriv,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* Validation of responses against the request. Agents have been seen
+ * to drop, reorder or substitute varbinds; take nothing for granted. */
+
+use super::error::{Error, Result};
+use super::oid::Oid;
+use super::pdu::PduPtr;
+use super::types::ErrType;
+use super::value::Value;
+use super::variable::VariablePtr;
+
+/// Per-oid results of a query. Exceptions (noSuchObject,
+/// noSuchInstance, endOfMibView) are returned as errors for the
+/// affected oid only.
+pub type VarResults = Vec<(Oid, std::result::Result<Value, ErrType>)>;
+
+/// Validate the response to a get request for `oids`: the response
+/// must contain one varbind per requested oid, for that oid and in
+/// the same order.
+pub fn validate_get(oids: &[Oid], response: &PduPtr) -> Result<VarResults> {
+    oids.iter()
+        .zip(check_vars(oids, response)?)
+        .map(|(oid, var)| {
+            let name = var.get_name();
+            match &name == oid {
+                true => Ok((name, var.get_value())),
+                false => Err(Error::VarBindMismatch(oid.clone(), name)),
+            }
+        })
+        .collect()
+}
+
+/// Validate the response to a getnext request for `oids`: the
+/// response must contain one varbind per requested oid, in the same
+/// order, each following the requested oid (or an endOfMibView
+/// exception for the requested oid).
+pub fn validate_next(oids: &[Oid], response: &PduPtr) -> Result<VarResults> {
+    oids.iter()
+        .zip(check_vars(oids, response)?)
+        .map(|(oid, var)| check_next(oid, var))
+        .collect()
+}
+
+pub(crate) fn check_next(
+    oid: &Oid,
+    var: &VariablePtr,
+) -> Result<(Oid, std::result::Result<Value, ErrType>)> {
+    let name = var.get_name();
+    let value = var.get_value();
+    match (&name > oid, &value) {
+        (true, _) => Ok((name, value)),
+        (false, Err(ErrType::EndOfMibView)) if &name == oid => Ok((name, value)),
+        (false, _) => Err(Error::OidsNotIncreasing),
+    }
+}
+
+fn check_vars<'a>(oids: &[Oid], response: &'a PduPtr) -> Result<Vec<&'a VariablePtr>> {
+    if let Some(err) = response.error() {
+        return Err(err);
+    }
+    let vars: Vec<_> = response.variables().collect();
+    match vars.len() == oids.len() {
+        true => Ok(vars),
+        false => Err(Error::VarBindCount(oids.len(), vars.len())),
+    }
+}
@@ -13,9 +13,10 @@
  ******************************************************************************/
 
 use std::ops::{Deref, DerefMut};
-use std::{mem, slice};
+use std::{mem, ptr, slice};
 
 use super::api;
+use super::ber::{OPAQUE_DOUBLE, OPAQUE_FLOAT};
 use super::oid::Oid;
 use super::types::{ErrType, VarType};
 use super::value::Value;
@@ -33,6 +34,10 @@ impl VariablePtr {
         &*(ptr as *const VariablePtr)
     }
 
+    pub fn as_ptr(&self) -> *const api::netsnmp_variable_list {
+        &self.0 as *const api::netsnmp_variable_list
+    }
+
     pub fn to_owned(&self) -> Variable {
         unsafe {
             let mut var = Box::new(mem::zeroed());
@@ -67,8 +72,8 @@ impl VariablePtr {
             0x42 => Ok(VarType::Gauge),
             0x43 => Ok(VarType::TimeTicks),
             0x46 => Ok(VarType::Counter64),
-            0x48 => Ok(VarType::Float),
-            0x49 => Ok(VarType::Double),
+            0x48 | OPAQUE_FLOAT => Ok(VarType::Float),
+            0x49 | OPAQUE_DOUBLE => Ok(VarType::Double),
             0x50 => Ok(VarType::Integer64),
             0x51 => Ok(VarType::Unsigned64),
 
@@ -121,8 +126,21 @@ impl VariablePtr {
                 VarType::Sequence => Err(ErrType::NotImplemented(VarType::Sequence as u8)),
                 VarType::Set => Err(ErrType::NotImplemented(VarType::Set as u8)),
                 VarType::Opaque => Err(ErrType::NotImplemented(VarType::Opaque as u8)),
-                VarType::Float => Err(ErrType::NotImplemented(VarType::Float as u8)),
-                VarType::Double => Err(ErrType::NotImplemented(VarType::Double as u8)),
+                /* The floatVal and doubleVal members depend on the netsnmp
+                 * configuration; they point to the same buffer, which is
+                 * not necessarily aligned. Truncated values are rejected. */
+                VarType::Float => match self.0.val_len == mem::size_of::<f32>() {
+                    true => Ok(Value::Float(ptr::read_unaligned(
+                        self.0.val.string as *const f32,
+                    ))),
+                    false => Err(ErrType::NotImplemented(VarType::Float as u8)),
+                },
+                VarType::Double => match self.0.val_len == mem::size_of::<f64>() {
+                    true => Ok(Value::Double(ptr::read_unaligned(
+                        self.0.val.string as *const f64,
+                    ))),
+                    false => Err(ErrType::NotImplemented(VarType::Double as u8)),
+                },
                 /* Should never happen when all types from get_type are implemented;
                  * disable panic to trigger warning at compile time.*/
                 //typ => panic!("unimplemented variable type {:?}", typ)
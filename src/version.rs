@@ -15,32 +15,33 @@
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
-use super::api;
 use super::error::{Error, Result};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/* The discriminants are the msgVersion values on the wire, which
+ * are also the values of net-snmp's SNMP_VERSION_* constants. */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 #[serde(tag = "version")]
 #[repr(u32)]
 pub enum Version {
     #[serde(rename = "1")]
-    V1 = api::SNMP_VERSION_1,
+    V1 = 0,
     #[serde(rename = "2c")]
-    V2c = api::SNMP_VERSION_2c,
+    V2c = 1,
     #[serde(rename = "3")]
-    V3 = api::SNMP_VERSION_3,
+    V3 = 3,
 }
 
 impl TryFrom<u32> for Version {
     type Error = Error;
     fn try_from(val: u32) -> Result<Self> {
         match val {
-            api::SNMP_VERSION_1 => Ok(Self::V1),
-            api::SNMP_VERSION_2c => Ok(Self::V2c),
-            api::SNMP_VERSION_3 => Ok(Self::V3),
-            api::SNMP_VERSION_2u => Err(Error::UnsupportedVersion("v2u")),
-            api::SNMP_VERSION_2p => Err(Error::UnsupportedVersion("v2p")),
-            api::SNMP_VERSION_sec => Err(Error::UnsupportedVersion("v2sec")),
-            api::SNMP_VERSION_2star => Err(Error::UnsupportedVersion("v2*")),
+            0 => Ok(Self::V1),
+            1 => Ok(Self::V2c),
+            3 => Ok(Self::V3),
+            2 => Err(Error::UnsupportedVersion("v2u")),
+            129 => Err(Error::UnsupportedVersion("v2p")),
+            128 => Err(Error::UnsupportedVersion("v2sec")),
+            130 => Err(Error::UnsupportedVersion("v2*")),
             _ => Err(Error::InvalidVersion(val)),
         }
     }
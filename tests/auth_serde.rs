@@ -0,0 +1,61 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use netsnmp::{EngineId, EngineInfo, Key, SecretString, V3AuthParams, V3AuthProtocol, V3Key};
+
+#[test]
+fn v3_key() {
+    let params: V3AuthParams =
+        serde_json::from_str(r#"{"protocol":"sha","user":"user","password":"secret"}"#).unwrap();
+    assert_eq!(params.protocol, V3AuthProtocol::SHA);
+    assert_eq!(params.key, V3Key::Password(SecretString::from("secret")));
+
+    let key = Key::from_slice(&[0x9f, 0xb5, 0xcc, 0x03]);
+    for (key, json) in [
+        (
+            V3Key::MasterKey(key.clone()),
+            r#"{"master_key":"0x9fb5cc03"}"#,
+        ),
+        (
+            V3Key::LocalizedKey(key.clone()),
+            r#"{"localized_key":"0x9fb5cc03"}"#,
+        ),
+    ] {
+        assert_eq!(serde_json::to_string(&key).unwrap(), json);
+        assert_eq!(serde_json::from_str::<V3Key>(json).unwrap(), key);
+    }
+
+    /* Colons are accepted in hex keys; empty or odd keys are not. */
+    let parsed: V3Key = serde_json::from_str(r#"{"master_key":"9f:b5:cc:03"}"#).unwrap();
+    assert_eq!(parsed, V3Key::MasterKey(key));
+    assert!(serde_json::from_str::<V3Key>(r#"{"master_key":""}"#).is_err());
+    assert!(serde_json::from_str::<V3Key>(r#"{"master_key":"0x9fb"}"#).is_err());
+}
+
+#[test]
+fn engine_info() {
+    let info = EngineInfo {
+        engine_id: EngineId::from_slice(b"\x80\x00\x1f\x88\x04mock"),
+        engine_boots: 3,
+        engine_time: 1234,
+    };
+    let json = r#"{"engine_id":"0x80001f88046d6f636b","engine_boots":3,"engine_time":1234}"#;
+    assert_eq!(serde_json::to_string(&info).unwrap(), json);
+    assert_eq!(serde_json::from_str::<EngineInfo>(json).unwrap(), info);
+
+    let id: EngineId = serde_json::from_str(r#""80:00:1f:88:04:6d:6f:63:6b""#).unwrap();
+    assert_eq!(id, info.engine_id);
+    assert!(serde_json::from_str::<EngineId>(r#""""#).is_err());
+    assert!(serde_json::from_str::<EngineId>(r#""mock""#).is_err());
+}
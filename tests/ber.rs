@@ -0,0 +1,283 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+use netsnmp::{
+    BerPdu, ErrType, Error, Message, Msg, Pdu, ScopedPdu, ScopedPduData, TrapV1, UsmSecurityParams,
+    V3Header, Value, VarBind, Version, SECURITY_MODEL_USM,
+};
+
+mod common;
+
+use common::oid;
+
+const GET_SYSDESCR: &[u8] = &[
+    0x30, 0x26, 0x02, 0x01, 0x01, 0x04, 0x06, 0x70, 0x75, 0x62, 0x6c, 0x69, 0x63, 0xa0, 0x19, 0x02,
+    0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x0e, 0x30, 0x0c, 0x06, 0x08, 0x2b, 0x06,
+    0x01, 0x02, 0x01, 0x01, 0x01, 0x00, 0x05, 0x00,
+];
+
+fn roundtrip(msg: &Message) {
+    let data = msg.encode().unwrap();
+    assert_eq!(&Message::decode(&data).unwrap(), msg);
+}
+
+#[test]
+fn decode_get_request() {
+    let msg = Message::decode(GET_SYSDESCR).unwrap();
+    match &msg {
+        Message::Community {
+            version,
+            community,
+            pdu,
+        } => {
+            assert_eq!(*version, Version::V2c);
+            assert_eq!(community, b"public");
+            assert_eq!(pdu.command, Msg::Get);
+            assert_eq!(pdu.request_id, 1);
+            assert_eq!(
+                pdu.varbinds,
+                vec![VarBind {
+                    oid: oid("1.3.6.1.2.1.1.1.0"),
+                    value: Ok(Value::Null)
+                }]
+            );
+        }
+        _ => panic!("expected a community message"),
+    }
+    assert_eq!(msg.encode().unwrap(), GET_SYSDESCR);
+}
+
+#[test]
+fn all_value_types() {
+    let values = vec![
+        Ok(Value::Boolean(true)),
+        Ok(Value::Integer(0)),
+        Ok(Value::Integer(-129)),
+        Ok(Value::Integer(i64::MIN)),
+        Ok(Value::Integer(i64::MAX)),
+        Ok(Value::BitStr(vec![0x80])),
+        Ok(Value::OctetStr(vec![])),
+        Ok(Value::OctetStr(vec![0xaa; 300])),
+        Ok(Value::Null),
+        Ok(Value::Oid(oid("1.3.6.1.4.1.8072.3.2.10"))),
+        Ok(Value::Oid(oid("2.999.18446744073709551615"))),
+        Ok(Value::IpAddress(u32::from_ne_bytes([10, 0, 0, 1]))),
+        Ok(Value::Counter(4294967295)),
+        Ok(Value::Gauge(128)),
+        Ok(Value::TimeTicks(0)),
+        Ok(Value::Counter64(u64::MAX)),
+        Ok(Value::Unsigned64(1 << 40)),
+        Ok(Value::Integer64(-5)),
+        Ok(Value::Float(1.5)),
+        Ok(Value::Double(-2.25)),
+        Err(ErrType::NoSuchObject),
+        Err(ErrType::NoSuchInstance),
+        Err(ErrType::EndOfMibView),
+    ];
+    let mut pdu = BerPdu::new(Msg::Response, 42);
+    pdu.varbinds = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| VarBind {
+            oid: oid("1.3.6.1.2.1").join([i as u64]),
+            value,
+        })
+        .collect();
+    roundtrip(&Message::Community {
+        version: Version::V1,
+        community: b"private".to_vec(),
+        pdu,
+    });
+}
+
+#[test]
+fn v1_trap() {
+    let mut pdu = BerPdu::new(Msg::Trap, 0);
+    pdu.trap = Some(TrapV1 {
+        enterprise: oid("1.3.6.1.4.1.8072"),
+        agent_addr: [192, 168, 0, 1],
+        generic_trap: 6,
+        specific_trap: 17,
+        time_stamp: 123456,
+    });
+    roundtrip(&Message::Community {
+        version: Version::V1,
+        community: b"public".to_vec(),
+        pdu,
+    });
+}
+
+#[test]
+fn v3_messages() {
+    let header = V3Header {
+        msg_id: 12345,
+        max_size: 65507,
+        flags: V3Header::FLAG_AUTH | V3Header::FLAG_REPORTABLE,
+        security_model: SECURITY_MODEL_USM,
+    };
+    let usm = UsmSecurityParams {
+        engine_id: vec![0x80, 0x00, 0x1f, 0x88, 0x80],
+        engine_boots: 3,
+        engine_time: 1000,
+        user_name: b"user".to_vec(),
+        auth_params: vec![0; 12],
+        priv_params: vec![],
+    };
+    assert_eq!(UsmSecurityParams::decode(&usm.encode()).unwrap(), usm);
+
+    let mut pdu = BerPdu::new(Msg::GetBulk, 7);
+    pdu.error_status = 1;
+    pdu.error_index = 10;
+    pdu.varbinds.push(VarBind {
+        oid: oid("1.3.6.1.2.1.2.2.1.2"),
+        value: Ok(Value::Null),
+    });
+    roundtrip(&Message::V3 {
+        header: header.clone(),
+        security_params: usm.encode(),
+        data: ScopedPduData::Plain(ScopedPdu {
+            context_engine_id: usm.engine_id.clone(),
+            context_name: Vec::new(),
+            pdu,
+        }),
+    });
+    roundtrip(&Message::V3 {
+        header: V3Header {
+            flags: header.flags | V3Header::FLAG_PRIV,
+            ..header
+        },
+        security_params: usm.encode(),
+        data: ScopedPduData::Encrypted(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+    });
+}
+
+#[test]
+fn unsigned_without_leading_zero() {
+    let mut data = GET_SYSDESCR.to_vec();
+    /* Replace the null value by Counter32 0xffffffff without padding
+     * (lengths stay the same up to the value). */
+    data.truncate(data.len() - 2);
+    data.extend([0x41, 0x04, 0xff, 0xff, 0xff, 0xff]);
+    data[1] += 4;
+    data[14] += 4;
+    data[25] += 4;
+    data[27] += 4;
+    let msg = Message::decode(&data).unwrap();
+    assert_eq!(
+        msg.pdu().unwrap().varbinds[0].value,
+        Ok(Value::Counter(0xffffffff))
+    );
+}
+
+#[test]
+fn malformed_input() {
+    for len in 0..GET_SYSDESCR.len() {
+        assert!(Message::decode(&GET_SYSDESCR[..len]).is_err());
+    }
+    let mut trailing = GET_SYSDESCR.to_vec();
+    trailing.push(0);
+    assert!(Message::decode(&trailing).is_err());
+    assert!(Message::decode(&[0x30, 0x80, 0x00, 0x00]).is_err());
+    assert!(Message::decode(&[0x30, 0x84, 0xff, 0xff, 0xff, 0xff]).is_err());
+    assert!(BerPdu::decode(&[0xa0, 0x03, 0x02, 0x09, 0x00]).is_err());
+}
+
+#[test]
+fn netsnmp_pdu_conversion() {
+    let msg = Message::decode(GET_SYSDESCR).unwrap();
+    let pdu = Pdu::from_message(&msg).unwrap();
+    assert_eq!(pdu.to_message().unwrap(), msg);
+
+    let mut ber = BerPdu::new(Msg::Response, 9);
+    ber.varbinds.push(VarBind {
+        oid: oid("1.3.6.1.2.1.1.3.0"),
+        value: Ok(Value::TimeTicks(100)),
+    });
+    ber.varbinds.push(VarBind {
+        oid: oid("1.3.6.1.2.1.1.4.0"),
+        value: Err(ErrType::NoSuchInstance),
+    });
+    assert_eq!(Pdu::from_ber(&ber).unwrap().to_ber().unwrap(), ber);
+}
+
+#[test]
+fn netsnmp_unsupported_values() {
+    let mut ber = BerPdu::new(Msg::Response, 10);
+    ber.varbinds.push(VarBind {
+        oid: oid("1.3.6.1.4.1.2021.10.1.6.1"),
+        value: Ok(Value::Float(0.25)),
+    });
+    ber.varbinds.push(VarBind {
+        oid: oid("1.3.6.1.4.1.2021.10.1.6.2"),
+        value: Ok(Value::Double(-1.5)),
+    });
+    assert_eq!(Pdu::from_ber(&ber).unwrap().to_ber().unwrap(), ber);
+
+    /* Netsnmp has no representation for these. */
+    for value in [
+        Ok(Value::Boolean(true)),
+        Ok(Value::Opaque),
+        Err(ErrType::NotImplemented(0x44)),
+        Err(ErrType::Undefined),
+    ] {
+        let mut ber = BerPdu::new(Msg::Response, 11);
+        ber.varbinds.push(VarBind {
+            oid: oid("1.3.6.1.2.1.1.1.0"),
+            value,
+        });
+        assert!(matches!(
+            Pdu::from_ber(&ber),
+            Err(Error::UnsupportedValue(_))
+        ));
+    }
+}
+
+#[test]
+fn netsnmp_constants() {
+    use netsnmp::api;
+
+    for (msg, val) in [
+        (Msg::Get, api::SNMP_MSG_GET),
+        (Msg::GetNext, api::SNMP_MSG_GETNEXT),
+        (Msg::Response, api::SNMP_MSG_RESPONSE),
+        (Msg::Set, api::SNMP_MSG_SET),
+        (Msg::GetBulk, api::SNMP_MSG_GETBULK),
+        (Msg::Inform, api::SNMP_MSG_INFORM),
+        (Msg::Trap, api::SNMP_MSG_TRAP),
+        (Msg::Trap2, api::SNMP_MSG_TRAP2),
+        (Msg::Report, api::SNMP_MSG_REPORT),
+    ] {
+        assert_eq!(msg as u32, val);
+        assert_eq!(Msg::try_from(val).unwrap(), msg);
+    }
+    for (version, val) in [
+        (Version::V1, api::SNMP_VERSION_1),
+        (Version::V2c, api::SNMP_VERSION_2c),
+        (Version::V3, api::SNMP_VERSION_3),
+    ] {
+        assert_eq!(version as u32, val);
+        assert_eq!(Version::try_from(val).unwrap(), version);
+    }
+    for val in [
+        api::SNMP_VERSION_2u,
+        api::SNMP_VERSION_2p,
+        api::SNMP_VERSION_sec,
+        api::SNMP_VERSION_2star,
+    ] {
+        assert!(matches!(
+            Version::try_from(val),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::io::Read;
+use std::sync::{Arc, OnceLock};
+
+use netsnmp::{Message, Msg, NetSNMP, Oid, Value};
+
+/* The library is initialized once for all tests. */
+pub fn snmp() -> &'static Arc<NetSNMP> {
+    static SNMP: OnceLock<Arc<NetSNMP>> = OnceLock::new();
+    SNMP.get_or_init(|| Arc::new(netsnmp::init("netsnmp-rs-test")))
+}
+
+pub fn oid(oid: &str) -> Oid {
+    oid.parse().unwrap()
+}
+
+/* Read one BER-framed message. */
+pub fn read_message<S: Read>(stream: &mut S) -> Vec<u8> {
+    let mut message = vec![0; 2];
+    stream.read_exact(&mut message).unwrap();
+    let len = match message[1] {
+        len if len < 0x80 => len as usize,
+        n => {
+            let mut bytes = vec![0; (n & 0x7f) as usize];
+            stream.read_exact(&mut bytes).unwrap();
+            message.extend(&bytes);
+            bytes.iter().fold(0, |len, b| len << 8 | *b as usize)
+        }
+    };
+    let start = message.len();
+    message.resize(start + len, 0);
+    stream.read_exact(&mut message[start..]).unwrap();
+    message
+}
+
+/* Answer a community-based request with "stream" for every
+ * variable. */
+pub fn respond(request: &[u8]) -> Vec<u8> {
+    let mut message = Message::decode(request).unwrap();
+    if let Message::Community { pdu, .. } = &mut message {
+        pdu.command = Msg::Response;
+        for varbind in &mut pdu.varbinds {
+            varbind.value = Ok(Value::OctetStr(b"stream".to_vec()));
+        }
+    }
+    message.encode().unwrap()
+}
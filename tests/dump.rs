@@ -0,0 +1,178 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+use netsnmp::{parse_snmprec, parse_snmpwalk, Value};
+
+mod common;
+
+use common::{oid, snmp};
+
+#[test]
+fn snmpwalk_numeric() {
+    let walk = r#".1.3.6.1.2.1.1.1.0 = STRING: "Linux host 5.4 \"lts\"
+second line"
+.1.3.6.1.2.1.1.2.0 = OID: .1.3.6.1.4.1.8072.3.2.10
+.1.3.6.1.2.1.1.3.0 = Timeticks: (12345) 0:02:03.45
+.1.3.6.1.2.1.1.4.0 = ""
+.1.3.6.1.2.1.2.2.1.3.1 = INTEGER: softwareLoopback(24)
+.1.3.6.1.2.1.2.2.1.5.2 = Gauge32: 10000000 bits/s
+.1.3.6.1.2.1.2.2.1.6.2 = Hex-STRING: 00 50 56 9A 1B 2C 00 50 56 9A 1B 2C 00 50 56 9A
+1B 2C
+.1.3.6.1.2.1.2.2.1.10.1 = Counter32: 123
+.1.3.6.1.2.1.4.20.1.1.10.0.0.1 = IpAddress: 10.0.0.1
+.1.3.6.1.2.1.31.1.1.1.6.1 = Counter64: 18446744073709551615
+.1.3.6.1.4.1.8072.1.1 = Opaque: Float: 1.500000
+.1.3.6.1.4.1.8072.1.2 = No Such Instance currently exists at this OID
+.1.3.6.1.4.1.8072.1.3 = BITS: 80 40 zero(0) nine(9)
+"#;
+    let vars = parse_snmpwalk(walk).unwrap();
+    assert_eq!(vars.len(), 12);
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.1.1.0")],
+        Value::OctetStr(b"Linux host 5.4 \"lts\"\nsecond line".to_vec())
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.1.2.0")],
+        Value::Oid(oid("1.3.6.1.4.1.8072.3.2.10"))
+    );
+    assert_eq!(vars[&oid("1.3.6.1.2.1.1.3.0")], Value::TimeTicks(12345));
+    assert_eq!(vars[&oid("1.3.6.1.2.1.1.4.0")], Value::OctetStr(Vec::new()));
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.3.1")], Value::Integer(24));
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.5.2")], Value::Gauge(10000000));
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.2.2.1.6.2")],
+        Value::OctetStr([0x00, 0x50, 0x56, 0x9a, 0x1b, 0x2c].repeat(3))
+    );
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.10.1")], Value::Counter(123));
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.4.20.1.1.10.0.0.1")],
+        Value::IpAddress(u32::from_ne_bytes([10, 0, 0, 1]))
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.31.1.1.1.6.1")],
+        Value::Counter64(u64::MAX)
+    );
+    assert_eq!(vars[&oid("1.3.6.1.4.1.8072.1.1")], Value::Float(1.5));
+    assert_eq!(
+        vars[&oid("1.3.6.1.4.1.8072.1.3")],
+        Value::BitStr(vec![0x80, 0x40])
+    );
+}
+
+#[test]
+#[ignore = "requires the net-snmp MIB files"]
+fn snmpwalk_symbolic() {
+    snmp();
+    let walk = "SNMPv2-MIB::sysObjectID.0 = OID: NET-SNMP-MIB::netSnmpAgentOIDs.10\n\
+                IF-MIB::ifType.1 = INTEGER: softwareLoopback(24)\n";
+    let vars = parse_snmpwalk(walk).unwrap();
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.1.2.0")],
+        Value::Oid(oid("1.3.6.1.4.1.8072.3.2.10"))
+    );
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.3.1")], Value::Integer(24));
+}
+
+#[test]
+fn snmpwalk_errors() {
+    let result =
+        parse_snmpwalk(".1.3.6.1.2.1.1.3.0 = Timeticks: (1) 0:00:00.01\nTimeout: No Response\n");
+    assert!(matches!(result, Err(netsnmp::Error::DumpParse(2, _))));
+    let result = parse_snmpwalk(".1.3.6.1.2.1.1.3.0 = Unknown: 1\n");
+    assert!(matches!(result, Err(netsnmp::Error::DumpParse(1, _))));
+}
+
+#[test]
+fn snmprec() {
+    let rec = "# sysDescr and friends\n\
+               1.3.6.1.2.1.1.1.0|4|Linux zeus 4.8.6|custom\n\
+               1.3.6.1.2.1.1.2.0|6|1.3.6.1.4.1.8072.3.2.10\n\
+               1.3.6.1.2.1.1.3.0|67|233425120\n\
+               1.3.6.1.2.1.1.6.0|4x|4d6f73636f77\n\
+               1.3.6.1.2.1.2.2.1.1.1|2|1\n\
+               1.3.6.1.2.1.4.20.1.1.10.0.0.2|64x|0a000002\n\
+               1.3.6.1.2.1.31.1.1.1.6.1|70|1234567890123\n\
+               1.3.6.1.2.1.99.0|128|\n";
+    let vars = parse_snmprec(rec).unwrap();
+    assert_eq!(vars.len(), 7);
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.1.1.0")],
+        Value::OctetStr(b"Linux zeus 4.8.6|custom".to_vec())
+    );
+    assert_eq!(vars[&oid("1.3.6.1.2.1.1.3.0")], Value::TimeTicks(233425120));
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.1.6.0")],
+        Value::OctetStr(b"Moscow".to_vec())
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.4.20.1.1.10.0.0.2")],
+        Value::IpAddress(u32::from_ne_bytes([10, 0, 0, 2]))
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.31.1.1.1.6.1")],
+        Value::Counter64(1234567890123)
+    );
+    assert!(parse_snmprec("1.3.6.1.2.1.1.1.0|4:numeric|x\n").is_err());
+
+    /* Hex oids are decoded before they are parsed. */
+    let vars = parse_snmprec("1.3.6.1.2.1.1.2.0|6x|312e332e362e312e34\n").unwrap();
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.1.2.0")],
+        Value::Oid(oid("1.3.6.1.4"))
+    );
+    assert!(parse_snmprec("1.3.6.1.2.1.1.2.0|6x|616263\n").is_err());
+    assert!(parse_snmprec("1.3.6.1.2.1.1.2.0|6x|ff\n").is_err());
+}
+
+#[test]
+fn display_hints() {
+    /* Decimal hints are undone; other numbers are kept as text. */
+    let walk = ".1.3.6.1.4.1.2021.13.16.2.1.3.1 = INTEGER: 23.5\n\
+                .1.3.6.1.4.1.2021.13.16.2.1.3.2 = Gauge32: 0.05\n\
+                .1.3.6.1.4.1.2021.13.16.2.1.3.3 = INTEGER: 0x1f\n";
+    let vars = parse_snmpwalk(walk).unwrap();
+    assert_eq!(
+        vars[&oid("1.3.6.1.4.1.2021.13.16.2.1.3.1")],
+        Value::Integer(235)
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.4.1.2021.13.16.2.1.3.2")],
+        Value::Gauge(5)
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.4.1.2021.13.16.2.1.3.3")],
+        Value::OctetStr(b"0x1f".to_vec())
+    );
+}
+
+#[test]
+fn snmprec_numbers() {
+    let rec = "1.3.6.1.2.1.2.2.1.1.1|2x|ff\n\
+               1.3.6.1.2.1.2.2.1.1.2|2x|0100\n\
+               1.3.6.1.2.1.2.2.1.10.1|65x|0000ffff\n\
+               1.3.6.1.2.1.31.1.1.1.6.1|70x|00000123456789ab\n\
+               1.3.6.1.2.1.2.2.1.5.1|66|fast\n";
+    let vars = parse_snmprec(rec).unwrap();
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.1.1")], Value::Integer(-1));
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.1.2")], Value::Integer(256));
+    assert_eq!(vars[&oid("1.3.6.1.2.1.2.2.1.10.1")], Value::Counter(65535));
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.31.1.1.1.6.1")],
+        Value::Counter64(0x123456789ab)
+    );
+    assert_eq!(
+        vars[&oid("1.3.6.1.2.1.2.2.1.5.1")],
+        Value::OctetStr(b"fast".to_vec())
+    );
+}
@@ -0,0 +1,113 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use netsnmp::{EngineId, EngineIdFormat};
+
+fn engine_id(s: &str) -> EngineId {
+    s.parse().unwrap()
+}
+
+#[test]
+fn parse() {
+    let id = engine_id("0x80001f88046d6f636b");
+    assert_eq!(id.as_slice(), b"\x80\x00\x1f\x88\x04mock");
+    assert_eq!(engine_id("80:00:1f:88:04:6d:6f:63:6b"), id);
+    assert_eq!(engine_id("0X80001F88046D6F636B"), id);
+    for invalid in ["", "0x", "0x800", "80001g", "mock"] {
+        assert!(invalid.parse::<EngineId>().is_err(), "{:?}", invalid);
+    }
+
+    /* Engine ids are 5 to 32 octets long. */
+    assert_eq!(engine_id("0x80001f8801").len(), 5);
+    assert_eq!(engine_id(&"01".repeat(32)).len(), 32);
+    for invalid in ["0x80001f88", "01".repeat(33).as_str()] {
+        assert!(invalid.parse::<EngineId>().is_err(), "{:?}", invalid);
+    }
+}
+
+#[test]
+fn display() {
+    let id = EngineId::from_slice(b"\x80\x00\x1f\x88\x04mock");
+    assert_eq!(id.to_string(), "0x80001f88046d6f636b");
+    assert_eq!(engine_id(&id.to_string()), id);
+    assert_eq!(id.enterprise(), Some(8072));
+}
+
+#[test]
+fn format() {
+    for (id, format, display) in [
+        (
+            "0x80001f8801c0000201",
+            EngineIdFormat::Ipv4(Ipv4Addr::new(192, 0, 2, 1)),
+            "ipv4 192.0.2.1",
+        ),
+        (
+            "0x80001f880220010db8000000000000000000000001",
+            EngineIdFormat::Ipv6("2001:db8::1".parse::<Ipv6Addr>().unwrap()),
+            "ipv6 2001:db8::1",
+        ),
+        (
+            "0x80001f8803020000000001",
+            EngineIdFormat::Mac([2, 0, 0, 0, 0, 1]),
+            "mac 02:00:00:00:00:01",
+        ),
+        (
+            "0x80001f88046d6f636b",
+            EngineIdFormat::Text(String::from("mock")),
+            "text \"mock\"",
+        ),
+        (
+            "0x80001f88050102",
+            EngineIdFormat::Octets(vec![1, 2]),
+            "octets 01:02",
+        ),
+        (
+            "0x80001f88060102",
+            EngineIdFormat::Reserved(6, vec![1, 2]),
+            "reserved(6) 01:02",
+        ),
+        (
+            "0x80001f88800102",
+            EngineIdFormat::Enterprise(128, vec![1, 2]),
+            "enterprise(128) 01:02",
+        ),
+        /* Pre-RFC 3411 engine ids have no format octet. */
+        (
+            "0x000000090102030405060708",
+            EngineIdFormat::Legacy(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+            "legacy 01:02:03:04:05:06:07:08",
+        ),
+    ] {
+        let format_of = engine_id(id).format().unwrap();
+        assert_eq!(format_of, format);
+        assert_eq!(format_of.to_string(), display);
+    }
+
+    /* Values that do not match their format. */
+    assert_eq!(
+        engine_id("0x80001f8801c00002").format(),
+        Some(EngineIdFormat::Octets(vec![0xc0, 0x00, 0x02]))
+    );
+    assert_eq!(
+        engine_id("0x80001f8803020000000001ff").format(),
+        Some(EngineIdFormat::Octets(vec![2, 0, 0, 0, 0, 1, 0xff]))
+    );
+    assert_eq!(
+        engine_id("0x80001f8804ff").format(),
+        Some(EngineIdFormat::Octets(vec![0xff]))
+    );
+    assert_eq!(EngineId::from_slice(b"\x80\x00\x1f\x88").format(), None);
+}
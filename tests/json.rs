@@ -0,0 +1,116 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+use netsnmp::{ErrType, NdjsonWriter, Value, VarBindRecord, VarBindType};
+
+mod common;
+
+use common::oid;
+
+#[test]
+fn record_format() {
+    let record = VarBindRecord::new(oid("1.3.6.1.2.1.1.3.0"), &Ok(Value::TimeTicks(12345)));
+    assert_eq!(
+        serde_json::to_string(&record).unwrap(),
+        r#"{"oid":"1.3.6.1.2.1.1.3.0","type":"TimeTicks","value":12345,"display":"(12345) 0:02:03.45"}"#
+    );
+
+    let record = VarBindRecord::new(
+        oid("1.3.6.1.2.1.2.2.1.6.2"),
+        &Ok(Value::OctetStr(vec![0, 0x50, 0x56])),
+    );
+    assert_eq!(
+        serde_json::to_string(&record).unwrap(),
+        r#"{"oid":"1.3.6.1.2.1.2.2.1.6.2","type":"OctetString","value":"005056","encoding":"hex","display":"00 50 56"}"#
+    );
+
+    let record = VarBindRecord::new(oid("1.3.6.1.2.1.1.9.0"), &Err(ErrType::NoSuchInstance));
+    assert_eq!(record.typ, VarBindType::NoSuchInstance);
+    assert_eq!(record.value, serde_json::Value::Null);
+}
+
+#[test]
+fn record_roundtrip() {
+    let values = [
+        Ok(Value::Boolean(true)),
+        Ok(Value::Integer(-5)),
+        Ok(Value::BitStr(vec![0x80, 0x01])),
+        Ok(Value::OctetStr(b"Linux host".to_vec())),
+        Ok(Value::OctetStr(vec![0x00, 0x50, 0xff])),
+        Ok(Value::Null),
+        Ok(Value::Oid(oid("1.3.6.1.4.1.8072.3.2.10"))),
+        Ok(Value::IpAddress(u32::from_ne_bytes([10, 0, 0, 1]))),
+        Ok(Value::MacAddress(0x0050569a1b2c)),
+        Ok(Value::Counter(123)),
+        Ok(Value::Gauge(10000000)),
+        Ok(Value::TimeTicks(12345)),
+        Ok(Value::Counter64(u64::MAX)),
+        Ok(Value::Float(1.5)),
+        Ok(Value::Double(2.25)),
+        Ok(Value::Integer64(-7)),
+        Ok(Value::Unsigned64(8)),
+        Err(ErrType::NoSuchObject),
+        Err(ErrType::EndOfMibView),
+        Err(ErrType::NotImplemented(0x44)),
+    ];
+    for value in values {
+        let record = VarBindRecord::new(oid("1.3.6.1.2.1.1.1.0"), &value);
+        let decoded: VarBindRecord =
+            serde_json::from_str(&serde_json::to_string(&record).unwrap()).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.to_value().unwrap(), value);
+    }
+}
+
+#[test]
+fn non_finite_floats() {
+    for (value, json) in [
+        (Value::Double(f64::INFINITY), r#""Infinity""#),
+        (Value::Double(f64::NEG_INFINITY), r#""-Infinity""#),
+        (Value::Float(f32::INFINITY), r#""Infinity""#),
+    ] {
+        let record = VarBindRecord::new(oid("1.3.6.1.4.1.8072.1.1"), &Ok(value.clone()));
+        assert_eq!(record.value.to_string(), json);
+        assert_eq!(record.to_value().unwrap(), Ok(value));
+    }
+
+    let record = VarBindRecord::new(oid("1.3.6.1.4.1.8072.1.1"), &Ok(Value::Float(f32::NAN)));
+    assert_eq!(record.value, serde_json::Value::from("NaN"));
+    let decoded: VarBindRecord =
+        serde_json::from_str(&serde_json::to_string(&record).unwrap()).unwrap();
+    assert!(matches!(decoded.to_value().unwrap(), Ok(Value::Float(v)) if v.is_nan()));
+}
+
+#[test]
+fn ndjson_writer() {
+    let mut writer = NdjsonWriter::new(Vec::new());
+    writer
+        .write_var(
+            oid("1.3.6.1.2.1.1.5.0"),
+            &Ok(Value::OctetStr(b"host".to_vec())),
+        )
+        .unwrap();
+    writer
+        .write_var(oid("1.3.6.1.2.1.1.6.0"), &Err(ErrType::NoSuchInstance))
+        .unwrap();
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"oid":"1.3.6.1.2.1.1.5.0","type":"OctetString","value":"host","encoding":"utf8","display":"\"host\""}"#,
+            r#"{"oid":"1.3.6.1.2.1.1.6.0","type":"NoSuchInstance","value":null,"display":"No Such Instance currently exists at this OID"}"#,
+        ]
+    );
+}
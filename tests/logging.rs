@@ -0,0 +1,73 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+/* With the "tracing" feature, messages go to tracing instead. */
+#![cfg(not(feature = "tracing"))]
+
+use std::ffi::CString;
+use std::sync::Mutex;
+
+use netsnmp::api;
+
+static RECORDS: Mutex<Vec<(log::Level, String, String)>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        RECORDS.lock().unwrap().push((
+            record.level(),
+            record.target().to_string(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+fn snmp_log(priority: u32, msg: &str) {
+    let msg = CString::new(msg).unwrap();
+    unsafe { api::snmp_log(priority as i32, b"%s\0".as_ptr() as *const _, msg.as_ptr()) };
+}
+
+#[test]
+fn route_logs() {
+    log::set_logger(&Capture).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+    let snmp = netsnmp::init("logging");
+    snmp.route_logs();
+
+    snmp_log(api::LOG_ERR, "Cannot open socket\n");
+    snmp_log(api::LOG_DEBUG, "usm: ");
+    snmp_log(api::LOG_DEBUG, "Looking up user ");
+    snmp_log(api::LOG_DEBUG, "admin\n");
+
+    let records = RECORDS.lock().unwrap();
+    let netsnmp: Vec<_> = records
+        .iter()
+        .filter(|(_, target, _)| target.starts_with("netsnmp"))
+        .map(|(level, target, msg)| (*level, target.as_str(), msg.as_str()))
+        .collect();
+    assert_eq!(
+        netsnmp,
+        [
+            (log::Level::Error, "netsnmp", "Cannot open socket"),
+            (log::Level::Debug, "netsnmp::usm", "Looking up user admin"),
+        ]
+    );
+}
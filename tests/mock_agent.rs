@@ -0,0 +1,807 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+//! Queries against the in-process mock agent; these need no snmpd.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use netsnmp::{
+    Auth, BulkResponse, EngineId, EngineInfo, ErrType, Error, Fault, MockAgent, MockAgentBuilder,
+    Pdu, PoolConfig, RetryPolicy, SecretString, SessionInfo, SessionPool, SingleSession, SyncQuery,
+    UsmAdmin, V2cAuth, V3Auth, V3AuthParams, V3AuthProtocol, V3Key, V3Level, V3PrivParams,
+    V3PrivProtocol, Value, Version,
+};
+
+mod common;
+
+use common::{oid, snmp};
+
+fn v3_level() -> V3Level {
+    v3_user("user", "authpassword", "privpassword")
+}
+
+fn v3_user(user: &str, auth: &str, privacy: &str) -> V3Level {
+    V3Level::AuthPriv {
+        auth: V3AuthParams {
+            protocol: V3AuthProtocol::SHA,
+            user: String::from(user),
+            key: V3Key::Password(SecretString::from(auth)),
+        },
+        privacy: V3PrivParams {
+            protocol: V3PrivProtocol::AES,
+            key: V3Key::Password(SecretString::from(privacy)),
+        },
+    }
+}
+
+fn agent() -> MockAgent {
+    MockAgentBuilder::new()
+        .add_var(oid("1.3.6.1.2.1.1.1.0"), Value::OctetStr(b"mock".to_vec()))
+        .add_var(oid("1.3.6.1.2.1.1.3.0"), Value::TimeTicks(4200))
+        .add_var(oid("1.3.6.1.2.1.1.5.0"), Value::OctetStr(b"host".to_vec()))
+        .add_var(
+            oid("1.3.6.1.2.1.2.2.1.2.1"),
+            Value::OctetStr(b"lo".to_vec()),
+        )
+        .add_var(
+            oid("1.3.6.1.2.1.2.2.1.2.2"),
+            Value::OctetStr(b"eth0".to_vec()),
+        )
+        .add_var(oid("1.3.6.1.2.1.2.2.1.10.1"), Value::Counter(100))
+        .add_var(oid("1.3.6.1.2.1.2.2.1.10.2"), Value::Counter(200))
+        .add_user("user", v3_level())
+        .start()
+        .unwrap()
+}
+
+fn open(agent: &MockAgent, auth: &Auth, version: Option<Version>) -> SingleSession {
+    let builder = snmp()
+        .session()
+        .set_peer(agent.peer().as_bytes())
+        .unwrap()
+        .set_auth(auth)
+        .unwrap()
+        .set_retries(0)
+        .set_timeout(Duration::from_millis(500));
+    match version {
+        Some(version) => builder.set_version(version),
+        None => builder,
+    }
+    .open_single()
+    .unwrap()
+}
+
+fn v2c() -> Auth {
+    Auth::V2c(V2cAuth {
+        community: SecretString::from("public"),
+    })
+}
+
+fn v3() -> Auth {
+    v3_auth(v3_level())
+}
+
+fn v3_auth(level: V3Level) -> Auth {
+    Auth::V3(V3Auth {
+        level,
+        context: None,
+        context_engine: None,
+        security_engine: None,
+        destination_engine: None,
+    })
+}
+
+#[test]
+fn v2c_sync() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+
+    let var = session.get(&oid("1.3.6.1.2.1.1.3.0")).unwrap().unwrap();
+    assert_eq!(var.get_value(), Ok(Value::TimeTicks(4200)));
+    let var = session.get(&oid("1.3.6.1.2.1.1.4.0")).unwrap().unwrap();
+    assert_eq!(var.get_value(), Err(ErrType::NoSuchObject));
+
+    let var = session
+        .get_next(&oid("1.3.6.1.2.1.1.3.0"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(var.get_name(), oid("1.3.6.1.2.1.1.5.0"));
+    let var = session
+        .get_next(&oid("1.3.6.1.2.1.2.2.1.10.2"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(var.get_value(), Err(ErrType::EndOfMibView));
+
+    let name = oid("1.3.6.1.2.1.1.5.0");
+    session
+        .set(&[(name.clone(), Value::OctetStr(b"renamed".to_vec()))])
+        .unwrap();
+    assert_eq!(agent.get(&name), Some(Value::OctetStr(b"renamed".to_vec())));
+    let response = session.set(&[(name, Value::Integer(1))]).unwrap();
+    assert!(response.error().is_some());
+}
+
+#[tokio::test]
+async fn v2c_async_bulk() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+
+    let table = oid("1.3.6.1.2.1.2.2.1");
+    let pdu = session
+        .get_bulk_async(
+            &[oid("1.3.6.1.2.1.1.1.0")],
+            &[table.join([2]), table.join([10])],
+            2,
+        )
+        .await
+        .unwrap();
+    let vars: Vec<_> = pdu
+        .variables()
+        .map(|var| (var.get_name(), var.get_value()))
+        .collect();
+    assert_eq!(
+        vars,
+        vec![
+            (oid("1.3.6.1.2.1.1.3.0"), Ok(Value::TimeTicks(4200))),
+            (table.join([2, 1]), Ok(Value::OctetStr(b"lo".to_vec()))),
+            (table.join([10, 1]), Ok(Value::Counter(100))),
+            (table.join([2, 2]), Ok(Value::OctetStr(b"eth0".to_vec()))),
+            (table.join([10, 2]), Ok(Value::Counter(200))),
+        ]
+    );
+}
+
+#[test]
+fn v1_no_such_name() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), Some(Version::V1));
+
+    let response = session
+        .synch_request(
+            Pdu::get()
+                .add_oid(&oid("1.3.6.1.2.1.1.1.0"))
+                .add_oid(&oid("1.3.6.1.2.1.1.4.0")),
+        )
+        .unwrap();
+    assert_eq!(response.error_status(), 2);
+    assert_eq!(response.error_index(), 2);
+}
+
+#[tokio::test]
+async fn v1_emulation() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), Some(Version::V1));
+
+    /* noSuchName errors are translated into exceptions. */
+    let oids = [oid("1.3.6.1.2.1.1.1.0"), oid("1.3.6.1.2.1.1.4.0")];
+    let expected = vec![
+        (oids[0].clone(), Ok(Value::OctetStr(b"mock".to_vec()))),
+        (oids[1].clone(), Err(ErrType::NoSuchObject)),
+    ];
+    assert_eq!(session.get_values(&oids).unwrap(), expected);
+    assert_eq!(session.get_values_async(&oids).await.unwrap(), expected);
+
+    let var = session
+        .get_next(&oid("1.3.6.1.2.1.2.2.1.10.2"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(var.get_value(), Err(ErrType::EndOfMibView));
+
+    /* GETBULK is emulated with GETNEXT requests. */
+    let sys_uptime = oid("1.3.6.1.2.1.1.3");
+    let columns = [oid("1.3.6.1.2.1.2.2.1.2"), oid("1.3.6.1.2.1.2.2.1.10")];
+    let pdu = session
+        .get_bulk_async(&[sys_uptime.clone()], &columns, 3)
+        .await
+        .unwrap();
+    let response = BulkResponse::split(&[sys_uptime], &columns, &pdu).unwrap();
+    assert_eq!(
+        response.non_repeaters,
+        vec![(oid("1.3.6.1.2.1.1.3.0"), Ok(Value::TimeTicks(4200)))]
+    );
+    assert_eq!(
+        response.columns[0].vars,
+        vec![
+            (
+                oid("1.3.6.1.2.1.2.2.1.2.1"),
+                Ok(Value::OctetStr(b"lo".to_vec()))
+            ),
+            (
+                oid("1.3.6.1.2.1.2.2.1.2.2"),
+                Ok(Value::OctetStr(b"eth0".to_vec()))
+            ),
+        ]
+    );
+    assert_eq!(
+        response.columns[1].vars,
+        vec![
+            (oid("1.3.6.1.2.1.2.2.1.10.1"), Ok(Value::Counter(100))),
+            (oid("1.3.6.1.2.1.2.2.1.10.2"), Ok(Value::Counter(200))),
+        ]
+    );
+    assert!(response.is_complete());
+
+    let pdu = session.get_bulk(&[], &columns, 3).unwrap();
+    assert_eq!(pdu.variables().count(), 6);
+}
+
+#[tokio::test]
+async fn v3_auth_priv() {
+    let agent = agent();
+    let mut session = open(&agent, &v3(), None);
+
+    let var = session
+        .get_async(&oid("1.3.6.1.2.1.1.1.0"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(var.get_value(), Ok(Value::OctetStr(b"mock".to_vec())));
+    assert_eq!(session.engine_info().unwrap().engine_id, *agent.engine_id());
+}
+
+#[test]
+fn v3_wrong_password() {
+    let agent = agent();
+    let mut auth = v3();
+    if let Auth::V3(V3Auth {
+        level: V3Level::AuthPriv { auth, .. },
+        ..
+    }) = &mut auth
+    {
+        auth.key = V3Key::Password(SecretString::from("wrongpassword"));
+    }
+    let mut session = open(&agent, &auth, None);
+    assert!(session.get(&oid("1.3.6.1.2.1.1.1.0")).is_err());
+    assert_eq!(agent.requests(), 0);
+    assert_eq!(session.stats().reports.wrong_digests, 1);
+}
+
+/* Two users of the same agent, in sessions to the same engine id:
+ * each session signs and encrypts with its own keys. */
+#[test]
+fn v3_session_users() {
+    let admin = v3_user("admin", "adminauthpassword", "adminprivpassword");
+    let agent = MockAgentBuilder::new()
+        .add_var(oid("1.3.6.1.2.1.1.5.0"), Value::OctetStr(b"host".to_vec()))
+        .add_user("user", v3_level())
+        .add_user("admin", admin.clone())
+        .start()
+        .unwrap();
+    let sys_name = oid("1.3.6.1.2.1.1.5.0");
+    let mut sessions = [v3_level(), admin].map(|level| open(&agent, &v3_auth(level), None));
+    for _ in 0..2 {
+        for session in &mut sessions {
+            let var = session.get(&sys_name).unwrap().unwrap();
+            assert_eq!(var.get_value(), Ok(Value::OctetStr(b"host".to_vec())));
+        }
+    }
+    for session in &sessions {
+        assert_eq!(session.engine_info().unwrap().engine_id, *agent.engine_id());
+        assert_eq!(session.stats().reports.wrong_digests, 0);
+    }
+    assert_eq!(agent.requests(), 4);
+
+    /* The name of one user with the keys of the other. */
+    let crossed = v3_user("admin", "authpassword", "privpassword");
+    let mut session = open(&agent, &v3_auth(crossed), None);
+    assert!(session.get(&sys_name).is_err());
+    assert_eq!(session.stats().reports.wrong_digests, 1);
+}
+
+/* Two agents with the same engine id and a user of the same name,
+ * but with different keys. */
+#[test]
+fn v3_same_engine_id() {
+    let names = ["first", "second"];
+    let levels = names.map(|name| {
+        v3_user(
+            "user",
+            &format!("{}authpassword", name),
+            &format!("{}privpassword", name),
+        )
+    });
+    let agents = [0, 1].map(|i| {
+        MockAgentBuilder::new()
+            .set_engine_id(EngineId::from_slice(b"\x80\x00\x1f\x88\x04mock"))
+            .add_var(
+                oid("1.3.6.1.2.1.1.5.0"),
+                Value::OctetStr(names[i].as_bytes().to_vec()),
+            )
+            .add_user("user", levels[i].clone())
+            .start()
+            .unwrap()
+    });
+    let sys_name = oid("1.3.6.1.2.1.1.5.0");
+    let mut sessions = [0, 1].map(|i| open(&agents[i], &v3_auth(levels[i].clone()), None));
+    for _ in 0..2 {
+        for (session, name) in sessions.iter_mut().zip(names) {
+            let var = session.get(&sys_name).unwrap().unwrap();
+            assert_eq!(
+                var.get_value(),
+                Ok(Value::OctetStr(name.as_bytes().to_vec()))
+            );
+        }
+    }
+    assert_eq!(agents[0].engine_id(), agents[1].engine_id());
+
+    /* The keys of one agent are not accepted by the other. */
+    let mut session = open(&agents[1], &v3_auth(levels[0].clone()), None);
+    assert!(session.get(&sys_name).is_err());
+    assert_eq!(session.stats().reports.wrong_digests, 1);
+}
+
+#[tokio::test]
+async fn engine_discovery() {
+    let agent = agent();
+    let builder = || {
+        snmp()
+            .session()
+            .set_peer(agent.peer().as_bytes())
+            .unwrap()
+            .set_auth(&v3())
+            .unwrap()
+            .set_retries(0)
+            .set_timeout(Duration::from_millis(500))
+            .set_async_probe(true)
+    };
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+
+    /* The engine is probed once, on request. */
+    let mut session = builder().open_single().unwrap();
+    assert_eq!(session.stats().requests, 0);
+    let engine = session.discover_engine_async().await.unwrap();
+    assert_eq!(engine.engine_id, *agent.engine_id());
+    assert_eq!(engine.engine_boots, 1);
+    assert_eq!(
+        session.discover_engine_async().await.unwrap().engine_id,
+        engine.engine_id
+    );
+    assert_eq!(session.stats().requests, 1);
+    assert!(session.get_async(&sys_descr).await.unwrap().is_some());
+
+    /* Sessions seeded with the engine send no probe. */
+    let mut seeded = builder()
+        .set_engine(&engine)
+        .unwrap()
+        .open_single()
+        .unwrap();
+    assert_eq!(seeded.stats().requests, 0);
+    assert!(seeded.get_async(&sys_descr).await.unwrap().is_some());
+    assert_eq!(seeded.stats().requests, 1);
+    assert_eq!(agent.requests(), 2);
+
+    let empty = EngineInfo {
+        engine_id: EngineId::from_slice(&[]),
+        ..engine
+    };
+    assert!(builder().set_engine(&empty).is_err());
+
+    let mut session = open(&agent, &v2c(), None);
+    assert!(session.discover_engine_async().await.is_err());
+}
+
+#[tokio::test]
+async fn usm_verify() {
+    let agent = agent();
+    let mut session = open(&agent, &v3(), None);
+    let mut usm = UsmAdmin::new(snmp(), &mut session).await.unwrap();
+
+    usm.verify(&v3_level()).await.unwrap();
+    let wrong = v3_user("user", "wrongauthpassword", "wrongprivpassword");
+    assert!(usm.verify(&wrong).await.is_err());
+
+    /* A response with an error status does not confirm the keys. */
+    agent.inject(Fault::TooBig, 1);
+    assert!(usm.verify(&v3_level()).await.is_err());
+}
+
+#[tokio::test]
+async fn usm_admin() {
+    let admin = v3_user("admin", "adminauthpassword", "adminprivpassword");
+    let template = v3_user("template", "templateauthpassword", "templateprivpassword");
+    let agent = MockAgentBuilder::new()
+        .add_var(oid("1.3.6.1.2.1.1.3.0"), Value::TimeTicks(4200))
+        .add_user("admin", admin.clone())
+        .add_user("template", template.clone())
+        .add_user("user", v3_level())
+        .start()
+        .unwrap();
+    let mut session = open(&agent, &v3_auth(admin.clone()), None);
+    let mut usm = UsmAdmin::new(snmp(), &mut session).await.unwrap();
+
+    /* The keys of another user. */
+    let user = v3_user("user", "newauthpassword", "newprivpassword");
+    usm.change_keys(&v3_level(), &user).await.unwrap();
+    usm.verify(&user).await.unwrap();
+    assert!(usm.verify(&v3_level()).await.is_err());
+    assert!(usm.change_keys(&v3_level(), &template).await.is_err());
+
+    /* The old keys are restored when the verification fails. */
+    let other = v3_user("user", "otherauthpassword", "otherprivpassword");
+    agent.inject(Fault::Delay(Duration::ZERO), 1);
+    agent.inject(Fault::TooBig, 1);
+    assert!(usm.change_keys(&user, &other).await.is_err());
+    usm.verify(&user).await.unwrap();
+    assert!(usm.verify(&other).await.is_err());
+
+    /* Users are cloned with their own keys. */
+    let bob = v3_user("bob", "bobauthpassword", "bobprivpassword");
+    usm.clone_user(&template, &bob).await.unwrap();
+    usm.verify(&bob).await.unwrap();
+    usm.verify(&template).await.unwrap();
+    assert!(usm.clone_user(&template, &bob).await.is_err());
+
+    /* A user that fails verification is removed again. */
+    let wrong = v3_user("template", "wrongauthpassword", "wrongprivpassword");
+    let carol = v3_user("carol", "carolauthpassword", "carolprivpassword");
+    assert!(usm.clone_user(&wrong, &carol).await.is_err());
+    usm.clone_user(&template, &carol).await.unwrap();
+
+    usm.delete_user("bob").await.unwrap();
+    assert!(usm.verify(&bob).await.is_err());
+    usm.verify(&carol).await.unwrap();
+
+    /* The session's own keys; the session keeps the old keys. */
+    let new_admin = v3_user("admin", "newadminauthpassword", "newadminprivpassword");
+    usm.change_keys(&admin, &new_admin).await.unwrap();
+    assert!(usm.verify(&admin).await.is_err());
+}
+
+#[tokio::test]
+async fn faults() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+
+    agent.inject(Fault::Drop, 1);
+    assert!(session.get_async(&sys_descr).await.is_err());
+
+    agent.inject(Fault::Delay(Duration::from_millis(100)), 1);
+    assert!(session.get_async(&sys_descr).await.unwrap().is_some());
+
+    agent.inject(Fault::TooBig, 1);
+    let response = session.get_many_async(&[sys_descr.clone()]).await.unwrap();
+    assert_eq!(response.error_status(), 1);
+
+    agent.inject(Fault::NonIncreasing, 1);
+    let var = session.get_next(&sys_descr).unwrap().unwrap();
+    assert_eq!(var.get_name(), sys_descr);
+
+    assert_eq!(agent.requests(), 4);
+}
+
+#[tokio::test]
+async fn stats() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+
+    session.get(&sys_descr).unwrap();
+    session.get_async(&sys_descr).await.unwrap();
+    agent.inject(Fault::Drop, 1);
+    assert!(session.get_async(&sys_descr).await.is_err());
+
+    let stats = session.stats();
+    assert_eq!(stats.requests, 3);
+    assert_eq!(stats.responses, 2);
+    assert_eq!(stats.timeouts, 1);
+    assert_eq!(stats.retries, 0);
+    assert_eq!(stats.reports.total(), 0);
+    assert_eq!(stats.latency.count, 2);
+    assert!(stats.bytes_out > 0 && stats.bytes_in > 0);
+}
+
+#[tokio::test]
+async fn retry_policy() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+    let policy = RetryPolicy::exponential(
+        Duration::from_millis(100),
+        2.0,
+        Duration::from_millis(300),
+        2,
+    );
+    assert_eq!(policy.attempt_timeout(0), Duration::from_millis(100));
+    assert_eq!(policy.attempt_timeout(1), Duration::from_millis(200));
+    assert_eq!(policy.attempt_timeout(2), Duration::from_millis(300));
+    let huge = RetryPolicy::exponential(Duration::from_secs(1), 10.0, Duration::MAX, 100);
+    assert_eq!(huge.attempt_timeout(99), Duration::MAX);
+    let huge = RetryPolicy::fixed(Duration::MAX, 0).with_jitter(0.5);
+    assert!(huge.attempt_timeout(0) >= Duration::from_secs(u64::MAX / 2));
+
+    /* Per-request policy: answered on the third attempt. */
+    agent.inject(Fault::Drop, 2);
+    let start = Instant::now();
+    let pdu = Pdu::get().add_oid(&sys_descr);
+    assert!(session.async_response_with(pdu, &policy).await.is_ok());
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(session.stats().retries, 2);
+
+    /* Session policy: all attempts dropped. */
+    session.set_retry_policy(Some(policy));
+    agent.inject(Fault::Drop, 3);
+    assert!(matches!(
+        session.get_async(&sys_descr).await,
+        Err(Error::Timeout)
+    ));
+    let stats = session.stats();
+    assert_eq!((stats.requests, stats.retries, stats.timeouts), (2, 4, 1));
+    assert_eq!(agent.requests(), 6);
+}
+
+#[tokio::test]
+async fn retry_policy_cancelled() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+    let policy = RetryPolicy::fixed(Duration::from_millis(50), 5);
+
+    agent.inject(Fault::Drop, 10);
+    let pdu = Pdu::get().add_oid(&sys_descr);
+    let request = session.async_response_with(pdu, &policy);
+    assert!(tokio::time::timeout(Duration::from_millis(120), request)
+        .await
+        .is_err());
+    agent.clear_faults();
+
+    /* The session timeout (500ms) is restored. */
+    agent.inject(Fault::Drop, 1);
+    let start = Instant::now();
+    assert!(session.get(&sys_descr).is_err());
+    assert!(start.elapsed() >= Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn validation() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+    let oids = [
+        oid("1.3.6.1.2.1.1.1.0"),
+        oid("1.3.6.1.2.1.1.1.1"),
+        oid("1.3.6.1.2.1.1.99.0"),
+        oid("1.3.6.1.2.1.2.2.1.10.1"),
+    ];
+
+    let vars = session.get_values_async(&oids).await.unwrap();
+    assert_eq!(
+        vars,
+        vec![
+            (oids[0].clone(), Ok(Value::OctetStr(b"mock".to_vec()))),
+            (oids[1].clone(), Err(ErrType::NoSuchInstance)),
+            (oids[2].clone(), Err(ErrType::NoSuchObject)),
+            (oids[3].clone(), Ok(Value::Counter(100))),
+        ]
+    );
+
+    agent.inject(Fault::Reorder, 1);
+    assert!(matches!(
+        session.get_values(&oids),
+        Err(Error::VarBindMismatch(..))
+    ));
+
+    agent.inject(Fault::DropVarBind, 1);
+    assert!(matches!(
+        session.get_values_async(&oids).await,
+        Err(Error::VarBindCount(4, 3))
+    ));
+
+    agent.inject(Fault::DropVarBind, 1);
+    assert!(matches!(
+        session.get(&oids[0]),
+        Err(Error::VarBindCount(1, 0))
+    ));
+}
+
+#[tokio::test]
+async fn get_next() {
+    let agent = agent();
+    let oids = [
+        oid("1.3.6.1.2.1.1.1.0"),
+        oid("1.3.6.1.2.1.1.4"),
+        oid("1.3.6.1.2.1.2.2.1.2.2"),
+        oid("1.3.6.1.2.1.2.2.1.10.2"),
+    ];
+    let expected = vec![
+        (oid("1.3.6.1.2.1.1.3.0"), Ok(Value::TimeTicks(4200))),
+        (
+            oid("1.3.6.1.2.1.1.5.0"),
+            Ok(Value::OctetStr(b"host".to_vec())),
+        ),
+        (oid("1.3.6.1.2.1.2.2.1.10.1"), Ok(Value::Counter(100))),
+        (oids[3].clone(), Err(ErrType::EndOfMibView)),
+    ];
+
+    for version in [None, Some(Version::V1)] {
+        let mut session = open(&agent, &v2c(), version);
+
+        /* The successor, not the variable itself. */
+        let var = session.get_next_async(&oids[0]).await.unwrap().unwrap();
+        assert_eq!(var.get_name(), oid("1.3.6.1.2.1.1.3.0"));
+        assert_eq!(var.get_value(), Ok(Value::TimeTicks(4200)));
+
+        assert_eq!(session.get_next_many(&oids).unwrap(), expected);
+        assert_eq!(session.get_next_many_async(&oids).await.unwrap(), expected);
+    }
+
+    let mut session = open(&agent, &v2c(), None);
+    agent.inject(Fault::Reorder, 1);
+    assert!(matches!(
+        session.get_next_many_async(&oids).await,
+        Err(Error::OidsNotIncreasing)
+    ));
+    agent.inject(Fault::DropVarBind, 1);
+    assert!(matches!(
+        session.get_next_many(&oids),
+        Err(Error::VarBindCount(4, 3))
+    ));
+}
+
+#[tokio::test]
+async fn bulk_columns() {
+    let agent = agent();
+    let mut session = open(&agent, &v2c(), None);
+    let sys_uptime = oid("1.3.6.1.2.1.1.3");
+    let columns = [oid("1.3.6.1.2.1.2.2.1.2"), oid("1.3.6.1.2.1.2.2.1.10")];
+
+    let pdu = session
+        .get_bulk_async(&[sys_uptime.clone()], &columns, 1)
+        .await
+        .unwrap();
+    let response = BulkResponse::split(&[sys_uptime], &columns, &pdu).unwrap();
+    assert_eq!(
+        response.non_repeaters,
+        vec![(oid("1.3.6.1.2.1.1.3.0"), Ok(Value::TimeTicks(4200)))]
+    );
+
+    let mut vars = BTreeMap::new();
+    let mut response = response;
+    let mut rounds = 1;
+    loop {
+        for column in &response.columns {
+            vars.entry(column.root.clone())
+                .or_insert_with(Vec::new)
+                .extend(column.vars.iter().cloned());
+        }
+        if response.is_complete() {
+            break;
+        }
+        let repeaters = response.next_repeaters();
+        let walks: Vec<_> = repeaters.iter().map(|(_, from)| from.clone()).collect();
+        let pdu = session.get_bulk_async(&[], &walks, 1).await.unwrap();
+        response = BulkResponse::split_from(&[], &repeaters, &pdu).unwrap();
+        rounds += 1;
+    }
+
+    assert_eq!(rounds, 3);
+    assert_eq!(
+        vars[&columns[0]],
+        vec![
+            (
+                oid("1.3.6.1.2.1.2.2.1.2.1"),
+                Ok(Value::OctetStr(b"lo".to_vec()))
+            ),
+            (
+                oid("1.3.6.1.2.1.2.2.1.2.2"),
+                Ok(Value::OctetStr(b"eth0".to_vec()))
+            ),
+        ]
+    );
+    assert_eq!(
+        vars[&columns[1]],
+        vec![
+            (oid("1.3.6.1.2.1.2.2.1.10.1"), Ok(Value::Counter(100))),
+            (oid("1.3.6.1.2.1.2.2.1.10.2"), Ok(Value::Counter(200))),
+        ]
+    );
+
+    /* A response without rows would make the walk loop forever. */
+    let pdu = session.get_bulk_async(&[], &columns, 0).await.unwrap();
+    assert!(matches!(
+        BulkResponse::split(&[], &columns, &pdu),
+        Err(Error::NoRepetitions)
+    ));
+}
+
+#[tokio::test]
+async fn session_pool() {
+    let agent = agent();
+    let pool = SessionPool::new(
+        snmp().clone(),
+        PoolConfig {
+            max_sessions: 2,
+            idle_timeout: Duration::from_millis(200),
+            max_errors: 1,
+            retry: RetryPolicy::fixed(Duration::from_millis(500), 0),
+        },
+    );
+    let peer = agent.peer();
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+
+    /* Returned sessions are reused. */
+    let mut lease = pool.lease(&peer, &v2c()).await.unwrap();
+    lease.get_async(&sys_descr).await.unwrap();
+    drop(lease);
+    assert_eq!(pool.idle_sessions(), 1);
+    let mut lease = pool.lease(&peer, &v2c()).await.unwrap();
+    assert_eq!(lease.stats().requests, 1);
+
+    /* Sessions with errors are re-opened. */
+    agent.inject(Fault::Drop, 1);
+    assert!(lease.get_async(&sys_descr).await.is_err());
+    drop(lease);
+    assert_eq!(pool.idle_sessions(), 0);
+    let mut lease = pool.lease(&peer, &v2c()).await.unwrap();
+    assert_eq!(lease.stats().requests, 0);
+
+    /* Sessions with an interrupted request are not reused. */
+    agent.inject(Fault::Drop, 1);
+    let request = lease.get_async(&sys_descr);
+    assert!(tokio::time::timeout(Duration::from_millis(100), request)
+        .await
+        .is_err());
+    drop(lease);
+    assert_eq!(pool.idle_sessions(), 0);
+
+    /* At most max_sessions sessions per target. */
+    let first = pool.lease(&peer, &v2c()).await.unwrap();
+    let second = pool.lease(&peer, &v2c()).await.unwrap();
+    let wait = tokio::time::timeout(Duration::from_millis(100), pool.lease(&peer, &v2c()));
+    assert!(wait.await.is_err());
+
+    /* Engine discovery is done asynchronously, on first use. */
+    let mut other = pool.lease(&peer, &v3()).await.unwrap();
+    assert_eq!(other.stats().requests, 0);
+    other.get_async(&sys_descr).await.unwrap();
+    drop((first, second, other));
+    assert_eq!(pool.idle_sessions(), 3);
+
+    /* Idle sessions are closed. */
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    pool.evict_idle();
+    assert_eq!(pool.idle_sessions(), 0);
+}
+
+#[tokio::test]
+async fn replay_dump() {
+    let path = std::env::temp_dir().join(format!("netsnmp-rs-{}.snmprec", std::process::id()));
+    fs::write(
+        &path,
+        "1.3.6.1.2.1.1.1.0|4|replayed\n\
+         1.3.6.1.2.1.2.2.1.2.1|4|lo\n\
+         1.3.6.1.2.1.2.2.1.2.2|4|eth0\n",
+    )
+    .unwrap();
+    let agent = MockAgentBuilder::new()
+        .load_dump(&path)
+        .unwrap()
+        .start()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    let mut session = open(&agent, &v2c(), None);
+
+    let column = oid("1.3.6.1.2.1.2.2.1.2");
+    let pdu = session
+        .get_bulk_async(&[], &[column.clone()], 10)
+        .await
+        .unwrap();
+    let names: Vec<_> = pdu
+        .variables()
+        .map(|var| var.get_name())
+        .take_while(|name| column.contains(name))
+        .collect();
+    assert_eq!(names, vec![column.join([1]), column.join([2])]);
+}
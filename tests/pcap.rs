@@ -0,0 +1,192 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+//! Decoding of the captures in tests/data. The SNMPv3 messages in
+//! snmpv3.pcapng are sent by user "user" (SHA, "authpassword"; AES,
+//! "privpassword") to engine 0x80001f880470636170.
+
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use netsnmp::{
+    BerPdu, CapturedMessage, Message, Msg, PcapDecoder, ScopedPduData, SecretString, V3Auth,
+    V3AuthParams, V3AuthProtocol, V3Key, V3Level, V3PrivParams, V3PrivProtocol, Value, Version,
+};
+
+mod common;
+
+use common::{oid, snmp};
+
+fn capture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name)
+}
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+fn pdu(message: &CapturedMessage) -> &BerPdu {
+    message.message.as_ref().unwrap().pdu().unwrap()
+}
+
+fn credentials(priv_password: &str) -> V3Auth {
+    V3Auth {
+        level: V3Level::AuthPriv {
+            auth: V3AuthParams {
+                protocol: V3AuthProtocol::SHA,
+                user: String::from("user"),
+                key: V3Key::Password(SecretString::from("authpassword")),
+            },
+            privacy: V3PrivParams {
+                protocol: V3PrivProtocol::AES,
+                key: V3Key::Password(SecretString::from(priv_password)),
+            },
+        },
+        context: None,
+        context_engine: None,
+        security_engine: None,
+        destination_engine: None,
+    }
+}
+
+#[test]
+fn ethernet() {
+    let messages = PcapDecoder::new()
+        .decode_file(capture("snmp_ethernet.pcap"))
+        .unwrap();
+
+    /* Packet 3 is not on an SNMP port. */
+    let packets: Vec<_> = messages.iter().map(|message| message.packet).collect();
+    assert_eq!(packets, vec![1, 2, 4]);
+
+    let request = &messages[0];
+    assert_eq!(request.timestamp, Duration::new(1700000000, 1000));
+    assert_eq!(request.source, addr("192.0.2.1:40000"));
+    assert_eq!(request.destination, addr("192.0.2.2:161"));
+    assert!(!request.decrypted);
+    match request.message.as_ref().unwrap() {
+        Message::Community {
+            version,
+            community,
+            pdu,
+        } => {
+            assert_eq!(*version, Version::V2c);
+            assert_eq!(community, b"public");
+            assert_eq!(pdu.command, Msg::Get);
+            assert_eq!(pdu.request_id, 1);
+            assert_eq!(pdu.varbinds[0].oid, oid("1.3.6.1.2.1.1.1.0"));
+        }
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    /* VLAN tagged. */
+    let response = &messages[1];
+    assert_eq!(response.timestamp, Duration::new(1700000000, 2_500_000));
+    assert_eq!(response.source, addr("192.0.2.2:161"));
+    assert_eq!(pdu(response).command, Msg::Response);
+    assert_eq!(
+        pdu(response).varbinds[0].value,
+        Ok(Value::OctetStr(b"router".to_vec()))
+    );
+
+    /* IPv6. */
+    let next = &messages[2];
+    assert_eq!(next.source, addr("[2001:db8::1]:40002"));
+    assert_eq!(next.destination, addr("[2001:db8::2]:161"));
+    assert_eq!(pdu(next).command, Msg::GetNext);
+    assert_eq!(pdu(next).varbinds[0].oid, oid("1.3.6.1.2.1.2.2.1.2"));
+}
+
+#[test]
+fn ports() {
+    let messages = PcapDecoder::new()
+        .set_ports(&[53])
+        .decode_file(capture("snmp_ethernet.pcap"))
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].packet, 3);
+    assert!(messages[0].message.is_err());
+}
+
+#[test]
+fn linux_sll() {
+    /* Big endian, with nanosecond timestamps. */
+    let messages = PcapDecoder::new()
+        .decode_file(capture("snmp_sll.pcap"))
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    let trap = &messages[0];
+    assert_eq!(trap.timestamp, Duration::new(1700000003, 500_000_000));
+    assert_eq!(trap.destination, addr("192.0.2.1:162"));
+    assert_eq!(pdu(trap).command, Msg::Trap2);
+    assert_eq!(pdu(trap).varbinds[0].value, Ok(Value::TimeTicks(4200)));
+    assert_eq!(
+        pdu(trap).varbinds[1].value,
+        Ok(Value::Oid(oid("1.3.6.1.6.3.1.1.5.3")))
+    );
+}
+
+#[test]
+fn v3_decryption() {
+    snmp();
+    let data = fs::read(capture("snmpv3.pcapng")).unwrap();
+
+    /* Without credentials, the scoped pdus stay encrypted. */
+    let messages = PcapDecoder::new().decode_bytes(&data).unwrap();
+    assert_eq!(messages.len(), 2);
+    for message in &messages {
+        assert!(!message.decrypted);
+        assert!(matches!(
+            message.message,
+            Ok(Message::V3 {
+                data: ScopedPduData::Encrypted(_),
+                ..
+            })
+        ));
+    }
+
+    let messages = PcapDecoder::new()
+        .add_credentials(credentials("privpassword"))
+        .decode_bytes(&data)
+        .unwrap();
+    let (request, response) = (&messages[0], &messages[1]);
+    assert_eq!(request.timestamp, Duration::new(1700000004, 1000));
+    assert!(request.decrypted && response.decrypted);
+    assert_eq!(pdu(request).command, Msg::Get);
+    assert_eq!(pdu(request).request_id, 10);
+    assert_eq!(pdu(request).varbinds[0].oid, oid("1.3.6.1.2.1.1.1.0"));
+    assert_eq!(pdu(response).command, Msg::Response);
+    assert_eq!(
+        pdu(response).varbinds[0].value,
+        Ok(Value::OctetStr(b"router".to_vec()))
+    );
+
+    let messages = PcapDecoder::new()
+        .add_credentials(credentials("wrongpassword"))
+        .decode_bytes(&data)
+        .unwrap();
+    assert!(messages.iter().all(|message| message.message.is_err()));
+}
+
+#[test]
+fn invalid() {
+    let data = fs::read(capture("snmp_ethernet.pcap")).unwrap();
+    assert!(PcapDecoder::new().decode_bytes(&data[..40]).is_err());
+    assert!(PcapDecoder::new().decode_bytes(b"abc").is_err());
+    assert!(PcapDecoder::new().decode_bytes(&[0; 64]).is_err());
+}
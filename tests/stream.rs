@@ -0,0 +1,83 @@
+/******************************************************************************
+ * Copyright 2025 ContinuousC                                                 *
+ *                                                                            *
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may *
+ * not use this file except in compliance with the License. You may  obtain a *
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          *
+ *                                                                            *
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software *
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT *
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the *
+ * License for the  specific language  governing permissions  and limitations *
+ * under the License.                                                         *
+ ******************************************************************************/
+
+//! Requests over tcp, against a minimal agent that sends each response
+//! in two chunks and closes the connection after it.
+
+use std::io::Write;
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use netsnmp::{Auth, SecretString, SingleSession, V2cAuth, Value};
+
+mod common;
+
+use common::{oid, read_message, respond, snmp};
+
+/* Serve one request on each of `connections` connections. */
+fn agent(connections: usize) -> (String, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let peer = format!("tcp:{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        for stream in listener.incoming().take(connections) {
+            let mut stream = stream.unwrap();
+            let response = respond(&read_message(&mut stream));
+            let (head, tail) = response.split_at(response.len() / 2);
+            stream.write_all(head).unwrap();
+            stream.flush().unwrap();
+            thread::sleep(Duration::from_millis(50));
+            stream.write_all(tail).unwrap();
+        }
+    });
+    (peer, handle)
+}
+
+fn open(peer: &str) -> SingleSession {
+    snmp()
+        .session()
+        .set_peer(peer.as_bytes())
+        .unwrap()
+        .set_auth(&Auth::V2c(V2cAuth {
+            community: SecretString::from("public"),
+        }))
+        .unwrap()
+        .set_retries(0)
+        .set_timeout(Duration::from_secs(2))
+        .open_single()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn reconnect_and_partial_reads() {
+    let (peer, agent) = agent(3);
+    let mut session = open(&peer);
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+    let expected = Ok(Value::OctetStr(b"stream".to_vec()));
+
+    /* The response is only complete after the second chunk. */
+    let var = session.get_async(&sys_descr).await.unwrap().unwrap();
+    assert_eq!(var.get_value(), expected);
+
+    /* The agent has closed the connection: the request is resent
+     * on a new one. */
+    let var = session.get_async(&sys_descr).await.unwrap().unwrap();
+    assert_eq!(var.get_value(), expected);
+
+    session.reconnect().unwrap();
+    let var = session.get_async(&sys_descr).await.unwrap().unwrap();
+    assert_eq!(var.get_value(), expected);
+
+    agent.join().unwrap();
+}
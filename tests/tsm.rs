@@ -0,0 +1,147 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+//! The session test requires a local snmpd with a (d)tls listener,
+//! eg.:
+//!
+//!   NETSNMP_TSM_PEER=dtlsudp:localhost:10161 \
+//!   NETSNMP_TSM_LOCAL_CERT=<fingerprint> \
+//!   NETSNMP_TSM_PEER_CERT=<fingerprint> \
+//!   NETSNMP_TSM_SECURITY_NAME=<certSecName of the local cert> \
+//!   cargo test --test tsm -- --ignored
+
+use std::env;
+
+use netsnmp::{Auth, SecurityLevel, SecurityModel, TsmAuth, TsmTransport};
+
+mod common;
+
+use common::{oid, snmp};
+
+fn auth(transport: TsmTransport) -> Auth {
+    Auth::Tsm(TsmAuth {
+        transport,
+        local_cert: String::from("local.crt"),
+        peer_cert: Some(String::from("peer.crt")),
+        trust_cert: None,
+        peer_hostname: Some(String::from("localhost")),
+        security_name: String::from("client"),
+        context: None,
+    })
+}
+
+#[test]
+fn tsm_builder() {
+    let snmp = snmp();
+    let builder = snmp
+        .session()
+        .set_peer(b"localhost:10161")
+        .unwrap()
+        .set_auth(&auth(TsmTransport::Dtls))
+        .unwrap();
+    assert_eq!(builder.security_model(), Some(SecurityModel::Tsm));
+    assert_eq!(builder.security_level(), Some(SecurityLevel::AuthPriv));
+    assert_eq!(builder.security_name().as_deref(), Some("client"));
+    assert_eq!(
+        builder.peername().as_deref(),
+        Some("dtlsudp:localhost:10161")
+    );
+    let config = builder.transport_config();
+    let config: Vec<_> = config
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    assert_eq!(
+        config,
+        vec![
+            ("localCert", "local.crt"),
+            ("peerCert", "peer.crt"),
+            ("their_hostname", "localhost"),
+        ]
+    );
+
+    /* The domain is also added when the peer is set last, unless
+     * the peer has one (in any case). */
+    let builder = snmp
+        .session()
+        .set_auth(&auth(TsmTransport::Tls))
+        .unwrap()
+        .set_peer(b"localhost:10161")
+        .unwrap();
+    assert_eq!(builder.peername().as_deref(), Some("tls:localhost:10161"));
+    for peer in ["DTLSUDP:localhost:10161", "tlstcp:localhost:10161"] {
+        let builder = snmp
+            .session()
+            .set_auth(&auth(TsmTransport::Tls))
+            .unwrap()
+            .set_peer(peer.as_bytes())
+            .unwrap();
+        assert_eq!(builder.peername().as_deref(), Some(peer));
+    }
+}
+
+/* The transport configuration of a builder that is never opened, or
+ * fails to open, is freed with the builder. */
+#[test]
+fn tsm_builder_without_session() {
+    let snmp = snmp();
+    let builder = snmp.session().set_auth(&auth(TsmTransport::Tls)).unwrap();
+    drop(builder);
+
+    let result = snmp
+        .session()
+        .set_peer(b"tls:127.0.0.1:1")
+        .unwrap()
+        .set_auth(&auth(TsmTransport::Tls))
+        .unwrap()
+        .open_single();
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires a local snmpd with a (d)tls listener"]
+async fn tsm_get_sysdescr() {
+    let peer = env::var("NETSNMP_TSM_PEER").expect("NETSNMP_TSM_PEER not set");
+    let auth = Auth::Tsm(TsmAuth {
+        transport: match peer.starts_with("tls") {
+            true => TsmTransport::Tls,
+            false => TsmTransport::Dtls,
+        },
+        local_cert: env::var("NETSNMP_TSM_LOCAL_CERT").expect("NETSNMP_TSM_LOCAL_CERT not set"),
+        peer_cert: env::var("NETSNMP_TSM_PEER_CERT").ok(),
+        trust_cert: env::var("NETSNMP_TSM_TRUST_CERT").ok(),
+        peer_hostname: None,
+        security_name: env::var("NETSNMP_TSM_SECURITY_NAME")
+            .expect("NETSNMP_TSM_SECURITY_NAME not set"),
+        context: None,
+    });
+
+    let snmp = snmp();
+    let mut session = snmp
+        .session()
+        .set_peer(peer.as_bytes())
+        .unwrap()
+        .set_auth(&auth)
+        .unwrap()
+        .open_single()
+        .unwrap();
+
+    let oid = oid("1.3.6.1.2.1.1.1.0");
+    let var = session
+        .get_async(&oid)
+        .await
+        .unwrap()
+        .expect("no variable in response");
+    assert_eq!(var.get_name(), oid);
+    assert!(var.get_value().is_ok());
+}
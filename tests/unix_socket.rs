@@ -0,0 +1,201 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+//! The snmpd tests require snmpd (net-snmp) in $PATH; run with:
+//!
+//!   cargo test --test unix_socket -- --ignored
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use netsnmp::{Auth, SecretString, SyncQuery, V2cAuth, Value};
+
+mod common;
+
+use common::{oid, read_message, respond, snmp};
+
+/// A local snmpd listening on a unix domain socket.
+struct Snmpd {
+    child: Child,
+    dir: PathBuf,
+    socket: PathBuf,
+}
+
+impl Snmpd {
+    fn start(name: &str) -> Self {
+        let dir = socket_dir(name);
+        fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("snmpd.sock");
+        let config = dir.join("snmpd.conf");
+        fs::write(
+            &config,
+            "com2secunix local default public\n\
+             group local v2c local\n\
+             view all included .1\n\
+             access local \"\" any noauth exact all none none\n",
+        )
+        .unwrap();
+
+        let child = Command::new("snmpd")
+            .arg("-f")
+            .arg("-Lo")
+            .arg("-C")
+            .arg("-c")
+            .arg(&config)
+            .arg(format!("unix:{}", socket.display()))
+            .env("SNMP_PERSISTENT_DIR", &dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start snmpd");
+
+        for _ in 0..50 {
+            if socket.exists() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(socket.exists(), "snmpd did not create {}", socket.display());
+
+        Self { child, dir, socket }
+    }
+}
+
+impl Drop for Snmpd {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn auth() -> Auth {
+    Auth::V2c(V2cAuth {
+        community: SecretString::from("public"),
+    })
+}
+
+fn socket_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("netsnmp-rs-{}-{}", name, std::process::id()))
+}
+
+/* A minimal agent that serves three requests on one connection. */
+#[tokio::test]
+async fn unix_socket_responder() {
+    let dir = socket_dir("responder");
+    fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("agent.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let agent = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        for _ in 0..3 {
+            let response = respond(&read_message(&mut stream));
+            stream.write_all(&response).unwrap();
+        }
+    });
+
+    let mut session = snmp()
+        .session()
+        .set_unix_socket(&socket)
+        .unwrap()
+        .set_auth(&auth())
+        .unwrap()
+        .set_retries(0)
+        .set_timeout(Duration::from_secs(2))
+        .open_single()
+        .unwrap();
+    assert!(session.transport().is_stream());
+
+    let sys_descr = oid("1.3.6.1.2.1.1.1.0");
+    for _ in 0..3 {
+        let var = session.get_async(&sys_descr).await.unwrap().unwrap();
+        assert_eq!(var.get_name(), sys_descr);
+        assert_eq!(var.get_value(), Ok(Value::OctetStr(b"stream".to_vec())));
+    }
+
+    agent.join().unwrap();
+    drop(session);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+#[ignore = "requires snmpd"]
+async fn unix_socket_async() {
+    let agent = Snmpd::start("async");
+    let snmp = snmp();
+    let mut session = snmp
+        .session()
+        .set_unix_socket(&agent.socket)
+        .unwrap()
+        .set_auth(&auth())
+        .unwrap()
+        .open_single()
+        .unwrap();
+
+    assert!(session.transport().is_stream());
+
+    /* Several requests over the same connection. */
+    let oid = oid("1.3.6.1.2.1.1.1.0");
+    for _ in 0..3 {
+        let var = session.get_async(&oid).await.unwrap().unwrap();
+        assert_eq!(var.get_name(), oid);
+        assert!(matches!(var.get_value(), Ok(Value::OctetStr(_))));
+    }
+
+    let walk = oid("1.3.6.1.2.1.1");
+    let pdu = session
+        .get_bulk_async(&[], &[walk.clone()], 5)
+        .await
+        .unwrap();
+    assert!(pdu.variables().all(|var| walk.contains(&var.get_name())));
+}
+
+#[test]
+#[ignore = "requires snmpd"]
+fn unix_socket_sync() {
+    let agent = Snmpd::start("sync");
+    let snmp = snmp();
+    let mut session = snmp
+        .session()
+        .set_unix_socket(&agent.socket)
+        .unwrap()
+        .set_auth(&auth())
+        .unwrap()
+        .open_single()
+        .unwrap();
+
+    let oid = oid("1.3.6.1.2.1.1.3.0");
+    let var = session.get(&oid).unwrap().unwrap();
+    assert_eq!(var.get_name(), oid);
+    assert!(matches!(var.get_value(), Ok(Value::TimeTicks(_))));
+}
+
+#[test]
+fn unix_socket_connect_error() {
+    let dir = socket_dir("missing");
+    let snmp = snmp();
+    let result = snmp
+        .session()
+        .set_unix_socket(dir.join("snmpd.sock"))
+        .unwrap()
+        .set_auth(&auth())
+        .unwrap()
+        .open_single();
+    assert!(matches!(result, Err(netsnmp::Error::Connect(_))));
+}
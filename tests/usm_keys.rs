@@ -0,0 +1,144 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+//! Password to key conversion and key localization, with the test
+//! vectors of RFC 3414 (appendix A.3).
+
+use netsnmp::{
+    decode_keychange, encode_keychange, generate_ku, generate_kul, EngineId, Key, SecretString,
+    V3AuthParams, V3AuthProtocol, V3Key, V3Level, V3PrivParams, V3PrivProtocol,
+};
+
+mod common;
+
+use common::snmp;
+
+fn key(hex: &str) -> Key {
+    hex.parse().unwrap()
+}
+
+fn engine_id() -> EngineId {
+    "0x000000000000000000000002".parse().unwrap()
+}
+
+const PASSWORD: &str = "maplesyrup";
+const MD5_KU: &str = "9faf3283884e92834ebc9847d8edd963";
+const MD5_KUL: &str = "526f5eed9fcce26f8964c2930787d82b";
+const SHA_KU: &str = "9fb5cc0381497b3793528939ff788d5d79145211";
+const SHA_KUL: &str = "6695febc9288e36282235fc7151f128497b38f3f";
+
+fn auth_priv(protocol: V3AuthProtocol, key: V3Key) -> V3Level {
+    V3Level::AuthPriv {
+        auth: V3AuthParams {
+            protocol,
+            user: String::from("user"),
+            key: key.clone(),
+        },
+        privacy: V3PrivParams {
+            protocol: V3PrivProtocol::AES,
+            key,
+        },
+    }
+}
+
+fn keys(level: &V3Level) -> (&V3Key, &V3Key) {
+    match level {
+        V3Level::AuthPriv { auth, privacy } => (&auth.key, &privacy.key),
+        _ => panic!("expected authPriv"),
+    }
+}
+
+#[test]
+fn md5_vectors() {
+    snmp();
+    let ku = generate_ku(&V3AuthProtocol::MD5, PASSWORD.as_bytes()).unwrap();
+    assert_eq!(ku, key(MD5_KU));
+    let kul = generate_kul(&V3AuthProtocol::MD5, &engine_id(), &ku).unwrap();
+    assert_eq!(kul, key(MD5_KUL));
+}
+
+#[test]
+fn sha_vectors() {
+    snmp();
+    let ku = generate_ku(&V3AuthProtocol::SHA, PASSWORD.as_bytes()).unwrap();
+    assert_eq!(ku, key(SHA_KU));
+    let kul = generate_kul(&V3AuthProtocol::SHA, &engine_id(), &ku).unwrap();
+    assert_eq!(kul, key(SHA_KUL));
+}
+
+#[test]
+fn invalid_input() {
+    snmp();
+    assert!(generate_ku(&V3AuthProtocol::SHA, b"").is_err());
+}
+
+#[test]
+fn level_keys() {
+    snmp();
+    let level = auth_priv(
+        V3AuthProtocol::SHA,
+        V3Key::Password(SecretString::from(PASSWORD)),
+    );
+
+    let master = level.to_master_keys().unwrap();
+    for key in [keys(&master).0, keys(&master).1] {
+        assert!(matches!(key, V3Key::MasterKey(ku) if *ku == self::key(SHA_KU)));
+    }
+
+    /* From passwords and from master keys. */
+    for level in [&level, &master] {
+        let localized = level.localize(&engine_id()).unwrap();
+        for key in [keys(&localized).0, keys(&localized).1] {
+            assert!(matches!(key, V3Key::LocalizedKey(kul) if *kul == self::key(SHA_KUL)));
+        }
+    }
+
+    /* Localized keys are kept as is. */
+    let localized = auth_priv(V3AuthProtocol::MD5, V3Key::LocalizedKey(key(MD5_KUL)));
+    let level = localized
+        .to_master_keys()
+        .unwrap()
+        .localize(&engine_id())
+        .unwrap();
+    assert!(matches!(keys(&level).0, V3Key::LocalizedKey(kul) if *kul == key(MD5_KUL)));
+}
+
+#[test]
+fn keychange() {
+    snmp();
+    let new = generate_kul(
+        &V3AuthProtocol::SHA,
+        &engine_id(),
+        &generate_ku(&V3AuthProtocol::SHA, b"newpassword").unwrap(),
+    )
+    .unwrap();
+    let old = key(SHA_KUL);
+    let kc = encode_keychange(&V3AuthProtocol::SHA, &old, &new).unwrap();
+    assert_eq!(kc.len(), 2 * new.len());
+    assert_eq!(
+        decode_keychange(&V3AuthProtocol::SHA, &old, &kc).unwrap(),
+        new
+    );
+
+    /* Privacy keys are shorter than the hash. */
+    let old = Key::from_slice(&old.as_slice()[..16]);
+    let new = Key::from_slice(&new.as_slice()[..16]);
+    let kc = encode_keychange(&V3AuthProtocol::SHA, &old, &new).unwrap();
+    assert_eq!(
+        decode_keychange(&V3AuthProtocol::SHA, &old, &kc).unwrap(),
+        new
+    );
+
+    assert!(decode_keychange(&V3AuthProtocol::SHA, &old, &kc[1..]).is_err());
+}
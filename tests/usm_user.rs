@@ -0,0 +1,247 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+//! Create / add / drop cycles for USM users. These do not need an
+//! agent; run them under valgrind or a sanitizer to check that all
+//! buffers are owned by the C allocator, eg.:
+//!
+//!   valgrind --error-exitcode=1 target/debug/deps/usm_user-<hash>
+//!
+//! The fields are read back through the C struct while the user is
+//! alive; nothing is read after it is freed.
+
+use std::ffi::CStr;
+use std::slice;
+
+use netsnmp::{
+    api, generate_ku, generate_kul, EngineId, Key, SecretString, SessionInfo, Usm, UsmUser,
+    V3AuthParams, V3AuthProtocol, V3Key, V3Level, V3PrivParams, V3PrivProtocol, Version,
+};
+
+mod common;
+
+use common::snmp;
+
+fn engine_id() -> EngineId {
+    "0x80001f8880e9630000d61ff449".parse().unwrap()
+}
+
+/* usmHMACSHAAuthProtocol and usmAesCfb128Protocol. */
+const SHA: [u64; 10] = [1, 3, 6, 1, 6, 3, 10, 1, 1, 3];
+const AES: [u64; 10] = [1, 3, 6, 1, 6, 3, 10, 1, 2, 4];
+
+fn name(user: &UsmUser) -> String {
+    unsafe { CStr::from_ptr((*user.as_ptr()).name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn sec_name(user: &UsmUser) -> String {
+    unsafe { CStr::from_ptr((*user.as_ptr()).secName) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn engine_id_of(user: &UsmUser) -> Vec<u8> {
+    unsafe {
+        let user = &*user.as_ptr();
+        slice::from_raw_parts(user.engineID, user.engineIDLen).to_vec()
+    }
+}
+
+fn protocols(user: &UsmUser) -> (Vec<u64>, Vec<u64>) {
+    unsafe {
+        let user = &*user.as_ptr();
+        (
+            slice::from_raw_parts(user.authProtocol, user.authProtocolLen).to_vec(),
+            slice::from_raw_parts(user.privProtocol, user.privProtocolLen).to_vec(),
+        )
+    }
+}
+
+fn keys(user: &UsmUser) -> (Vec<u8>, Vec<u8>) {
+    unsafe {
+        let user = &*user.as_ptr();
+        (
+            slice::from_raw_parts(user.authKey, user.authKeyLen).to_vec(),
+            slice::from_raw_parts(user.privKey, user.privKeyLen).to_vec(),
+        )
+    }
+}
+
+fn auth_priv(key: V3Key) -> V3Level {
+    V3Level::AuthPriv {
+        auth: V3AuthParams {
+            protocol: V3AuthProtocol::SHA,
+            user: String::from("user"),
+            key: key.clone(),
+        },
+        privacy: V3PrivParams {
+            protocol: V3PrivProtocol::AES,
+            key,
+        },
+    }
+}
+
+#[test]
+fn create_and_drop() {
+    snmp();
+    let usm = Usm::init();
+    let user = usm
+        .create_user()
+        .set_name("user")
+        .unwrap()
+        .set_engine_id(&engine_id())
+        .unwrap()
+        .set_auth(&auth_priv(V3Key::Password(SecretString::from("password"))))
+        .unwrap();
+
+    let kul = generate_kul(
+        &V3AuthProtocol::SHA,
+        &engine_id(),
+        &generate_ku(&V3AuthProtocol::SHA, b"password").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(name(&user), "user");
+    assert_eq!(sec_name(&user), "user");
+    assert_eq!(engine_id_of(&user), engine_id().as_slice());
+    assert_eq!(protocols(&user), (SHA.to_vec(), AES.to_vec()));
+    assert_eq!(
+        keys(&user),
+        (kul.as_slice().to_vec(), kul.as_slice().to_vec())
+    );
+    drop(user);
+}
+
+#[test]
+fn replace_fields() {
+    snmp();
+    let usm = Usm::init();
+    let kul = Key::from_slice(&[0x55; 20]);
+    let user = usm
+        .create_user()
+        .set_name("user")
+        .unwrap()
+        .set_name("other")
+        .unwrap()
+        .set_sec_name("other")
+        .unwrap()
+        .set_engine_id(&engine_id())
+        .unwrap()
+        .set_engine_id(&engine_id())
+        .unwrap()
+        .set_auth(&auth_priv(V3Key::Password(SecretString::from("password"))))
+        .unwrap()
+        .set_auth(&auth_priv(V3Key::LocalizedKey(kul.clone())))
+        .unwrap();
+
+    /* The security name is set from the auth parameters. */
+    assert_eq!(name(&user), "other");
+    assert_eq!(sec_name(&user), "user");
+    assert_eq!(engine_id_of(&user), engine_id().as_slice());
+    assert_eq!(
+        keys(&user),
+        (kul.as_slice().to_vec(), kul.as_slice().to_vec())
+    );
+    drop(user);
+}
+
+/* Drop runs the same wipe right before the buffers are freed; they
+ * can not be read after that without a use after free. */
+#[test]
+fn wipe_keys() {
+    snmp();
+    let usm = Usm::init();
+    let mut user = usm
+        .create_user()
+        .set_name("user")
+        .unwrap()
+        .set_auth(&auth_priv(V3Key::LocalizedKey(Key::from_slice(
+            &[0x55; 20],
+        ))))
+        .unwrap();
+    assert_eq!(keys(&user), (vec![0x55; 20], vec![0x55; 20]));
+    user.wipe_keys();
+    assert_eq!(keys(&user), (vec![0; 20], vec![0; 20]));
+    drop(user);
+}
+
+#[test]
+fn missing_engine_id() {
+    snmp();
+    let usm = Usm::init();
+    let user = usm.create_user().set_name("user").unwrap();
+    assert!(user
+        .set_auth(&auth_priv(V3Key::Password(SecretString::from("password"))))
+        .is_err());
+
+    /* Localized keys do not need the engine id. */
+    let kul = Key::from_slice(&[0x55; 20]);
+    let user = usm.create_user().set_name("user").unwrap();
+    assert!(user.set_auth(&auth_priv(V3Key::LocalizedKey(kul))).is_ok());
+}
+
+#[test]
+fn invalid_name() {
+    snmp();
+    let usm = Usm::init();
+    assert!(usm.create_user().set_name("us\0er").is_err());
+}
+
+#[test]
+fn add_to_global_list() {
+    snmp();
+    let mut usm = Usm::init();
+    let user = usm
+        .create_user()
+        .set_name("global")
+        .unwrap()
+        .set_sec_name("global")
+        .unwrap()
+        .set_engine_id(&engine_id())
+        .unwrap()
+        .set_auth(&auth_priv(V3Key::MasterKey(Key::from_slice(&[0xaa; 20]))))
+        .unwrap();
+    usm.add_user(user).unwrap();
+}
+
+#[test]
+fn add_to_session_list() {
+    let usm: Usm<'static> = Usm::init();
+    let user = |name: &str| {
+        usm.create_user()
+            .set_name(name)
+            .unwrap()
+            .set_sec_name(name)
+            .unwrap()
+            .set_engine_id(&engine_id())
+            .unwrap()
+            .set_auth(&auth_priv(V3Key::Password(SecretString::from("password"))))
+            .unwrap()
+    };
+
+    let mut session = snmp()
+        .session()
+        .set_peer(b"udp:127.0.0.1:161")
+        .unwrap()
+        .set_version(Version::V3)
+        .set_async_probe(true)
+        .add_usm_user(user("first"))
+        .open_single()
+        .unwrap();
+    session.add_usm_user(user("second")).unwrap();
+
+    /* The session frees its user list on close. */
+    drop(session);
+}
@@ -0,0 +1,108 @@
+/****************************************************************************** 
+ * Copyright 2025 ContinuousC                                                 * 
+ *                                                                            * 
+ * Licensed under the Apache License,  Version 2.0  (the "License");  you may * 
+ * not use this file except in compliance with the License. You may  obtain a * 
+ * copy of the License at http://www.apache.org/licenses/LICENSE-2.0          * 
+ *                                                                            * 
+ * Unless  required  by  applicable  law  or agreed  to in  writing, software * 
+ * distributed under the License is distributed on an "AS IS"  BASIS, WITHOUT * 
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express  or implied.  See the * 
+ * License for the  specific language  governing permissions  and limitations * 
+ * under the License.                                                         * 
+ ******************************************************************************/
+
+//! VACM table indexes and view masks, using the examples of RFC 3415
+//! (appendix A).
+
+use netsnmp::{ContextMatch, Error, Oid, SecurityLevel, SecurityModel, VacmAccess, VacmView};
+
+mod common;
+
+use common::oid;
+
+fn octets(s: &str) -> Vec<u64> {
+    std::iter::once(s.len() as u64)
+        .chain(s.bytes().map(u64::from))
+        .collect()
+}
+
+fn view(subtree: &str, wildcards: Vec<usize>) -> VacmView {
+    VacmView {
+        name: String::from("restricted"),
+        subtree: oid(subtree),
+        wildcards,
+        included: true,
+    }
+}
+
+#[test]
+fn group_member_index() {
+    /* vacmSecurityModel USM, vacmSecurityName "initial" */
+    let index = SecurityModel::Usm.group_member_index("initial");
+    let expected: Vec<u64> = std::iter::once(3).chain(octets("initial")).collect();
+    assert_eq!(index.as_slice(), expected.as_slice());
+    assert_eq!(
+        SecurityModel::V2c.group_member_index("").as_slice(),
+        &[2, 0]
+    );
+}
+
+#[test]
+fn access_index() {
+    /* vacmGroupName "initial", vacmAccessContextPrefix "",
+     * vacmAccessSecurityModel USM, vacmAccessSecurityLevel noAuthNoPriv */
+    let access = VacmAccess {
+        group: String::from("initial"),
+        context_prefix: String::new(),
+        context_match: ContextMatch::Exact,
+        model: SecurityModel::Usm,
+        level: SecurityLevel::NoAuthNoPriv,
+        read_view: Some(String::from("restricted")),
+        write_view: None,
+        notify_view: Some(String::from("restricted")),
+    };
+    let expected: Vec<u64> = octets("initial")
+        .into_iter()
+        .chain(octets(""))
+        .chain([3, 1])
+        .collect();
+    assert_eq!(access.index().as_slice(), expected.as_slice());
+}
+
+#[test]
+fn view_index() {
+    /* vacmViewTreeFamilyViewName "restricted", subtree system */
+    let expected: Vec<u64> = octets("restricted")
+        .into_iter()
+        .chain([7, 1, 3, 6, 1, 2, 1, 1])
+        .collect();
+    assert_eq!(
+        view("1.3.6.1.2.1.1", vec![]).index().as_slice(),
+        expected.as_slice()
+    );
+}
+
+#[test]
+fn view_mask() {
+    /* No wildcards: an empty mask (all sub-identifiers match). */
+    assert_eq!(view("1.3.6.1.2.1.1", vec![]).mask().unwrap(), vec![]);
+
+    /* ifEntry.*.5: a wildcard for the column. */
+    let mask = view("1.3.6.1.2.1.2.2.1.1.5", vec![9]).mask().unwrap();
+    assert_eq!(mask, vec![0xff, 0xbf]);
+
+    let mask = view("1.3.6.1", vec![0, 3]).mask().unwrap();
+    assert_eq!(mask, vec![0x6f]);
+
+    assert!(view("1.3.6.1", vec![4]).mask().is_err());
+
+    /* The mask holds at most 16 octets. */
+    let mut long = view("1.3.6.1", vec![127]);
+    long.subtree = Oid::from_slice(&[1; 128]);
+    assert_eq!(long.mask().unwrap().len(), 16);
+    long.subtree = Oid::from_slice(&[1; 129]);
+    assert!(matches!(long.mask(), Err(Error::Vacm(_))));
+    long.wildcards.clear();
+    assert!(long.mask().is_err());
+}